@@ -1,11 +1,15 @@
+use std::path::PathBuf;
+use rspirv::{binary::Assemble, dr::{Builder, Operand}, spirv::{BuiltIn, Decoration, ExecutionMode, Op}};
 use spirv_builder::{MetadataPrintout, SpirvBuilder, Capability};
 
 const CRATE: &str = "gpu";
 const TARGET: &str = "spirv-unknown-vulkan1.1";
+/// Ids of the specialization constants holding the workgroup size (see `vulkan::pipeline::WORKGROUP_SIZE_IDS`)
+const WORKGROUP_SIZE_IDS: [u32; 3] = [1000, 1001, 1002];
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    SpirvBuilder::new(CRATE, TARGET)
-        .print_metadata(MetadataPrintout::Full)
+    let result = SpirvBuilder::new(CRATE, TARGET)
+        .print_metadata(MetadataPrintout::DependencyOnly)
         //.release(!cfg!(debug_assertions))
         .extension("SPV_KHR_variable_pointers")
         .extension("SPV_KHR_non_semantic_info")
@@ -13,5 +17,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .capability(Capability::Int16)
         .capability(Capability::VariablePointersStorageBuffer)
         .build()?;
+
+    let module = rspirv::dr::load_words(read_words(result.module.unwrap_single())?)
+        .map_err(|e| format!("failed to parse the shader module: {e:?}"))?;
+    let words = specialize_workgroup_size(Builder::new_from_module(module))?;
+
+    let path = PathBuf::from(std::env::var("OUT_DIR")?).join("gpu.spv");
+    std::fs::write(&path, words.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>())?;
+    println!("cargo:rustc-env=gpu.spv={}", path.display());
     Ok(())
-}
\ No newline at end of file
+}
+
+fn read_words(path: &std::path::Path) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    Ok(bytes.chunks_exact(4).map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]])).collect())
+}
+
+/// Declares the `WorkgroupSize` built-in as a composite of the specialization constants [`WORKGROUP_SIZE_IDS`],
+/// defaulting to the local size the entry points were compiled with, since `rust-gpu` can only emit literal sizes.
+fn specialize_workgroup_size(mut builder: Builder) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let module = builder.module_ref();
+    let mut local_sizes = module.execution_modes.iter()
+        .filter(|x| x.operands.get(1) == Some(&Operand::ExecutionMode(ExecutionMode::LocalSize)))
+        .map(|x| x.operands[2..5].iter().map(Operand::unwrap_literal_int32).collect::<Vec<_>>());
+
+    let local_size = local_sizes.next().ok_or("the shader module has no compute entry point")?;
+    if local_sizes.any(|x| x != local_size) {
+        return Err("the compute entry points must share the same local size to specialize it".into());
+    }
+
+    let built_in = Operand::BuiltIn(BuiltIn::WorkgroupSize);
+    if module.annotations.iter().any(|x| x.class.opcode == Op::Decorate && x.operands.get(2) == Some(&built_in)) {
+        return Err("the shader module already declares a workgroup size".into());
+    }
+
+    let uint = builder.type_int(32, 0);
+    let uvec3 = builder.type_vector(uint, 3);
+    let constants = local_size.into_iter()
+        .zip(WORKGROUP_SIZE_IDS)
+        .map(|(size, spec_id)| {
+            let id = builder.spec_constant_u32(uint, size);
+            builder.decorate(id, Decoration::SpecId, [Operand::LiteralInt32(spec_id)]);
+            id
+        })
+        .collect::<Vec<_>>();
+
+    let size = builder.spec_constant_composite(uvec3, constants);
+    builder.decorate(size, Decoration::BuiltIn, [built_in]);
+    Ok(builder.module().assemble())
+}
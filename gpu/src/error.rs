@@ -0,0 +1,40 @@
+use shared::error::{ErrorBuffer, ErrorRecord, MAX_ERRORS};
+use spirv_std::{
+    arch::atomic_i_add,
+    glam::UVec3,
+    memory::{Scope, Semantics},
+};
+
+/// Asserts that `cond` holds, otherwise reporting `code` into `errors` & returning from the kernel.
+///
+/// ```ignore
+/// gpu_assert!(errors, entry::GENERATE_PEOPLE, id, (id.x as usize) < people.len(), code::OUT_OF_BOUNDS);
+/// ```
+#[macro_export]
+macro_rules! gpu_assert {
+    ($errors:expr, $entry:expr, $id:expr, $cond:expr, $code:expr) => {
+        if !$cond {
+            $crate::error::report($errors, $entry, $id, $code);
+            return;
+        }
+    };
+}
+
+/// Appends an error to `errors`. If it's already full, the error is only counted.
+#[inline]
+pub fn report(errors: &mut ErrorBuffer, entry: u32, id: UVec3, code: u32) {
+    let idx = unsafe {
+        atomic_i_add::<u32, { Scope::Device as u32 }, { Semantics::NONE.bits() }>(
+            &mut errors.count,
+            1,
+        )
+    } as usize;
+
+    if idx < MAX_ERRORS {
+        errors.records[idx] = ErrorRecord {
+            code,
+            entry,
+            invocation: [id.x, id.y, id.z],
+        };
+    }
+}
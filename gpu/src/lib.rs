@@ -7,23 +7,36 @@
 // HACK(eddyb) can't easily see warnings otherwise from `spirv-builder` builds.
 #![deny(warnings)]
 
+pub mod error;
 pub mod math;
 pub mod rand;
 
 use crate::rand::{Random2, Random3};
-use shared::{person::{Person, PersonStats}, person_event::PersonalEvent, time::GameDuration, ExternBool};
-use spirv_std::{glam::UVec3, macros::debug_printfln, spirv};
+use shared::{
+    dispatch::DispatchSize,
+    error::{code, entry, ErrorBuffer},
+    person::{GeneratePeopleConstants, Person, PersonStats},
+    person_event::{PersonalEvent, PersonalEventConstants},
+    time::GameDuration,
+    ExternBool,
+};
+use spirv_std::{
+    arch::atomic_i_add,
+    glam::UVec3,
+    macros::debug_printfln,
+    memory::{Scope, Semantics},
+    spirv,
+};
 
-// Regular odds (1f32 chance) will result in true once every 100 ticks (approximately, obviously)
-//const BASE_CHANCE: f32 = 1f32 / 100f32;
-const BASE_CHANCE: f32 = 1f32;
-
-// x = # of people
+// x = # of people, rounded up to the workgroup size
+// Binding 0 is reserved for the error buffer (see `shared::error::ERROR_BINDING`)
+// The workgroup size is set by the host, through the specialization constants `build.rs` declares it with
 #[spirv(compute(threads(1)))]
 pub fn generate_people(
     #[spirv(global_invocation_id)] id: UVec3,
-    #[spirv(push_constant)] seed: &f32,
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] people: &mut [Person],
+    #[spirv(push_constant)] constants: &GeneratePeopleConstants,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] errors: &mut ErrorBuffer,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] people: &mut [Person],
 ) {
     #[inline]
     fn random(seed: &mut f32, x: f32) -> f32 {
@@ -40,7 +53,13 @@ pub fn generate_people(
         return v;
     }
 
-    let mut seed = *seed;
+    // Invocations past the end of the last workgroup have nobody to generate
+    if id.x >= constants.people {
+        return;
+    }
+
+    gpu_assert!(errors, entry::GENERATE_PEOPLE, id, (id.x as usize) < people.len(), code::OUT_OF_BOUNDS);
+    let mut seed = constants.seed;
     let x = id.x as f32;
 
     people[id.x as usize] = Person {
@@ -60,22 +79,107 @@ pub fn generate_people(
     }
 }
 
-// x = # of people, y = # of events
+// x = # of people, y = # of events (offset by the push constants, when the dispatch is split into regions),
+// both rounded up to the workgroup size, which is set by the host
+// Binding 0 is reserved for the error buffer (see `shared::error::ERROR_BINDING`)
+// Specialization constants (only `u32` is supported, so floats are passed by their bits & booleans as 0 or 1):
+//  - 0: base chance of every event (defaults to `1f32`)
+//  - 1: whether to print the chance of every event (defaults to false)
 #[spirv(compute(threads(1, 1)))]
 pub fn compute_personal_event(
     #[spirv(global_invocation_id)] id: UVec3,
-    #[spirv(push_constant)] seed: &f32,
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] people: &[Person],
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] events: &[PersonalEvent],
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] results: &mut [ExternBool], // [_; x * y]
+    #[spirv(spec_constant(id = 0, default = 1065353216))] base_chance: u32,
+    #[spirv(spec_constant(id = 1, default = 0))] print_chances: u32,
+    #[spirv(push_constant)] constants: &PersonalEventConstants,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] errors: &mut ErrorBuffer,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] people: &[Person],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] events: &[PersonalEvent],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] results: &mut [ExternBool], // [_; x * y]
 ) {
+    const ENTRY: u32 = entry::COMPUTE_PERSONAL_EVENT;
+    let id = id + UVec3::new(constants.first_person, constants.first_event, 0);
+    // Invocations past the end of the last workgroups have nothing to compute
+    if id.x >= constants.people || id.y >= constants.events {
+        return;
+    }
+
+    gpu_assert!(errors, ENTRY, id, (id.x as usize) < people.len(), code::OUT_OF_BOUNDS);
+    gpu_assert!(errors, ENTRY, id, (id.y as usize) < events.len(), code::OUT_OF_BOUNDS);
+
     let person = &people[id.x as usize];
     let event = &events[id.y as usize];
-    let chance = BASE_CHANCE * event.calculate_chance(*person);
-    unsafe { debug_printfln!("%f", chance) }
+    let chance = f32::from_bits(base_chance) * event.calculate_chance(*person);
+    if print_chances != 0 {
+        unsafe { debug_printfln!("%f", chance) }
+    }
 
-    if Random3::generate(id.x as f32, id.y as f32, *seed) < chance {
+    if Random3::generate(id.x as f32, id.y as f32, constants.seed) < chance {
         let idx = (id.x as usize) * events.len() + (id.y as usize);
+        gpu_assert!(errors, ENTRY, id, idx < results.len(), code::OUT_OF_BOUNDS);
         results[idx].set()
     }
 }
+
+// x = # of people
+// Appends the people hit by at least one event to `hits`, counting them in `dispatch.x`,
+// which must be reset to 0 beforehand. `dispatch` then sizes the dispatch of `apply_personal_effects`.
+// Specialization constants:
+//  - 2: number of events (defaults to 1)
+#[spirv(compute(threads(1)))]
+pub fn collect_hits(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(spec_constant(id = 2, default = 1))] event_count: u32,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] errors: &mut ErrorBuffer,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] results: &[ExternBool], // [_; x * event_count]
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] dispatch: &mut DispatchSize,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] hits: &mut [u32],
+) {
+    const ENTRY: u32 = entry::COLLECT_HITS;
+    let first = (id.x as usize) * (event_count as usize);
+    gpu_assert!(errors, ENTRY, id, first + (event_count as usize) <= results.len(), code::OUT_OF_BOUNDS);
+
+    let mut i = 0;
+    while i < event_count as usize {
+        if results[first + i].get() {
+            let idx = unsafe {
+                atomic_i_add::<u32, { Scope::Device as u32 }, { Semantics::NONE.bits() }>(
+                    &mut dispatch.x,
+                    1,
+                )
+            } as usize;
+
+            gpu_assert!(errors, ENTRY, id, idx < hits.len(), code::OUT_OF_BOUNDS);
+            hits[idx] = id.x;
+            return;
+        }
+        i += 1;
+    }
+}
+
+// x = # of hits (dispatched indirectly, from the size written by `collect_hits`)
+// Applies the effects of every event that hit the person
+#[spirv(compute(threads(1)))]
+pub fn apply_personal_effects(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] errors: &mut ErrorBuffer,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] people: &mut [Person],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] events: &[PersonalEvent],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] results: &[ExternBool], // [_; people * events]
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] hits: &[u32],
+) {
+    const ENTRY: u32 = entry::APPLY_PERSONAL_EFFECTS;
+    gpu_assert!(errors, ENTRY, id, (id.x as usize) < hits.len(), code::OUT_OF_BOUNDS);
+
+    let person = hits[id.x as usize] as usize;
+    let first = person * events.len();
+    gpu_assert!(errors, ENTRY, id, person < people.len(), code::OUT_OF_BOUNDS);
+    gpu_assert!(errors, ENTRY, id, first + events.len() <= results.len(), code::OUT_OF_BOUNDS);
+
+    let mut i = 0;
+    while i < events.len() {
+        if results[first + i].get() {
+            people[person].stats.apply(events[i].effects);
+        }
+        i += 1;
+    }
+}
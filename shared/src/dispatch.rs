@@ -0,0 +1,10 @@
+/// Workgroup counts of an indirect dispatch, laid out like `vulkan::pool::DispatchIndirectCommand`,
+/// so a kernel can size the dispatch of the next one without a round trip to the host
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct DispatchSize {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
@@ -0,0 +1,88 @@
+/// Binding (in descriptor set 0) reserved for the error buffer of every kernel
+pub const ERROR_BINDING: u32 = 0;
+/// Maximum number of errors recorded per submission. Errors past this limit are only counted.
+pub const MAX_ERRORS: usize = 32;
+
+/// Error codes reported by kernels
+pub mod code {
+    /// An index was out of the bounds of its buffer
+    pub const OUT_OF_BOUNDS: u32 = 1;
+    /// A value was in an invalid state
+    pub const INVALID_STATE: u32 = 2;
+
+    #[inline]
+    pub const fn name(code: u32) -> Option<&'static str> {
+        return match code {
+            OUT_OF_BOUNDS => Some("out of bounds"),
+            INVALID_STATE => Some("invalid state"),
+            _ => None,
+        };
+    }
+}
+
+/// Identifiers of the kernels' entry points, since strings can't be written from the GPU
+pub mod entry {
+    pub const GENERATE_PEOPLE: u32 = 1;
+    pub const COMPUTE_PERSONAL_EVENT: u32 = 2;
+    pub const COLLECT_HITS: u32 = 3;
+    pub const APPLY_PERSONAL_EFFECTS: u32 = 4;
+
+    #[inline]
+    pub const fn name(entry: u32) -> Option<&'static str> {
+        return match entry {
+            GENERATE_PEOPLE => Some("generate_people"),
+            COMPUTE_PERSONAL_EVENT => Some("compute_personal_event"),
+            COLLECT_HITS => Some("collect_hits"),
+            APPLY_PERSONAL_EFFECTS => Some("apply_personal_effects"),
+            _ => None,
+        };
+    }
+}
+
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub struct ErrorRecord {
+    pub code: u32,
+    pub entry: u32,
+    /// Global invocation id of the invocation that reported the error
+    pub invocation: [u32; 3],
+}
+
+/// Errors reported by kernels, appended through an atomic counter
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct ErrorBuffer {
+    /// Number of errors reported, which may be larger than [`MAX_ERRORS`]
+    pub count: u32,
+    pub records: [ErrorRecord; MAX_ERRORS],
+}
+
+impl ErrorBuffer {
+    #[inline]
+    pub const fn new() -> Self {
+        return Self {
+            count: 0,
+            records: [ErrorRecord {
+                code: 0,
+                entry: 0,
+                invocation: [0; 3],
+            }; MAX_ERRORS],
+        };
+    }
+
+    /// Records that were written, ignoring the ones that didn't fit
+    #[inline]
+    pub fn records(&self) -> &[ErrorRecord] {
+        let len = if (self.count as usize) < MAX_ERRORS { self.count as usize } else { MAX_ERRORS };
+        return &self.records[..len];
+    }
+}
+
+impl Default for ErrorBuffer {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,8 @@
 #![cfg_attr(target_arch = "spirv", no_std, feature(asm_experimental_arch))]
 #![feature(portable_simd)]
 
+pub mod dispatch;
+pub mod error;
 pub mod time;
 pub mod person;
 pub mod person_event;
@@ -23,6 +23,24 @@ impl<T> PersonStats<T> {
     }
 }
 
+impl PersonStats<u8> {
+    /// Adds `effects` to the stats, saturating at their bounds
+    #[inline]
+    pub fn apply (&mut self, effects: PersonStats<i8>) {
+        #[inline(always)]
+        fn add (stat: &mut u8, effect: i8) {
+            *stat = (*stat as i32 + effect as i32).clamp(0, u8::MAX as i32) as u8;
+        }
+
+        add(&mut self.cordiality, effects.cordiality);
+        add(&mut self.intelligence, effects.intelligence);
+        add(&mut self.knowledge, effects.knowledge);
+        add(&mut self.finesse, effects.finesse);
+        add(&mut self.gullability, effects.gullability);
+        add(&mut self.health, effects.health);
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
 #[repr(C)]
@@ -30,4 +48,14 @@ pub struct Person {
     pub is_male: ExternBool,
     pub age: GameDuration, // in weeks
     pub stats: PersonStats<u8>
-}
\ No newline at end of file
+}
+
+/// Push constants of `generate_people`.
+/// Invocations past `people` only pad the last workgroup, and do nothing.
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct GeneratePeopleConstants {
+    pub seed: f32,
+    pub people: u32,
+}
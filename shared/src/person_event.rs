@@ -16,6 +16,20 @@ pub struct PersonalEvent {
     pub effects: PersonStats<i8>,
 }
 
+/// Push constants of `compute_personal_event`.
+/// The offsets are added to the invocation id, so the people & events can be split into regions dispatched separately.
+/// Invocations past `people` or `events` only pad the last workgroups, and do nothing.
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct PersonalEventConstants {
+    pub seed: f32,
+    pub first_person: u32,
+    pub first_event: u32,
+    pub people: u32,
+    pub events: u32,
+}
+
 impl PersonalEvent {
     #[inline]
     pub fn calculate_chance(self, person: Person) -> f32 {
@@ -1,23 +1,153 @@
-use vulkan::{*, device::{DeviceRef, Device}, pool::{CommandPool, CommandPoolFlags, CommandBufferLevel}, queue::Queue};
+use vulkan::{*, device::{DeviceRef, Device}, pool::{Command, CommandPool, CommandPoolFlags, CommandBufferLevel, CommandBufferUsage}, queue::Queue, physical_dev::Family, pipeline::PipelineStages, sync::{Fence, FenceFlags, Semaphore}};
+
+/// Number of ticks that can be recorded while the previous ones execute
+pub const FRAMES_IN_FLIGHT: u32 = 2;
 
 pub struct Context<D: DeviceRef> {
+    /// Holds one command buffer per frame in flight
+    pub pool: CommandPool<D>,
+    pub queue: Queue,
+    /// Signaled once the submission of the matching frame completes
+    fences: Box<[Fence<D>]>,
+    frame: u32,
+    /// Dedicated transfer queue, if the device has a separate transfer family
+    pub transfer: Option<TransferQueue<D>>
+}
+
+/// Index of the upload command buffer in [`TransferQueue::pool`]
+pub const UPLOAD_BUFFER: u32 = 0;
+/// Index of the readback command buffer in [`TransferQueue::pool`]
+pub const READBACK_BUFFER: u32 = 1;
+
+/// Queue of a separate transfer family, used to overlap uploads & readbacks with compute work
+pub struct TransferQueue<D: DeviceRef> {
+    /// Holds the command buffers at [`UPLOAD_BUFFER`] & [`READBACK_BUFFER`]
     pub pool: CommandPool<D>,
-    pub queue: Queue
+    pub queue: Queue,
+    /// Signaled by uploads, for compute submissions to wait on
+    pub semaphore: Semaphore<D>,
+    /// Signaled by compute submissions, for readbacks to wait on
+    pub compute_semaphore: Semaphore<D>,
+    /// Signaled once the readback completes
+    pub fence: Fence<D>
 }
 
 impl<D: DeviceRef> Context<D> {
     #[inline]
-    pub fn new (device: D, queue: Queue) -> Result<Self> {
-        let family = device.physical().families().next().unwrap();
+    pub fn new (device: D, queue: Queue) -> Result<Self> where D: Clone {
+        return Self::with_frames(device, queue, FRAMES_IN_FLIGHT)
+    }
+
+    /// Creates a context with `frames` command buffers & fences, recycled in order
+    pub fn with_frames (device: D, queue: Queue, frames: u32) -> Result<Self> where D: Clone {
+        debug_assert!(frames > 0);
         let pool = CommandPool::new(
-            device,
-            family,
-            CommandPoolFlags::empty(),
-            1,
+            device.clone(),
+            queue.family(),
+            CommandPoolFlags::RESET_COMMAND_BUFFER,
+            frames,
             CommandBufferLevel::Primary,
         )?;
 
-        return Ok(Self { pool, queue })
+        // Fences start signaled, since their frames have nothing to wait for yet
+        let fences = (0..frames)
+            .map(|_| Fence::new(device.clone(), FenceFlags::SIGNALED))
+            .collect::<Result<Box<[_]>>>()?;
+
+        return Ok(Self { pool, queue, fences, frame: 0, transfer: None })
+    }
+
+    /// Creates a context that runs transfers on `transfer`, if provided and from a different family than `queue`.
+    /// Otherwise, transfers fall back to `queue`.
+    #[inline]
+    pub fn with_transfer (device: D, queue: Queue, transfer: Option<Queue>) -> Result<Self> where D: Clone {
+        let mut this = Self::new(device.clone(), queue)?;
+
+        if let Some(queue) = transfer.filter(|x| x.family() != this.queue.family()) {
+            let pool = CommandPool::new(
+                device.clone(),
+                queue.family(),
+                CommandPoolFlags::empty(),
+                2,
+                CommandBufferLevel::Primary,
+            )?;
+
+            let semaphore = Semaphore::new(device.clone())?;
+            let compute_semaphore = Semaphore::new(device.clone())?;
+            let fence = Fence::new(device, FenceFlags::empty())?;
+            this.transfer = Some(TransferQueue { pool, queue, semaphore, compute_semaphore, fence });
+        }
+
+        return Ok(this)
+    }
+
+    /// Number of frames that can be in flight at once
+    #[inline]
+    pub fn frames (&self) -> u32 {
+        return self.pool.len()
+    }
+
+    /// Index of the frame recorded by the next [`begin_frame`](Self::begin_frame)
+    #[inline]
+    pub fn frame (&self) -> u32 {
+        return self.frame
+    }
+
+    /// Waits for the previous submission of the current frame to complete, then begins recording its command buffer
+    pub async fn begin_frame<P: DeviceRef> (&mut self, usage: CommandBufferUsage) -> Result<Command<'_, P>> {
+        self.fences[self.frame as usize].wait_async().await?;
+        return self.pool.begin_mut(self.frame, usage)
+    }
+
+    /// Submits the command buffer of the current frame, advancing to the next one without waiting for the submission to complete.
+    /// If `wait_transfer` is set, the compute shader stage waits for the transfer queue's semaphore to be signaled.
+    /// If `signal_transfer` is set, the transfer queue's compute semaphore is signaled once the frame completes.
+    /// Both are ignored without a dedicated transfer queue.
+    pub fn submit_frame (&mut self, wait_transfer: bool, signal_transfer: bool) -> Result<()> where D: Clone {
+        let frame = self.frame as usize;
+        let fence = &mut self.fences[frame];
+        fence.reset()?;
+
+        let mut submitter = self.queue
+            .submitter::<D, D, D>(Some(fence))
+            .add(&self.pool, frame..frame + 1);
+
+        if let Some(transfer) = self.transfer.as_ref().filter(|_| wait_transfer) {
+            submitter = submitter.wait(&transfer.semaphore, PipelineStages::COMPUTE_SHADER);
+        }
+
+        if let Some(transfer) = self.transfer.as_ref().filter(|_| signal_transfer) {
+            submitter = submitter.signal(&transfer.compute_semaphore);
+        }
+
+        if let Err(e) = submitter.submit() {
+            // The fence was reset but will never be signaled, so it's replaced by a signaled one for the frame to stay usable
+            self.fences[frame] = Fence::new(self.pool.owned_device(), FenceFlags::SIGNALED)?;
+            return Err(e)
+        }
+
+        self.frame = (self.frame + 1) % self.frames();
+        return Ok(())
+    }
+
+    /// Waits for the submissions of every frame to complete.
+    /// A frame's fence only covers its own submission, so this is needed to wait for everything submitted so far.
+    pub async fn wait_all (&self) -> Result<()> {
+        for fence in self.fences.iter() {
+            fence.wait_async().await?;
+        }
+        return Ok(())
+    }
+
+    #[inline]
+    pub fn compute_family (&self) -> Family {
+        return self.queue.family()
+    }
+
+    /// Family transfers run on, which is the compute family if there isn't a dedicated transfer queue
+    #[inline]
+    pub fn transfer_family (&self) -> Family {
+        return self.transfer.as_ref().map_or(self.queue.family(), |x| x.queue.family())
     }
 
     #[inline]
@@ -29,4 +159,4 @@ impl<D: DeviceRef> Context<D> {
     pub fn owned_device (&self) -> D where D: Clone {
         return self.pool.owned_device()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,96 @@
+use shared::error::{code, entry, ErrorBuffer, ErrorRecord};
+use vulkan::{
+    alloc::{DeviceAllocator, MemoryUsage},
+    barrier::{AccessFlags, BufferMemoryBarrier},
+    buffer::{Buffer, BufferFlags, UsageFlags},
+    error::Error,
+    Result,
+};
+
+/// Host side of the error buffer kernels report their `gpu_assert!` failures into
+pub struct GpuErrors<A: DeviceAllocator> {
+    buffer: Buffer<ErrorBuffer, A>,
+}
+
+impl<A: DeviceAllocator> GpuErrors<A> {
+    #[inline]
+    pub fn new(alloc: A) -> Result<Self> {
+        let buffer = Buffer::from_sized_iter(
+            [ErrorBuffer::new()],
+            UsageFlags::STORAGE_BUFFER,
+            BufferFlags::empty(),
+            MemoryUsage::readback(),
+            alloc,
+        )?;
+
+        return Ok(Self { buffer });
+    }
+
+    /// Buffer to be bound at `shared::error::ERROR_BINDING`
+    #[inline]
+    pub fn buffer(&self) -> &Buffer<ErrorBuffer, A> {
+        return &self.buffer;
+    }
+
+    /// Barrier ordering the errors written by kernels before those of the next kernels,
+    /// so that they don't race on the error count
+    #[inline]
+    pub fn kernel_barrier(&self) -> BufferMemoryBarrier<'_> {
+        return BufferMemoryBarrier::new(
+            &self.buffer,
+            AccessFlags::SHADER_WRITE,
+            AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+        );
+    }
+
+    /// Barrier making the errors written by kernels visible to the host
+    #[inline]
+    pub fn barrier(&self) -> BufferMemoryBarrier<'_> {
+        return BufferMemoryBarrier::new(&self.buffer, AccessFlags::SHADER_WRITE, AccessFlags::HOST_READ);
+    }
+
+    /// Takes the errors reported since the last call (up to `shared::error::MAX_ERRORS`), resetting the buffer.
+    /// Must only be called once the submissions using the buffer have completed.
+    pub fn take(&mut self) -> Result<Vec<Error>> {
+        let mut map = self.buffer.map_mut(..)?;
+        let errors = &mut map[0];
+
+        let result = errors.records().iter().map(to_error).collect::<Vec<_>>();
+        *errors = ErrorBuffer::new();
+        return Ok(result);
+    }
+
+    /// Returns the first error reported since the last call, if any, resetting the buffer.
+    /// The other errors are logged, since only one can be returned.
+    /// Must only be called once the submissions using the buffer have completed.
+    pub fn check(&mut self) -> Result<()> {
+        let mut errors = self.take()?.into_iter();
+        let first = match errors.next() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        for e in errors {
+            log::error!(target: "society_sim::gpu", "{e}");
+        }
+        return Err(first);
+    }
+}
+
+fn to_error(record: &ErrorRecord) -> Error {
+    let entry = match entry::name(record.entry) {
+        Some(name) => name.to_string(),
+        None => format!("<unknown entry {}>", record.entry),
+    };
+
+    let message = match code::name(record.code) {
+        Some(name) => name.to_string(),
+        None => format!("error code {}", record.code),
+    };
+
+    return Error::Shader {
+        entry,
+        invocation: record.invocation,
+        message,
+    };
+}
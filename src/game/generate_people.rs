@@ -1,15 +1,15 @@
 use std::mem::MaybeUninit;
 use rand::{distributions::OpenClosed01, thread_rng, Rng};
-use shared::{person::Person};
+use shared::{error::ErrorBuffer, person::{GeneratePeopleConstants, Person}};
 use vulkan::{
-    alloc::{DeviceAllocator, MemoryFlags},
-    buffer::{Buffer, UsageFlags, BufferFlags},
+    alloc::DeviceAllocator,
+    buffer::Buffer,
     device::DeviceRef,
-    pipeline::{ComputeBuilder, Pipeline},
-    pool::{CommandPool, CommandBufferUsage, PipelineBindPoint},
-    Result, descriptor::{DescriptorSet, DescriptorType}, utils::u64_to_u32, queue::{Queue}, shader::ShaderStages, cstr, sync::{Fence, FenceFlags},
+    pipeline::{ComputeBuilder, Pipeline, PipelineCache},
+    library::ShaderLibrary,
+    pool::{Command, PipelineBindPoint},
+    Result, descriptor::{DescriptorSet, DescriptorType}, utils::u64_to_u32, shader::ShaderStages, cstr,
 };
-use crate::context::Context;
 
 pub struct GeneratePeople<D: DeviceRef> {
     pipeline: Pipeline<D>,
@@ -18,46 +18,43 @@ pub struct GeneratePeople<D: DeviceRef> {
 
 impl<D: DeviceRef> GeneratePeople<D> {
     #[inline]
-    pub fn new (dev: D, words: &[u32]) -> Result<Self> where D: Clone {
-        let pipeline = ComputeBuilder::new(dev)
-            .entry(cstr!("generate_people"))
+    pub fn builder<'a, C: DeviceRef> (library: &ShaderLibrary<D>, cache: &'a PipelineCache<C>) -> ComputeBuilder<'a, D> where D: Clone {
+        return library.compute(cstr!("generate_people"))
+            .cache(cache)
+            .push_constant::<GeneratePeopleConstants>()
+            .binding(DescriptorType::StorageBuffer, 1) // errors
             .binding(DescriptorType::StorageBuffer, 1)
-            .build(words)?;
-
-        return Ok(Self {
-            pipeline,
-            seed: 100f32 * thread_rng().sample::<f32, _>(OpenClosed01),
-        });
     }
 
     #[inline]
-    pub fn generate<Ctx: DeviceRef, A: DeviceAllocator> (&mut self, len: u64,  usage: UsageFlags, flags: BufferFlags, memory_flags: MemoryFlags, alloc: A, ctx: &mut Context<Ctx>) -> Result<Buffer<Person, A>> where D: Clone {
-        let people = Buffer::new_uninit(len, usage, flags, memory_flags, alloc)?;
-        return self.call(people, &mut ctx.pool, &mut ctx.queue)
+    pub fn new (pipeline: Pipeline<D>) -> Self {
+        return Self {
+            pipeline,
+            seed: 100f32 * thread_rng().sample::<f32, _>(OpenClosed01),
+        };
     }
 
+    /// Records the generation of `people` into `cmd_buff`, reporting failed assertions into `errors`.
+    /// Subsequent reads of `people` must be preceded by a barrier on the compute shader stage.
     #[inline]
-    pub fn call<Pool: DeviceRef, P: DeviceAllocator>(
-        &mut self,
-        people: Buffer<MaybeUninit<Person>, P>,
-        pool: &mut CommandPool<Pool>,
-        queue: &mut Queue,
-    ) -> Result<Buffer<Person, P>> {
+    pub fn record<'a, P: DeviceAllocator, Err: DeviceAllocator>(
+        &'a mut self,
+        cmd_buff: &mut Command<'a, D>,
+        errors: &Buffer<ErrorBuffer, Err>,
+        people: &Buffer<MaybeUninit<Person>, P>,
+    ) -> Result<()> {
         let set: &DescriptorSet = self.pipeline.sets().first().unwrap();
-        let people_desc = set.write_descriptor(&people, 0);
-        self.pipeline.sets_mut().update(&[people_desc]);
+        let errors_desc = set.write_descriptor(errors, 0);
+        let people_desc = set.write_descriptor(people, 0);
+        self.pipeline.sets_mut().update(&[errors_desc, people_desc]);
 
-        let mut cmd_buff = pool.begin_mut(0, CommandBufferUsage::ONE_TIME_SUBMIT)?;
+        // The pipeline stays borrowed by `cmd_buff`, so the next seed is picked beforehand
+        let seed = core::mem::replace(&mut self.seed, 100f32 * thread_rng().sample::<f32, _>(OpenClosed01));
         cmd_buff.bind_pipeline(PipelineBindPoint::Compute, &self.pipeline, ..);
-        cmd_buff.push_contant(&self.seed, ShaderStages::COMPUTE)?;
-        cmd_buff.dispatch(u64_to_u32(people.len()), 1, 1);
-        drop(cmd_buff);
-
-        let mut fence = Fence::new(self.pipeline.device(), FenceFlags::empty())?;
-        fence.bind_to::<_, Pool>(pool, queue, None)?;
-        fence.wait(None)?;
-
-        self.seed = 100f32 * thread_rng().sample::<f32, _>(OpenClosed01);
-        return unsafe { Ok(people.assume_init()) };
+        let people = u64_to_u32(people.len());
+        cmd_buff.push_contant(&GeneratePeopleConstants { seed, people }, ShaderStages::COMPUTE)?;
+        let [x, y, z] = self.pipeline.workgroup_count([people, 1, 1]);
+        cmd_buff.dispatch(x, y, z);
+        return Ok(())
     }
 }
@@ -1,2 +1,3 @@
 pub mod personal_events;
+pub mod personal_effects;
 pub mod generate_people;
\ No newline at end of file
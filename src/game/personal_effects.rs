@@ -0,0 +1,135 @@
+use std::mem::MaybeUninit;
+use shared::{error::ErrorBuffer, person::Person, person_event::PersonalEvent, ExternBool};
+use vulkan::{
+    alloc::{DeviceAllocator, MemoryUsage},
+    buffer::{Buffer, UsageFlags, BufferFlags},
+    device::DeviceRef,
+    pipeline::{ComputeBuilder, Pipeline, PipelineCache, PipelineStages},
+    library::ShaderLibrary,
+    pool::{Command, DispatchIndirectCommand, PipelineBindPoint},
+    barrier::{AccessFlags, BufferMemoryBarrier},
+    Result, descriptor::{DescriptorSet, DescriptorType}, utils::u64_to_u32, cstr,
+};
+
+/// Specialization constant holding the number of events of `collect_hits`
+const EVENT_COUNT_ID: u32 = 2;
+
+/// Applies the effects of the personal events that hit each person.
+///
+/// `collect_hits` gathers the people hit by at least one event and writes the size of the dispatch of
+/// `apply_personal_effects`, so only they are processed, without waiting for the host.
+pub struct PersonalEffects<D: DeviceRef, A: DeviceAllocator> {
+    collect: Pipeline<D>,
+    apply: Pipeline<D>,
+    /// Workgroup counts of `apply`, written by `collect`
+    dispatch: Buffer<DispatchIndirectCommand, A>,
+    /// Indices of the people hit by an event, written by `collect`
+    hits: Buffer<MaybeUninit<u32>, A>,
+}
+
+impl<D: DeviceRef, A: DeviceAllocator> PersonalEffects<D, A> {
+    /// Builds the kernel collecting the hits of `event_count` events, which must be the length of the events given to [`record`](Self::record)
+    #[inline]
+    pub fn collect_builder<'a, C: DeviceRef> (library: &ShaderLibrary<D>, cache: &'a PipelineCache<C>, event_count: u32) -> ComputeBuilder<'a, D> where D: Clone {
+        return library.compute(cstr!("collect_hits"))
+            .cache(cache)
+            .constant(EVENT_COUNT_ID, event_count)
+            .binding(DescriptorType::StorageBuffer, 1) // errors
+            .binding(DescriptorType::StorageBuffer, 1)
+            .binding(DescriptorType::StorageBuffer, 1)
+            .binding(DescriptorType::StorageBuffer, 1)
+    }
+
+    #[inline]
+    pub fn apply_builder<'a, C: DeviceRef> (library: &ShaderLibrary<D>, cache: &'a PipelineCache<C>) -> ComputeBuilder<'a, D> where D: Clone {
+        return library.compute(cstr!("apply_personal_effects"))
+            .cache(cache)
+            .binding(DescriptorType::StorageBuffer, 1) // errors
+            .binding(DescriptorType::StorageBuffer, 1)
+            .binding(DescriptorType::StorageBuffer, 1)
+            .binding(DescriptorType::StorageBuffer, 1)
+            .binding(DescriptorType::StorageBuffer, 1)
+    }
+
+    /// Creates the kernels, able to process up to `capacity` people
+    pub fn new (collect: Pipeline<D>, apply: Pipeline<D>, capacity: u64, alloc: A) -> Result<Self> where A: Clone {
+        let dispatch = Buffer::<DispatchIndirectCommand, _>::new_uninit(
+            1,
+            UsageFlags::STORAGE_BUFFER | UsageFlags::INDIRECT_BUFFER | UsageFlags::TRANSFER_DST,
+            BufferFlags::empty(),
+            MemoryUsage::device_local(),
+            alloc.clone(),
+        )?;
+
+        let hits = Buffer::<u32, _>::new_uninit(
+            capacity,
+            UsageFlags::STORAGE_BUFFER,
+            BufferFlags::empty(),
+            MemoryUsage::device_local(),
+            alloc,
+        )?;
+
+        // The dispatch size is reset by `record` before every use
+        let dispatch = unsafe { dispatch.assume_init() };
+        return Ok(Self { collect, apply, dispatch, hits })
+    }
+
+    /// Records the application of the effects of the events that hit `people`, as computed into `results` by `PersonalEvents`.
+    /// Writes to `people` & `results` must be made visible to the compute shader stage beforehand,
+    /// and subsequent accesses to `people` must be preceded by a barrier on the compute shader stage.
+    pub fn record<'a, P: DeviceAllocator, E: DeviceAllocator, R: DeviceAllocator, Err: DeviceAllocator>(
+        &'a mut self,
+        cmd_buff: &mut Command<'a, D>,
+        errors: &Buffer<ErrorBuffer, Err>,
+        people: &Buffer<Person, P>,
+        events: &Buffer<PersonalEvent, E>,
+        results: &Buffer<ExternBool, R>
+    ) -> Result<()> {
+        debug_assert!(people.len() <= self.hits.len());
+
+        let set: &DescriptorSet = self.collect.sets().first().unwrap();
+        let descriptors = [
+            set.write_descriptor(errors, 0),
+            set.write_descriptor(results, 0),
+            set.write_descriptor(&self.dispatch, 0),
+            set.write_descriptor(&self.hits, 0),
+        ];
+        self.collect.sets_mut().update(&descriptors);
+
+        let set: &DescriptorSet = self.apply.sets().first().unwrap();
+        let descriptors = [
+            set.write_descriptor(errors, 0),
+            set.write_descriptor(people, 0),
+            set.write_descriptor(events, 0),
+            set.write_descriptor(results, 0),
+            set.write_descriptor(&self.hits, 0),
+        ];
+        self.apply.sets_mut().update(&descriptors);
+
+        let this: &'a Self = self;
+        cmd_buff.update_buffer(&this.dispatch, 0, &[DispatchIndirectCommand::new(0, 1, 1)])?;
+        cmd_buff.buffer_barrier(
+            PipelineStages::TRANSFER,
+            PipelineStages::COMPUTE_SHADER,
+            &[BufferMemoryBarrier::new(&this.dispatch, AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE)]
+        );
+
+        cmd_buff.bind_pipeline(PipelineBindPoint::Compute, &this.collect, ..);
+        cmd_buff.dispatch(u64_to_u32(people.len()), 1, 1);
+
+        // The dispatch size is read when the indirect dispatch executes, before any shader runs
+        cmd_buff.buffer_barrier(
+            PipelineStages::COMPUTE_SHADER,
+            PipelineStages::DRAW_INDIRECT | PipelineStages::COMPUTE_SHADER,
+            &[
+                BufferMemoryBarrier::new(&this.dispatch, AccessFlags::SHADER_WRITE, AccessFlags::INDIRECT_COMMAND_READ),
+                BufferMemoryBarrier::new(&this.hits, AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ),
+                BufferMemoryBarrier::new(errors, AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE),
+            ]
+        );
+
+        cmd_buff.bind_pipeline(PipelineBindPoint::Compute, &this.apply, ..);
+        cmd_buff.dispatch_indirect(&this.dispatch, 0);
+        return Ok(())
+    }
+}
@@ -1,15 +1,23 @@
+use std::mem::MaybeUninit;
 use rand::{distributions::OpenClosed01, thread_rng, Rng};
-use shared::{person::Person, person_event::PersonalEvent, ExternBool};
+use shared::{error::ErrorBuffer, person::Person, person_event::{PersonalEvent, PersonalEventConstants}, ExternBool};
 use vulkan::{
-    alloc::{DeviceAllocator, MemoryFlags},
-    buffer::{Buffer, UsageFlags, BufferFlags},
+    alloc::DeviceAllocator,
+    buffer::Buffer,
     device::DeviceRef,
-    pipeline::{ComputeBuilder, Pipeline},
-    pool::{CommandBufferUsage, PipelineBindPoint},
-    Result, descriptor::{DescriptorSet, DescriptorType}, utils::u64_to_u32, shader::ShaderStages, cstr, sync::{FenceFlags, Fence},
+    pipeline::{ComputeBuilder, Pipeline, PipelineCache},
+    library::ShaderLibrary,
+    pool::{Command, ParallelPools, PipelineBindPoint},
+    Result, descriptor::{DescriptorSet, DescriptorType}, utils::u64_to_u32, shader::ShaderStages, cstr,
 };
 
-use crate::context::Context;
+/// Specialization constant holding the base chance of every event
+const BASE_CHANCE_ID: u32 = 0;
+/// Specialization constant toggling the printing of every event's chance
+const PRINT_CHANCES_ID: u32 = 1;
+// Regular odds (1f32 chance) will result in true once every 100 ticks (approximately, obviously)
+//pub const BASE_CHANCE: f32 = 1f32 / 100f32;
+pub const BASE_CHANCE: f32 = 1f32;
 
 pub struct PersonalEvents<D: DeviceRef> {
     pipeline: Pipeline<D>,
@@ -18,52 +26,99 @@ pub struct PersonalEvents<D: DeviceRef> {
 
 impl<D: DeviceRef> PersonalEvents<D> {
     #[inline]
-    pub fn new (dev: D, words: &[u32]) -> Result<Self> where D: Clone {
-        let pipeline = ComputeBuilder::new(dev)
-            .entry(cstr!("compute_personal_event"))
+    pub fn builder<'a, C: DeviceRef> (library: &ShaderLibrary<D>, cache: &'a PipelineCache<C>, base_chance: f32, print_chances: bool) -> ComputeBuilder<'a, D> where D: Clone {
+        return library.compute(cstr!("compute_personal_event"))
+            .cache(cache)
+            .constant(BASE_CHANCE_ID, base_chance.to_bits())
+            .constant(PRINT_CHANCES_ID, print_chances)
+            .push_constant::<PersonalEventConstants>()
+            .binding(DescriptorType::StorageBuffer, 1) // errors
             .binding(DescriptorType::StorageBuffer, 1)
             .binding(DescriptorType::StorageBuffer, 1)
             .binding(DescriptorType::StorageBuffer, 1)
-            .build(words)?;
+    }
 
-        return Ok(Self {
+    #[inline]
+    pub fn new (pipeline: Pipeline<D>) -> Self {
+        return Self {
             pipeline,
             seed: 100f32 * thread_rng().sample::<f32, _>(OpenClosed01),
-        });
+        };
     }
 
+    /// Records the computation of the events `people` may trigger into `cmd_buff`, reporting failed assertions into `errors`.
+    /// Writes to `people` & `events` must be made visible to the compute shader stage beforehand.
     #[inline]
-    pub fn call<Ctx: DeviceRef, P: Clone + DeviceAllocator, E: DeviceAllocator>(
-        &mut self,
+    pub fn record<'a, P: DeviceAllocator, E: DeviceAllocator, R: DeviceAllocator, Err: DeviceAllocator>(
+        &'a mut self,
+        cmd_buff: &mut Command<'a, D>,
+        errors: &Buffer<ErrorBuffer, Err>,
         people: &Buffer<Person, P>,
         events: &Buffer<PersonalEvent, E>,
-        ctx: &mut Context<Ctx>
-    ) -> Result<Buffer<ExternBool, P>> {
-        let result = Buffer::<ExternBool, _>::new_uninit(
-            people.len() * events.len(),
-            UsageFlags::STORAGE_BUFFER,
-            BufferFlags::empty(),
-            MemoryFlags::MAPABLE,
-            people.alloc().clone(),
-        )?;
+        result: &Buffer<MaybeUninit<ExternBool>, R>
+    ) -> Result<()> {
+        self.update_sets(errors, people, events, result);
 
-        let set: &DescriptorSet = self.pipeline.sets().first().unwrap();
-        let people_desc = set.write_descriptor(people, 0);
-        let events_desc = set.write_descriptor(events, 0);
-        let result_desc = set.write_descriptor(&result, 0);
-        self.pipeline.sets_mut().update(&[people_desc, events_desc, result_desc]);
-
-        let mut cmd_buff = ctx.pool.begin_mut(0, CommandBufferUsage::ONE_TIME_SUBMIT)?;
+        // The pipeline stays borrowed by `cmd_buff`, so the next seed is picked beforehand
+        let seed = core::mem::replace(&mut self.seed, 100f32 * thread_rng().sample::<f32, _>(OpenClosed01));
         cmd_buff.bind_pipeline(PipelineBindPoint::Compute, &self.pipeline, ..);
-        cmd_buff.push_contant(&self.seed, ShaderStages::COMPUTE)?;
-        cmd_buff.dispatch(u64_to_u32(people.len()), u64_to_u32(events.len()), 1);
-        drop(cmd_buff);
+        let (people, events) = (u64_to_u32(people.len()), u64_to_u32(events.len()));
+        cmd_buff.push_contant(&PersonalEventConstants { seed, first_person: 0, first_event: 0, people, events }, ShaderStages::COMPUTE)?;
+        let [x, y, z] = self.pipeline.workgroup_count([people, events, 1]);
+        cmd_buff.dispatch(x, y, z);
+        return Ok(())
+    }
+
+    /// Records the same work as [`record`](Self::record), split into regions of up to `region_size` people
+    /// (rounded up to the workgroup size, so that regions don't overlap).
+    /// The regions are recorded into the secondary command buffers of `frame` by the threads of `pools`, and executed from `cmd_buff`.
+    pub fn record_parallel<'a, C: DeviceRef + Send, P: DeviceAllocator, E: DeviceAllocator, R: DeviceAllocator, Err: DeviceAllocator>(
+        &'a mut self,
+        cmd_buff: &mut Command<'_, D>,
+        pools: &'a mut ParallelPools<C>,
+        frame: u32,
+        region_size: u32,
+        errors: &Buffer<ErrorBuffer, Err>,
+        people: &Buffer<Person, P>,
+        events: &Buffer<PersonalEvent, E>,
+        result: &Buffer<MaybeUninit<ExternBool>, R>
+    ) -> Result<()> where Pipeline<D>: Sync {
+        debug_assert!(region_size > 0);
+        self.update_sets(errors, people, events, result);
 
-        let mut fence = Fence::new(self.pipeline.device(), FenceFlags::empty())?;
-        fence.bind_to::<_, Ctx>(&mut ctx.pool, &mut ctx.queue, None)?;
-        fence.wait(None)?;
+        let seed = core::mem::replace(&mut self.seed, 100f32 * thread_rng().sample::<f32, _>(OpenClosed01));
+        let group_size = self.pipeline.workgroup_size()[0].max(1);
+        let region_size = (region_size + group_size - 1) / group_size * group_size;
 
-        self.seed = 100f32 * thread_rng().sample::<f32, _>(OpenClosed01);
-        return unsafe { Ok(result.assume_init()) };
+        let (people, events) = (u64_to_u32(people.len()), u64_to_u32(events.len()));
+        let regions = (0..people)
+            .step_by(region_size as usize)
+            .map(|first| (first, region_size.min(people - first)))
+            .collect::<Vec<_>>();
+
+        let pipeline: &'a Pipeline<D> = &self.pipeline;
+        return pools.record(frame, cmd_buff, &regions, |cmd_buff, &(first_person, len)| {
+            cmd_buff.bind_pipeline(PipelineBindPoint::Compute, pipeline, ..);
+            cmd_buff.push_contant(&PersonalEventConstants { seed, first_person, first_event: 0, people, events }, ShaderStages::COMPUTE)?;
+            let [x, y, z] = pipeline.workgroup_count([len, events, 1]);
+            cmd_buff.dispatch(x, y, z);
+            return Ok(())
+        })
+    }
+
+    #[inline]
+    fn update_sets<P: DeviceAllocator, E: DeviceAllocator, R: DeviceAllocator, Err: DeviceAllocator>(
+        &mut self,
+        errors: &Buffer<ErrorBuffer, Err>,
+        people: &Buffer<Person, P>,
+        events: &Buffer<PersonalEvent, E>,
+        result: &Buffer<MaybeUninit<ExternBool>, R>
+    ) {
+        let set: &DescriptorSet = self.pipeline.sets().first().unwrap();
+        let errors_desc = set.write_descriptor(errors, 0);
+        let people_desc = set.write_descriptor(people, 0);
+        let events_desc = set.write_descriptor(events, 0);
+        let result_desc = set.write_descriptor(result, 0);
+        self.pipeline.sets_mut().update(&[errors_desc, people_desc, events_desc, result_desc]);
     }
 }
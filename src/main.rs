@@ -1,24 +1,51 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![feature(ptr_metadata, rustc_attrs)]
 
-use std::{collections::HashMap, io::BufReader, panic::resume_unwind, path::Path};
+use std::{collections::HashMap, io::BufReader, num::NonZeroU64, panic::resume_unwind, path::Path};
 
-use context::Context;
+use context::{Context, READBACK_BUFFER, UPLOAD_BUFFER};
+use errors::GpuErrors;
+use profiler::Profiler;
 use futures::{pin_mut, stream::FuturesUnordered, FutureExt, Stream, StreamExt, TryStreamExt};
 use shared::{person::Person, person_event::PersonalEvent, ExternBool};
 use vulkan::{
-    alloc::{DeviceAllocator, MemoryFlags, Page},
+    alloc::{Book, Dedicated, MemoryUsage, Raw},
+    barrier::{AccessFlags, BufferMemoryBarrier},
     buffer::{Buffer, BufferFlags, UsageFlags},
-    device::{Device, DeviceRef},
-    extension_props, include_spv,
-    physical_dev::PhysicalDevice,
+    debug,
+    device::Device,
+    features::Feature,
+    cstr, extension_props, include_spv,
+    library::ShaderLibrary,
+    physical_dev::{FamilyQueueFlags, PhysicalDevice},
+    pipeline::{PipelineCache, PipelineCacheFlags, PipelineStages},
+    pool::{BufferCopy, CommandBufferUsage, ParallelPools},
+    staging::StagingUploader,
+    utils::usize_to_u32,
     Entry,
 };
 
 const WORDS: &[u32] = include_spv!("gpu.spv");
+const PIPELINE_CACHE: &str = "pipeline.cache";
+const PAGE_SIZE: u64 = 1 << 20;
+const STAGING_SIZE: u64 = 1 << 20;
+const POPULATION: u64 = 10_000;
+const PROFILER_SECTIONS: u32 = 8;
+/// People per region of the personal events dispatch, each region being recorded on its own thread
+const REGION_SIZE: u32 = 1024;
+/// Invocations per workgroup of the kernels running once per person
+const WORKGROUP_SIZE: u32 = 64;
+/// Environment variable overriding the physical device, by name, index or UUID
+const DEVICE_ENV: &str = "SOCIETY_SIM_DEVICE";
+/// Environment variable enabling the validation layer, along with its debug printf, when set
+const VALIDATION_ENV: &str = "SOCIETY_SIM_VALIDATION";
+/// Features matching the capabilities requested from `spirv-builder` in `build.rs`
+const SHADER_FEATURES: &[Feature] = &[Feature::ShaderInt8, Feature::ShaderInt16, Feature::VariablePointersStorageBuffer];
 
-use crate::game::{generate_people::GeneratePeople, personal_events::PersonalEvents};
+use crate::game::{generate_people::GeneratePeople, personal_effects::PersonalEffects, personal_events::{PersonalEvents, BASE_CHANCE}};
 pub mod context;
+pub mod errors;
+pub mod profiler;
 pub mod game;
 
 #[macro_export]
@@ -34,24 +61,270 @@ macro_rules! flat_mod {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     //let _ = unsafe { Entry::builder(1, 0, 0).build_in("/opt/homebrew/Cellar/molten-vk/1.2.1/lib/libMoltenVK.dylib") }?;
-    let _ = unsafe { Entry::builder(1, 1, 0).build() }?;
+    env_logger::init();
+
+    // The validation layer slows every call down & may not be installed, so it's opt-in
+    let entry = unsafe { Entry::builder(1, 2, 0) };
+    let entry = match std::env::var_os(VALIDATION_ENV) {
+        Some(_) => entry.validation_errors().debug_printf(),
+        None => entry,
+    };
+    let _ = unsafe { entry.build() }?;
 
     #[cfg(debug_assertions)]
     println!("{:#?}", extension_props());
 
-    let phy = PhysicalDevice::first()?;
-    let (dev, queues) = Device::builder(phy).queues(&[1f32]).build().build()?;
-    let mut ctx = Context::new(&dev, queues.into_iter().next().unwrap())?;
-    let alloc = Page::new(&dev, 2048, MemoryFlags::MAPABLE)?;
+    let phy = PhysicalDevice::select().require_features(SHADER_FEATURES.iter().copied());
+    // Shader printf is only used in debug builds
+    #[cfg(debug_assertions)]
+    let phy = phy.require_extensions([cstr!("VK_KHR_shader_non_semantic_info")]);
+    let phy = phy.queue_flags(FamilyQueueFlags::COMPUTE).env_override(DEVICE_ENV).build()?;
+
+    // Uploads run on a dedicated transfer family, if there is one
+    let transfer_family = phy.best_transfer_family().filter(|x| Some(*x) != phy.best_compute_family());
+    let builder = Device::builder(phy)
+        .require_features(SHADER_FEATURES.iter().copied())
+        .prefer_features([Feature::StorageBuffer8BitAccess, Feature::StorageBuffer16BitAccess])
+        // Lets the allocator follow the heaps' actual budgets, instead of their sizes
+        .prefer_extensions([cstr!("VK_EXT_memory_budget")]);
+    #[cfg(debug_assertions)]
+    let builder = builder.extensions([cstr!("VK_KHR_shader_non_semantic_info")]);
+    let mut builder = builder.queues(&[1f32]).build()?;
+
+    if let Some(family) = &transfer_family {
+        builder = builder.queues(&[1f32]).family(family)?.build()?;
+    }
+
+    let (dev, queues) = builder.build()?;
+    let mut queues = queues.into_iter();
+    let mut ctx = Context::with_transfer(&dev, queues.next().unwrap(), queues.next())?;
+    let alloc = Book::new(&dev, NonZeroU64::new(PAGE_SIZE), None);
+    let mut uploader = StagingUploader::new(STAGING_SIZE, &alloc)?;
+    let cache = PipelineCache::load(&dev, PipelineCacheFlags::empty(), PIPELINE_CACHE)?;
+
+    let (_event_names, tmp_events) = load_personal_events("game/personal_events").await?;
 
-    let people = initialize_population(10_000, &mut ctx, &alloc)?;
-    let (_event_names, events) =
-        initialize_personal_events("game/personal_events", &mut ctx, &alloc).await?;
+    let library = ShaderLibrary::new(&dev, WORDS)?;
+    let [generate, personal, collect, apply] = library.build_array([
+        GeneratePeople::builder(&library, &cache).workgroup_size(WORKGROUP_SIZE, 1, 1),
+        PersonalEvents::builder(&library, &cache, BASE_CHANCE, cfg!(debug_assertions)).workgroup_size(WORKGROUP_SIZE, 1, 1),
+        PersonalEffects::<_, &Book<Raw<&Device>>>::collect_builder(&library, &cache, usize_to_u32(tmp_events.len())),
+        PersonalEffects::<_, &Book<Raw<&Device>>>::apply_builder(&library, &cache),
+    ])?;
+    generate.set_name(cstr!("generate_people"))?;
+    personal.set_name(cstr!("compute_personal_event"))?;
+    collect.set_name(cstr!("collect_hits"))?;
+    apply.set_name(cstr!("apply_personal_effects"))?;
+    cache.save(PIPELINE_CACHE)?;
 
-    let mut evt = PersonalEvents::new(&dev, WORDS)?;
-    let result = evt.call(&people, &events, &mut ctx)?;
+    let mut generator = GeneratePeople::new(generate);
+    let mut evt = PersonalEvents::new(personal);
+    let mut effects = PersonalEffects::new(collect, apply, POPULATION, &alloc)?;
+    let mut profiler = Profiler::new(&dev, ctx.compute_family(), PROFILER_SECTIONS)?;
+    let mut parallel = ParallelPools::with_available_parallelism(&dev, ctx.compute_family(), ctx.frames())?;
+
+    let people = Buffer::<Person, _>::new_uninit(
+        POPULATION,
+        UsageFlags::STORAGE_BUFFER,
+        BufferFlags::empty(),
+        // The population is the largest buffer by far, so it gets an allocation of its own
+        MemoryUsage::device_local().dedicated(Dedicated::Always),
+        &alloc,
+    )?;
+
+    let events = Buffer::<PersonalEvent, _>::new_uninit(
+        tmp_events.len() as u64,
+        UsageFlags::STORAGE_BUFFER | UsageFlags::TRANSFER_DST,
+        BufferFlags::empty(),
+        MemoryUsage::device_local(),
+        &alloc,
+    )?;
 
-    let result = result.map(..)?;
+    let result = Buffer::<ExternBool, _>::new_uninit(
+        people.len() * events.len(),
+        UsageFlags::STORAGE_BUFFER | UsageFlags::TRANSFER_SRC | UsageFlags::TRANSFER_DST,
+        BufferFlags::empty(),
+        MemoryUsage::device_local(),
+        &alloc,
+    )?;
+
+    let readback = Buffer::<ExternBool, _>::new_uninit(
+        result.len(),
+        UsageFlags::TRANSFER_DST,
+        BufferFlags::empty(),
+        MemoryUsage::readback(),
+        &alloc,
+    )?;
+
+    let mut errors = GpuErrors::new(&alloc)?;
+    errors.buffer().set_name(cstr!("gpu_errors"))?;
+    people.set_name(cstr!("people"))?;
+    events.set_name(cstr!("personal_events"))?;
+    result.set_name(cstr!("personal_event_results"))?;
+
+    // With a dedicated transfer queue, the events are uploaded & the results cleared while the population is generated,
+    // releasing their ownership to the compute family once done
+    let (transfer_family, compute_family) = (ctx.transfer_family(), ctx.compute_family());
+    let dedicated_transfer = ctx.transfer.is_some();
+    if let Some(transfer) = ctx.transfer.as_mut() {
+        let mut cmd_buff = transfer.pool.begin_mut(UPLOAD_BUFFER, CommandBufferUsage::ONE_TIME_SUBMIT)?;
+        uploader.upload_uninit(&mut cmd_buff, &tmp_events, &events, 0)?;
+        // Events only set the results they hit, so the rest must start cleared
+        cmd_buff.fill_buffer(&result, .., 0)?;
+        cmd_buff.buffer_barrier(
+            PipelineStages::TRANSFER,
+            PipelineStages::BOTTOM_OF_PIPE,
+            &[
+                BufferMemoryBarrier::new(&events, AccessFlags::TRANSFER_WRITE, AccessFlags::empty())
+                    .queue_transfer(transfer_family, compute_family),
+                BufferMemoryBarrier::new(&result, AccessFlags::TRANSFER_WRITE, AccessFlags::empty())
+                    .queue_transfer(transfer_family, compute_family),
+            ],
+        );
+        drop(cmd_buff);
+
+        transfer.queue
+            .submitter::<&Device, _, _>(None)
+            .add(&transfer.pool, UPLOAD_BUFFER as usize..UPLOAD_BUFFER as usize + 1)
+            .signal(&transfer.semaphore)
+            .submit()?;
+    }
+
+    // The population is generated by a submission of its own, which doesn't wait for the uploads.
+    // Without a dedicated transfer queue, it also uploads the events & clears the results.
+    let mut cmd_buff = ctx.begin_frame(CommandBufferUsage::ONE_TIME_SUBMIT).await?;
+    profiler.begin(&mut cmd_buff);
+
+    if !dedicated_transfer {
+        let section = profiler.start_at(&mut cmd_buff, "upload_events", PipelineStages::TRANSFER);
+        uploader.upload_uninit(&mut cmd_buff, &tmp_events, &events, 0)?;
+        cmd_buff.fill_buffer(&result, .., 0)?;
+        profiler.end(&mut cmd_buff, section);
+    }
+
+    let section = profiler.start(&mut cmd_buff, "generate_people");
+    generator.record(&mut cmd_buff, errors.buffer(), &people)?;
+    profiler.end(&mut cmd_buff, section);
+    drop(cmd_buff);
+    let mark = uploader.mark();
+    ctx.submit_frame(false, false)?;
+
+    // Only the personal events wait for the uploads, recorded while the population is being generated
+    let frame = ctx.frame();
+    let mut cmd_buff = ctx.begin_frame(CommandBufferUsage::ONE_TIME_SUBMIT).await?;
+
+    // Both buffers are initialized by the time the barrier below is passed.
+    // If the events were uploaded by the transfer queue, the barrier acquires their ownership, as well as the results'.
+    let (people, events) = unsafe { (people.assume_init(), events.assume_init()) };
+    let upload_access = match dedicated_transfer {
+        true => AccessFlags::empty(),
+        false => AccessFlags::TRANSFER_WRITE,
+    };
+    cmd_buff.buffer_barrier(
+        PipelineStages::TRANSFER | PipelineStages::COMPUTE_SHADER,
+        PipelineStages::COMPUTE_SHADER,
+        &[
+            BufferMemoryBarrier::new(&events, upload_access, AccessFlags::SHADER_READ)
+                .queue_transfer(transfer_family, compute_family),
+            BufferMemoryBarrier::new(&people, AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ),
+            BufferMemoryBarrier::new(&result, upload_access, AccessFlags::SHADER_WRITE)
+                .queue_transfer(transfer_family, compute_family),
+            errors.kernel_barrier(),
+        ],
+    );
+
+    let section = profiler.start(&mut cmd_buff, "compute_personal_event");
+    evt.record_parallel(&mut cmd_buff, &mut parallel, frame, REGION_SIZE, errors.buffer(), &people, &events, &result)?;
+    profiler.end(&mut cmd_buff, section);
+
+    // The results were cleared before being computed, so they're initialized once the barrier below is passed
+    let result = unsafe { result.assume_init() };
+    cmd_buff.buffer_barrier(
+        PipelineStages::COMPUTE_SHADER,
+        PipelineStages::COMPUTE_SHADER,
+        &[
+            BufferMemoryBarrier::new(&result, AccessFlags::SHADER_WRITE, AccessFlags::SHADER_READ),
+            errors.kernel_barrier(),
+        ],
+    );
+
+    let section = profiler.start(&mut cmd_buff, "apply_personal_effects");
+    effects.record(&mut cmd_buff, errors.buffer(), &people, &events, &result)?;
+    profiler.end(&mut cmd_buff, section);
+
+    // The results are read back by the transfer queue if there's one, releasing their ownership to it
+    let copy = [BufferCopy { src_offset: 0, dst_offset: 0, size: result.size() }];
+    if dedicated_transfer {
+        cmd_buff.buffer_barrier(
+            PipelineStages::COMPUTE_SHADER,
+            PipelineStages::BOTTOM_OF_PIPE,
+            &[BufferMemoryBarrier::new(&result, AccessFlags::SHADER_WRITE, AccessFlags::empty())
+                .queue_transfer(compute_family, transfer_family)],
+        );
+    } else {
+        cmd_buff.buffer_barrier(
+            PipelineStages::COMPUTE_SHADER,
+            PipelineStages::TRANSFER,
+            &[BufferMemoryBarrier::new(&result, AccessFlags::SHADER_WRITE, AccessFlags::TRANSFER_READ)],
+        );
+        cmd_buff.copy_buffer(&result, &readback, &copy);
+        cmd_buff.buffer_barrier(
+            PipelineStages::TRANSFER,
+            PipelineStages::HOST,
+            &[BufferMemoryBarrier::new(&readback, AccessFlags::TRANSFER_WRITE, AccessFlags::HOST_READ)],
+        );
+    }
+
+    cmd_buff.buffer_barrier(PipelineStages::COMPUTE_SHADER, PipelineStages::HOST, &[errors.barrier()]);
+    drop(cmd_buff);
+    ctx.submit_frame(dedicated_transfer, dedicated_transfer)?;
+
+    if let Some(transfer) = ctx.transfer.as_mut() {
+        let mut cmd_buff = transfer.pool.begin_mut(READBACK_BUFFER, CommandBufferUsage::ONE_TIME_SUBMIT)?;
+        cmd_buff.buffer_barrier(
+            PipelineStages::TOP_OF_PIPE,
+            PipelineStages::TRANSFER,
+            &[BufferMemoryBarrier::new(&result, AccessFlags::empty(), AccessFlags::TRANSFER_READ)
+                .queue_transfer(compute_family, transfer_family)],
+        );
+        cmd_buff.copy_buffer(&result, &readback, &copy);
+        cmd_buff.buffer_barrier(
+            PipelineStages::TRANSFER,
+            PipelineStages::HOST,
+            &[BufferMemoryBarrier::new(&readback, AccessFlags::TRANSFER_WRITE, AccessFlags::HOST_READ)],
+        );
+        drop(cmd_buff);
+
+        transfer.queue
+            .submitter::<&Device, _, _>(Some(&mut transfer.fence))
+            .add(&transfer.pool, READBACK_BUFFER as usize..READBACK_BUFFER as usize + 1)
+            .wait(&transfer.compute_semaphore, PipelineStages::TRANSFER)
+            .submit()?;
+    }
+
+    ctx.wait_all().await?;
+    if let Some(transfer) = ctx.transfer.as_ref() {
+        transfer.fence.wait_async().await?;
+    }
+    unsafe { uploader.release(mark) };
+    errors.check()?;
+    debug::check()?;
+
+    if let Some(report) = profiler.report()? {
+        for (label, time) in report {
+            println!("{label}: {time:?}");
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        println!("{:#?}", alloc.stats());
+        println!("{:#?}", alloc.budget());
+    }
+
+    // The submissions above have copied the results into the readback buffer
+    let readback = unsafe { readback.assume_init() };
+    let result = readback.map(..)?;
     println!("{:#?}", &result as &[ExternBool]);
 
     // let mut main = setup_main(&dev)?;
@@ -65,33 +338,9 @@ async fn main() -> anyhow::Result<()> {
 }
 
 #[inline]
-fn initialize_population<D: Clone + DeviceRef, A: DeviceAllocator>(
-    capacity: u64,
-    ctx: &mut Context<D>,
-    alloc: A,
-) -> vulkan::Result<Buffer<Person, A>> {
-    let mut generator = GeneratePeople::new(ctx.owned_device(), WORDS)?;
-    return generator.generate(
-        capacity,
-        UsageFlags::STORAGE_BUFFER,
-        BufferFlags::empty(),
-        // MemoryFlags::DEVICE_LOCAL,
-        MemoryFlags::MAPABLE,
-        alloc,
-        ctx,
-    );
-}
-
-#[inline]
-async fn initialize_personal_events<
-    P: 'static + Send + Clone + AsRef<Path>,
-    D: Clone + DeviceRef,
-    A: DeviceAllocator,
->(
+async fn load_personal_events<P: 'static + Send + Clone + AsRef<Path>>(
     path: P,
-    ctx: &mut Context<D>,
-    alloc: A,
-) -> anyhow::Result<(Vec<String>, Buffer<PersonalEvent, A>)> {
+) -> anyhow::Result<(Vec<String>, Vec<PersonalEvent>)> {
     let mut handles = FuturesUnordered::new();
     let mut dir = tokio::fs::read_dir(path.as_ref()).await?;
 
@@ -124,17 +373,5 @@ async fn initialize_personal_events<
         }
     }
 
-    let mut events = Buffer::<PersonalEvent, _>::new_uninit(
-        tmp_events.len() as u64,
-        UsageFlags::STORAGE_BUFFER,
-        BufferFlags::empty(),
-        MemoryFlags::MAPABLE,
-        alloc,
-    )?;
-
-    let mut map = events.map_mut(..)?;
-    map.init_from_slice(&tmp_events);
-    drop(map);
-
-    return unsafe { Ok((names, events.assume_init())) };
+    return Ok((names, tmp_events));
 }
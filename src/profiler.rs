@@ -0,0 +1,102 @@
+use std::time::Duration;
+use vulkan::{device::DeviceRef, physical_dev::Family, pipeline::PipelineStages, pool::Command, query::QueryPool, Result};
+
+/// Measures the GPU time spent on every labeled section of a tick.
+/// Does nothing if the queue family doesn't support timestamps.
+pub struct Profiler<D: DeviceRef> {
+    queries: Option<QueryPool<D>>,
+    capacity: u32,
+    labels: Vec<&'static str>,
+}
+
+/// Section started by [`Profiler::start`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct Section(u32);
+
+impl<D: DeviceRef> Profiler<D> {
+    /// Creates a profiler able to measure up to `capacity` sections per tick, recorded on queues of `family`
+    #[inline]
+    pub fn new(device: D, family: Family, capacity: u32) -> Result<Self> {
+        let queries = match family.supports_timestamps() {
+            true => Some(QueryPool::timestamps(device, family, 2 * capacity)?),
+            false => None,
+        };
+
+        return Ok(Self {
+            queries,
+            capacity,
+            labels: Vec::with_capacity(capacity as usize),
+        });
+    }
+
+    /// Resets the profiler. Must be recorded before any section of the tick.
+    #[inline]
+    pub fn begin<P: DeviceRef>(&mut self, cmd_buff: &mut Command<'_, P>) {
+        if let Some(queries) = &self.queries {
+            cmd_buff.reset_queries(queries, ..);
+        }
+        self.labels.clear();
+    }
+
+    /// Starts a new section of the tick, made of compute dispatches
+    ///
+    /// # Panics
+    /// This method will panic if more sections than the profiler's capacity are started in a single tick.
+    #[inline]
+    pub fn start<P: DeviceRef>(&mut self, cmd_buff: &mut Command<'_, P>, label: &'static str) -> Section {
+        return self.start_at(cmd_buff, label, PipelineStages::COMPUTE_SHADER);
+    }
+
+    /// Starts a new section of the tick, whose commands begin executing at `stage`.
+    /// Timestamps written at the first stage of the section don't measure the time spent waiting on earlier commands,
+    /// unlike those written at the top of the pipe.
+    ///
+    /// # Panics
+    /// This method will panic if more sections than the profiler's capacity are started in a single tick.
+    pub fn start_at<P: DeviceRef>(
+        &mut self,
+        cmd_buff: &mut Command<'_, P>,
+        label: &'static str,
+        stage: PipelineStages,
+    ) -> Section {
+        let idx = self.labels.len() as u32;
+        assert!(idx < self.capacity, "too many profiler sections");
+
+        if let Some(queries) = &self.queries {
+            cmd_buff.write_timestamp(queries, 2 * idx, stage);
+        }
+        self.labels.push(label);
+        return Section(idx);
+    }
+
+    #[inline]
+    pub fn end<P: DeviceRef>(&mut self, cmd_buff: &mut Command<'_, P>, section: Section) {
+        if let Some(queries) = &self.queries {
+            cmd_buff.write_timestamp(queries, 2 * section.0 + 1, PipelineStages::BOTTOM_OF_PIPE);
+        }
+    }
+
+    /// Returns the GPU time spent on every section of the last tick, or `None` if it hasn't completed yet.
+    /// The report is empty if timestamps aren't supported.
+    pub fn report(&self) -> Result<Option<Vec<(&'static str, Duration)>>> {
+        let queries = match &self.queries {
+            Some(x) if !self.labels.is_empty() => x,
+            _ => return Ok(Some(Vec::new())),
+        };
+
+        let timestamps = match queries.results(0, 2 * self.labels.len() as u32, false)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let report = self
+            .labels
+            .iter()
+            .zip(timestamps.chunks_exact(2))
+            .map(|(label, x)| (*label, queries.elapsed(x[0], x[1])))
+            .collect::<Vec<_>>();
+
+        return Ok(Some(report));
+    }
+}
@@ -106,6 +106,7 @@ pub fn entry (item: proc_macro::TokenStream) -> proc_macro::TokenStream {
         pub struct Entry {
             lib: Library,
             instance: NonZeroU64,
+            api_version: u32,
             #[cfg(unix)]
             pub(crate) get_instance_proc_addr: libloading::os::unix::Symbol<vk::FnGetInstanceProcAddr>,
             #[cfg(windows)]
@@ -119,6 +120,7 @@ pub fn entry (item: proc_macro::TokenStream) -> proc_macro::TokenStream {
         impl Entry {
             unsafe fn new (
                 instance: NonZeroU64,
+                api_version: u32,
                 lib: Library,
                 create_instance: vk::FnCreateInstance,
                 #[cfg(unix)] get_instance_proc_addr: libloading::os::unix::Symbol<vk::FnGetInstanceProcAddr>,
@@ -127,6 +129,7 @@ pub fn entry (item: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 return Self {
                     lib,
                     instance,
+                    api_version,
                     create_instance,
                     #(
                         #lower_case: transmute((get_instance_proc_addr)(instance.get(), #upper_case.as_ptr())),
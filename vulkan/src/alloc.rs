@@ -1,6 +1,7 @@
 use crate::{
     device::{Device, DeviceRef},
     error::Error,
+    physical_dev::HeapBudget,
     utils::{u64_to_usize, UpQueue},
     Entry, Result,
 };
@@ -8,22 +9,33 @@ use std::{
     ffi::c_void,
     fmt::Debug,
     marker::PhantomData,
-    mem::{ManuallyDrop, MaybeUninit},
     num::{NonZeroU64, NonZeroUsize},
     ops::{Bound, Deref, Range, RangeBounds},
     pin::Pin,
     ptr::{addr_of, addr_of_mut, NonNull},
     rc::Rc,
     sync::{
-        atomic::{AtomicPtr, Ordering},
+        atomic::{AtomicPtr, AtomicU64, AtomicUsize, Ordering},
         Arc, Mutex, TryLockError,
     },
+    task::{Poll, Waker},
 };
 use once_cell::sync::OnceCell;
-use vk::{MemoryType, DeviceSize};
+use vk::DeviceSize;
+
+mod backend;
+mod buddy;
+mod usage;
+pub use backend::*;
+pub use buddy::*;
+pub use usage::*;
 
 const UNINIT: *mut c_void = core::ptr::null_mut();
 const INITIALIZING: *mut c_void = NonNull::dangling().as_ptr();
+/// Smallest block handed out by a [`Page`]
+const MIN_BLOCK_SIZE: vk::DeviceSize = 256;
+/// Size of the pages of [`Book::new`], unless the device's memory budget is too small for it
+pub const DEFAULT_PAGE_SIZE: vk::DeviceSize = 64 << 20;
 
 pub trait MemoryMetadata {
     fn range(&self) -> Range<vk::DeviceSize>;
@@ -61,6 +73,11 @@ impl<M: MemoryMetadata> MemoryPtr<M> {
         let range = self.range();
         return range.end - range.start;
     }
+
+    #[inline]
+    pub fn metadata(&self) -> &M {
+        return &self._meta;
+    }
 }
 
 pub unsafe trait DeviceAllocator {
@@ -74,9 +91,8 @@ pub unsafe trait DeviceAllocator {
 
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>>;
 
     unsafe fn free(&self, ptr: MemoryPtr<Self::Metadata>);
@@ -91,6 +107,18 @@ pub unsafe trait DeviceAllocator {
     /// # Safety
     /// It is up to the caller to ensure that Rust's [borrowing rules](https://doc.rust-lang.org/stable/book/ch04-02-references-and-borrowing.html) are followed for the maps.
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>);
+
+    /// Makes host writes to the bytes of `mem` in `range` visible to the device. Does nothing if the memory is host-coherent.
+    ///
+    /// # Safety
+    /// `range` must be mapped
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()>;
+
+    /// Makes device writes to the bytes of `mem` in `range` visible to the host. Does nothing if the memory is host-coherent.
+    ///
+    /// # Safety
+    /// `range` must be mapped
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()>;
 }
 
 unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for &T {
@@ -113,11 +141,10 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for &T {
     #[inline]
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>> {
-        return T::allocate(*self, size, align, flags);
+        return T::allocate(*self, requirements, usage);
     }
 
     #[inline]
@@ -138,6 +165,16 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for &T {
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         T::unmap(*self, mem)
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::flush(*self, mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::invalidate(*self, mem, range);
+    }
 }
 
 unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Box<T> {
@@ -160,11 +197,10 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Box<T> {
     #[inline]
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>> {
-        return T::allocate(self, size, align, flags);
+        return T::allocate(self, requirements, usage);
     }
 
     #[inline]
@@ -185,6 +221,16 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Box<T> {
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         T::unmap(self, mem)
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::flush(self, mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::invalidate(self, mem, range);
+    }
 }
 
 unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Rc<T> {
@@ -207,11 +253,10 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Rc<T> {
     #[inline]
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>> {
-        return T::allocate(self, size, align, flags);
+        return T::allocate(self, requirements, usage);
     }
 
     #[inline]
@@ -232,6 +277,16 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Rc<T> {
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         T::unmap(self, mem)
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::flush(self, mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::invalidate(self, mem, range);
+    }
 }
 
 unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Arc<T> {
@@ -254,11 +309,10 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Arc<T> {
     #[inline]
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>> {
-        return T::allocate(self, size, align, flags);
+        return T::allocate(self, requirements, usage);
     }
 
     #[inline]
@@ -279,6 +333,16 @@ unsafe impl<T: ?Sized + DeviceAllocator> DeviceAllocator for Arc<T> {
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         T::unmap(self, mem)
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::flush(self, mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return T::invalidate(self, mem, range);
+    }
 }
 
 unsafe impl<T: Deref> DeviceAllocator for Pin<T>
@@ -304,11 +368,10 @@ where
     #[inline]
     fn allocate(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<Self::Metadata>> {
-        return <T::Target as DeviceAllocator>::allocate(self, size, align, flags);
+        return <T::Target as DeviceAllocator>::allocate(self, requirements, usage);
     }
 
     #[inline]
@@ -329,12 +392,23 @@ where
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         <T::Target as DeviceAllocator>::unmap(self, mem)
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return <T::Target as DeviceAllocator>::flush(self, mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return <T::Target as DeviceAllocator>::invalidate(self, mem, range);
+    }
 }
 
+/// Allocator handing out whole blocks of device memory, one per allocation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct RawInner<D>(pub D);
+pub struct Raw<D>(pub D);
 
-unsafe impl<D: DeviceRef> DeviceAllocator for RawInner<D> {
+unsafe impl<D: DeviceRef> DeviceAllocator for Raw<D> {
     type Device = D;
     type Metadata = RawInfo;
 
@@ -351,59 +425,24 @@ unsafe impl<D: DeviceRef> DeviceAllocator for RawInner<D> {
         return self.0.deref();
     }
 
-    fn allocate<'a>(
+    fn allocate(
         &self,
-        size: vk::DeviceSize,
-        _align: vk::DeviceSize,
-        flags: MemoryFlags,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
     ) -> Result<MemoryPtr<RawInfo>> {
-        let entry = Entry::get();
-
-        let mut props = MaybeUninit::uninit();
-        (entry.get_physical_device_memory_properties)(self.0.physical().id(), props.as_mut_ptr());
-        let props = unsafe { props.assume_init() };
-
-        let mut info = None;
-        for i in 0..props.memoryTypeCount {
-            let MemoryType {
-                propertyFlags,
-                heapIndex,
-            } = props.memoryTypes[i as usize];
-            if MemoryFlags::from_bits_truncate(propertyFlags).contains(flags) {
-                info = Some((props.memoryHeaps[heapIndex as usize].size, i));
-                break;
-            }
-        }
+        let size = requirements.size;
+        // Every allocation is already a block of its own, so the driver is only told when it's interested
+        let dedicated = requirements
+            .buffer
+            .filter(|_| usage.is_dedicated(requirements, DeviceSize::MAX));
 
-        if let Some((_, mem_type_idx)) = info {
-            let info = vk::MemoryAllocateInfo {
-                sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
-                pNext: core::ptr::null(),
-                allocationSize: size,
-                memoryTypeIndex: mem_type_idx,
-            };
+        let types = self.memory_types();
+        let (inner, flags) = allocate_with_fallback(&types, requirements, usage, |memory_type| {
+            MemoryBackend::allocate(self, size, memory_type, dedicated)
+                .map(|x| (x, types[memory_type as usize]))
+        })?;
 
-            let mut memory = 0;
-            match (entry.allocate_memory)(
-                self.0.id(),
-                addr_of!(info),
-                core::ptr::null(),
-                addr_of_mut!(memory),
-            ) {
-                vk::SUCCESS => {}
-                e => return Err(e.into()),
-            }
-
-            if let Some(inner) = NonZeroU64::new(memory) {
-                return Ok(MemoryPtr {
-                    inner,
-                    _meta: RawInfo { size },
-                    _phtm: PhantomData,
-                });
-            }
-        }
-
-        return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into());
+        return unsafe { Ok(MemoryPtr::new(inner, RawInfo { size, flags })) };
     }
 
     #[inline]
@@ -433,16 +472,20 @@ unsafe impl<D: DeviceRef> DeviceAllocator for RawInner<D> {
             Bound::Unbounded => mem._meta.size as usize,
         };
 
+        // The whole block is mapped, so that flushed & invalidated ranges can be widened to the atom size
         let len = end - start;
         let mut ptr: *mut c_void = core::ptr::null_mut();
-        (entry.map_memory)(
+        match (entry.map_memory)(
             self.device().id(),
             mem.id(),
-            start as u64,
-            len as u64,
+            0,
+            vk::WHOLE_SIZE,
             0,
             addr_of_mut!(ptr),
-        );
+        ) {
+            vk::SUCCESS => {}
+            e => return Err(e.into()),
+        }
 
         if let Some(ptr) = NonNull::new(ptr) {
             let ptr = ptr.as_ptr().byte_add(start);
@@ -460,190 +503,323 @@ unsafe impl<D: DeviceRef> DeviceAllocator for RawInner<D> {
     unsafe fn unmap(&self, mem: &MemoryPtr<Self::Metadata>) {
         (Entry::get().unmap_memory)(self.device().id(), mem.id())
     }
-}
 
-#[derive(Debug)]
-pub struct Page<D: DeviceRef> {
-    inner: ManuallyDrop<MemoryPtr<RawInfo>>,
-    flags: MemoryFlags,
-    ranges: Mutex<Vec<Range<vk::DeviceSize>>>,
-    mapped_ptr: AtomicPtr<c_void>,
-    alloc: RawInner<D>,
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return match non_coherent_range(mem._meta.flags, 0, mem._meta.size, range, || {
+            self.non_coherent_atom_size()
+        }) {
+            Some(range) => MemoryBackend::flush(self, mem.inner, mem._meta.size, range),
+            None => Ok(()),
+        };
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return match non_coherent_range(mem._meta.flags, 0, mem._meta.size, range, || {
+            self.non_coherent_atom_size()
+        }) {
+            Some(range) => MemoryBackend::invalidate(self, mem.inner, mem._meta.size, range),
+            None => Ok(()),
+        };
+    }
 }
 
-impl<D: DeviceRef> Page<D> {
+unsafe impl<D: DeviceRef> MemoryBackend for Raw<D> {
     #[inline]
-    pub fn new(device: D, size: vk::DeviceSize, flags: MemoryFlags) -> Result<Self> {
-        let raw = RawInner(device);
-        let inner = raw.allocate(size, 1, flags)?;
-        return Ok(Self {
-            inner: ManuallyDrop::new(inner),
-            ranges: Mutex::new(vec![0..size]),
-            mapped_ptr: AtomicPtr::new(UNINIT),
-            alloc: raw,
-            flags,
+    fn memory_types(&self) -> Vec<MemoryFlags> {
+        return self.0.physical().memory_types();
+    }
+
+    fn allocate(
+        &self,
+        size: vk::DeviceSize,
+        memory_type: u32,
+        dedicated: Option<NonZeroU64>,
+    ) -> Result<NonZeroU64> {
+        let dedicated_info = dedicated.map(|buffer| vk::MemoryDedicatedAllocateInfo {
+            sType: vk::STRUCTURE_TYPE_MEMORY_DEDICATED_ALLOCATE_INFO,
+            pNext: core::ptr::null(),
+            image: 0,
+            buffer: buffer.get(),
         });
+
+        let info = vk::MemoryAllocateInfo {
+            sType: vk::STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+            pNext: match &dedicated_info {
+                Some(x) => (x as *const vk::MemoryDedicatedAllocateInfo).cast(),
+                None => core::ptr::null(),
+            },
+            allocationSize: size,
+            memoryTypeIndex: memory_type,
+        };
+
+        let mut memory = 0;
+        match (Entry::get().allocate_memory)(
+            self.0.id(),
+            addr_of!(info),
+            core::ptr::null(),
+            addr_of_mut!(memory),
+        ) {
+            vk::SUCCESS => {}
+            e => return Err(e.into()),
+        }
+
+        return NonZeroU64::new(memory).ok_or_else(|| vk::ERROR_OUT_OF_DEVICE_MEMORY.into());
     }
 
     #[inline]
-    pub fn flags(&self) -> MemoryFlags {
-        return self.flags;
+    unsafe fn free(&self, memory: NonZeroU64, size: vk::DeviceSize) {
+        let mem = MemoryPtr::new(memory, RawInfo { size, flags: MemoryFlags::empty() });
+        <Self as DeviceAllocator>::free(self, mem)
+    }
+
+    #[inline]
+    unsafe fn map(&self, memory: NonZeroU64, size: vk::DeviceSize) -> Result<NonNull<c_void>> {
+        let mem = MemoryPtr::new(memory, RawInfo { size, flags: MemoryFlags::empty() });
+        return <Self as DeviceAllocator>::map(self, &mem, ..).map(NonNull::cast);
     }
 
     #[inline]
-    pub(super) fn try_allocate(
+    unsafe fn unmap(&self, memory: NonZeroU64) {
+        (Entry::get().unmap_memory)(self.device().id(), memory.get())
+    }
+
+    #[inline]
+    fn non_coherent_atom_size(&self) -> vk::DeviceSize {
+        return self.0.physical().properties().limits().nonCoherentAtomSize;
+    }
+
+    fn available(&self, memory_type: u32) -> Option<vk::DeviceSize> {
+        let heap = *self.0.physical().memory_type_heaps().get(memory_type as usize)?;
+        return self.0.memory_budget().get(heap as usize).map(HeapBudget::available);
+    }
+
+    #[inline]
+    unsafe fn flush(
         &self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        _flags: MemoryFlags,
-    ) -> Result<Option<MemoryPtr<PageInfo>>> {
-        debug_assert_eq!(_flags, self.flags);
+        memory: NonZeroU64,
+        _size: vk::DeviceSize,
+        range: Range<vk::DeviceSize>,
+    ) -> Result<()> {
+        let range = mapped_range(memory, range);
+        tri! {
+            (Entry::get().flush_mapped_memory_ranges)(self.device().id(), 1, addr_of!(range))
+        }
+        return Ok(());
+    }
 
-        return match self.ranges.try_lock() {
-            Ok(mut ranges) => Self::inner_allocate(&self.inner, &mut ranges, size, align).map(Some),
-            Err(TryLockError::Poisoned(e)) => {
-                Self::inner_allocate(&self.inner, &mut e.into_inner(), size, align).map(Some)
-            }
-            Err(_) => Ok(None),
-        };
+    #[inline]
+    unsafe fn invalidate(
+        &self,
+        memory: NonZeroU64,
+        _size: vk::DeviceSize,
+        range: Range<vk::DeviceSize>,
+    ) -> Result<()> {
+        let range = mapped_range(memory, range);
+        tri! {
+            (Entry::get().invalidate_mapped_memory_ranges)(self.device().id(), 1, addr_of!(range))
+        }
+        return Ok(());
     }
+}
 
+#[inline]
+fn mapped_range(memory: NonZeroU64, range: Range<vk::DeviceSize>) -> vk::MappedMemoryRange {
+    return vk::MappedMemoryRange {
+        sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+        pNext: core::ptr::null(),
+        memory: memory.get(),
+        offset: range.start,
+        size: range.end - range.start,
+    };
+}
+
+/// Widens `range` to multiples of `atom`, as required to flush or invalidate non-coherent memory.
+/// `atom` must be a power of two, as `nonCoherentAtomSize` is.
+/// The end is clamped to the `size` of the block of memory, which Vulkan accepts even if it isn't a multiple of `atom`.
+pub fn align_to_atom(
+    range: Range<vk::DeviceSize>,
+    atom: vk::DeviceSize,
+    size: vk::DeviceSize,
+) -> Range<vk::DeviceSize> {
+    let atom = atom.max(1);
+    debug_assert!(atom.is_power_of_two());
+
+    let start = range.start & !(atom - 1);
+    let end = match range.end.checked_add(atom - 1).map(|x| x & !(atom - 1)) {
+        Some(x) => x.min(size),
+        None => size,
+    };
+    return start..end;
+}
+
+/// Range of a block of memory with `flags` to flush or invalidate for the bytes in `range` of an allocation at `offset`,
+/// or `None` if the memory is host-coherent (or the range is empty)
+#[inline]
+fn non_coherent_range(
+    flags: MemoryFlags,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    range: Range<usize>,
+    atom: impl FnOnce() -> vk::DeviceSize,
+) -> Option<Range<vk::DeviceSize>> {
+    if flags.contains(MemoryFlags::HOST_COHERENT) || range.is_empty() {
+        return None;
+    }
+
+    let range = offset + range.start as u64..offset + range.end as u64;
+    return Some(align_to_atom(range, atom(), size));
+}
+
+/// Single block of memory, sub-allocated with a [`Buddy`] allocator
+#[derive(Debug)]
+pub struct Page<B: MemoryBackend> {
+    inner: NonZeroU64,
+    size: vk::DeviceSize,
+    memory_type: u32,
+    flags: MemoryFlags,
+    /// Alignment of flushed & invalidated ranges, if the memory isn't host-coherent
+    atom: vk::DeviceSize,
+    blocks: Mutex<Buddy>,
+    mapped_ptr: AtomicPtr<c_void>,
+    backend: B,
+}
+
+impl<D: DeviceRef> Page<Raw<D>> {
     #[inline]
-    pub(super) fn allocate_mut(
-        &mut self,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-        _flags: MemoryFlags,
-    ) -> Result<MemoryPtr<PageInfo>> {
-        debug_assert_eq!(_flags, self.flags);
+    pub fn new(device: D, size: vk::DeviceSize, memory_type: u32) -> Result<Self> {
+        return Self::with_backend(Raw(device), size, memory_type);
+    }
+}
 
-        return match self.ranges.get_mut() {
-            Ok(ranges) => Self::inner_allocate(&self.inner, ranges, size, align),
-            Err(e) => {
-                Self::inner_allocate(&self.inner, e.into_inner(), size, align)
+impl<B: MemoryBackend> Page<B> {
+    #[inline]
+    pub fn with_backend(backend: B, size: vk::DeviceSize, memory_type: u32) -> Result<Self> {
+        let flags = match backend.memory_types().get(memory_type as usize) {
+            Some(x) => *x,
+            None => {
+                return Err(Error::NoMemoryType(format!(
+                    "memory type {memory_type} doesn't exist"
+                )))
             }
         };
+
+        let inner = backend.allocate(size, memory_type, None)?;
+        let atom = match flags.contains(MemoryFlags::HOST_COHERENT) {
+            true => 1,
+            false => backend.non_coherent_atom_size(),
+        };
+
+        return Ok(Self {
+            inner,
+            size,
+            memory_type,
+            atom,
+            blocks: Mutex::new(Buddy::new(size, MIN_BLOCK_SIZE)),
+            mapped_ptr: AtomicPtr::new(UNINIT),
+            backend,
+            flags,
+        });
     }
 
-    fn inner_allocate(
-        inner: &MemoryPtr<RawInfo>,
-        ranges: &mut Vec<Range<vk::DeviceSize>>,
-        size: vk::DeviceSize,
-        align: vk::DeviceSize,
-    ) -> Result<MemoryPtr<PageInfo>> {
-        let mut result = None;
-        for i in 0..ranges.len() {
-            let range = unsafe { ranges.get_unchecked(i) };
-            let padding = range.start % align;
-            let chunk_size = range.end - range.start;
-            if chunk_size > padding + size {
-                result = Some((i, padding))
-            }
-        }
+    /// Size a page must have to fit an allocation of `size` bytes aligned to `align`
+    #[inline]
+    pub fn required_size(size: vk::DeviceSize, align: vk::DeviceSize) -> Option<vk::DeviceSize> {
+        return Buddy::block_size(size, align, MIN_BLOCK_SIZE);
+    }
 
-        if let Some((i, padding)) = result {
-            let mut range = unsafe { ranges.get_unchecked_mut(i) };
+    #[inline]
+    pub fn id(&self) -> u64 {
+        return self.inner.get();
+    }
 
-            if padding == 0 {
-                let start = range.start;
-                let end = range.start + size;
+    #[inline]
+    pub fn size(&self) -> vk::DeviceSize {
+        return self.size;
+    }
 
-                range.start = end;
-                return unsafe {
-                    Ok(MemoryPtr::new(
-                        inner.inner,
-                        PageInfo { range: start..end },
-                    ))
-                };
-            } else {
-                let start = range.start + padding;
-                let end = start + size;
-
-                let prev_end = core::mem::replace(&mut range.end, padding);
-                ranges.push(end..prev_end);
-
-                return unsafe {
-                    Ok(MemoryPtr::new(
-                        inner.inner,
-                        PageInfo { range: start..end },
-                    ))
-                };
-            }
-        } else {
-            return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into());
-        }
+    #[inline]
+    pub fn memory_type(&self) -> u32 {
+        return self.memory_type;
     }
-}
 
-unsafe impl<D: DeviceRef> DeviceAllocator for Page<D> {
-    type Device = D;
-    type Metadata = PageInfo;
+    #[inline]
+    pub fn flags(&self) -> MemoryFlags {
+        return self.flags;
+    }
 
+    /// Whether the page's memory type can back an allocation for `requirements` & `usage`
     #[inline]
-    fn owned_device(&self) -> Self::Device
-    where
-        Self::Device: Clone,
-    {
-        return self.alloc.owned_device();
+    pub fn supports(&self, requirements: &MemoryRequirements, usage: &MemoryUsage) -> bool {
+        return requirements.type_bits & (1 << self.memory_type) != 0 && usage.accepts(self.flags);
     }
 
     #[inline]
-    fn device(&self) -> &Device {
-        return self.alloc.device();
+    pub fn backend(&self) -> &B {
+        return &self.backend;
     }
 
+    /// Current occupancy of the page
     #[inline]
-    fn allocate(
+    pub fn metrics(&self) -> BuddyMetrics {
+        return match self.blocks.lock() {
+            Ok(x) => x.metrics(),
+            Err(e) => e.into_inner().metrics(),
+        };
+    }
+
+    /// Allocates `size` bytes aligned to `align`, waiting for other threads using the page
+    #[inline]
+    pub fn allocate_block(
         &self,
         size: vk::DeviceSize,
         align: vk::DeviceSize,
-        _flags: MemoryFlags,
     ) -> Result<MemoryPtr<PageInfo>> {
-        debug_assert_eq!(_flags, self.flags);
-        let mut ranges = match self.ranges.lock() {
+        let mut blocks = match self.blocks.lock() {
             Ok(x) => x,
             Err(e) => e.into_inner(),
         };
 
-        return Self::inner_allocate(&self.inner, &mut ranges, size, align);
+        return Self::inner_allocate(self.inner, &mut blocks, size, align);
     }
 
+    /// Allocates `size` bytes aligned to `align`, returning `None` if another thread is using the page
     #[inline]
-    unsafe fn free(&self, ptr: MemoryPtr<PageInfo>) {
-        let mut ranges = match self.ranges.lock() {
+    pub fn try_allocate(
+        &self,
+        size: vk::DeviceSize,
+        align: vk::DeviceSize,
+    ) -> Result<Option<MemoryPtr<PageInfo>>> {
+        return match self.blocks.try_lock() {
+            Ok(mut blocks) => Self::inner_allocate(self.inner, &mut blocks, size, align).map(Some),
+            Err(TryLockError::Poisoned(e)) => {
+                Self::inner_allocate(self.inner, &mut e.into_inner(), size, align).map(Some)
+            }
+            Err(_) => Ok(None),
+        };
+    }
+
+    /// # Safety
+    /// `ptr` must have been allocated by this page
+    #[inline]
+    pub unsafe fn free_block(&self, ptr: MemoryPtr<PageInfo>) {
+        let mut blocks = match self.blocks.lock() {
             Ok(x) => x,
             Err(e) => e.into_inner(),
         };
 
-        let mut ptr_range = ptr._meta.range;
-        let mut i = 0;
-
-        while i < ranges.len() {
-            let range = unsafe { ranges.get_unchecked_mut(i) };
-
-            if range.start == ptr_range.end {
-                range.start = ptr_range.start;
-                ptr_range = ranges.swap_remove(i);
-                i = 0;
-                continue;
-            }
-
-            if range.end == ptr_range.start {
-                range.end = ptr_range.end;
-                ptr_range = ranges.swap_remove(i);
-                i = 0;
-                continue;
-            }
-
-            i += 1;
-        }
-
-        ranges.push(ptr_range)
+        let PageInfo { range, order } = ptr._meta;
+        blocks.free(BuddyBlock { offset: range.start, order }, range.end - range.start);
     }
 
-    unsafe fn map(
+    /// Maps the `bounds` of `mem`. The whole page is mapped the first time, and stays mapped until it's dropped.
+    ///
+    /// # Safety
+    /// `mem` must have been allocated by this page, and Rust's borrowing rules must be followed for the maps
+    pub unsafe fn map_block(
         &self,
-        mem: &MemoryPtr<Self::Metadata>,
+        mem: &MemoryPtr<PageInfo>,
         bounds: impl RangeBounds<usize>,
     ) -> Result<NonNull<[u8]>> {
         // Obtained general mapped pointer
@@ -656,8 +832,8 @@ unsafe impl<D: DeviceRef> DeviceAllocator for Page<D> {
             ) {
                 // Map the full region
                 Ok(_) => {
-                    let ptr = match self.alloc.map(&self.inner, ..) {
-                        Ok(x) => x.cast::<c_void>(),
+                    let ptr = match self.backend.map(self.inner, self.size) {
+                        Ok(x) => x,
                         Err(e) => {
                             self.mapped_ptr.store(UNINIT, Ordering::Release);
                             return Err(e);
@@ -675,235 +851,632 @@ unsafe impl<D: DeviceRef> DeviceAllocator for Page<D> {
             }
         };
 
-        // Calculate start & end points
-        let offset = u64_to_usize(mem._meta.range.start);
-        let start = offset
-            + match bounds.start_bound() {
-                Bound::Excluded(x) => *x + 1,
-                Bound::Included(x) => *x,
-                Bound::Unbounded => 0,
-            };
-        let end = match bounds.end_bound() {
-            Bound::Excluded(x) => offset + *x,
-            Bound::Included(x) => offset + *x + 1,
-            Bound::Unbounded => u64_to_usize(mem._meta.range.end),
+        return slice_mapping(ptr, mem._meta.range.clone(), bounds);
+    }
+
+    /// Makes host writes to `range` of `mem` visible to the device, if the page isn't host-coherent
+    ///
+    /// # Safety
+    /// `mem` must have been allocated by this page, and mapped
+    #[inline]
+    pub unsafe fn flush_block(&self, mem: &MemoryPtr<PageInfo>, range: Range<usize>) -> Result<()> {
+        return match non_coherent_range(self.flags, mem._meta.range.start, self.size, range, || self.atom) {
+            Some(range) => self.backend.flush(self.inner, self.size, range),
+            None => Ok(()),
         };
+    }
 
-        // Check that mapped bounds are contained inside buffer
-        if (start as u64) > mem._meta.range.end || (end as u64) > mem._meta.range.end {
-            #[cfg(debug_assertions)]
-            eprintln!("Bounds overflow");
-            return Err(vk::ERROR_MEMORY_MAP_FAILED.into());
+    /// Makes device writes to `range` of `mem` visible to the host, if the page isn't host-coherent
+    ///
+    /// # Safety
+    /// `mem` must have been allocated by this page, and mapped
+    #[inline]
+    pub unsafe fn invalidate_block(&self, mem: &MemoryPtr<PageInfo>, range: Range<usize>) -> Result<()> {
+        return match non_coherent_range(self.flags, mem._meta.range.start, self.size, range, || self.atom) {
+            Some(range) => self.backend.invalidate(self.inner, self.size, range),
+            None => Ok(()),
+        };
+    }
+
+    #[inline]
+    fn inner_allocate(
+        inner: NonZeroU64,
+        blocks: &mut Buddy,
+        size: vk::DeviceSize,
+        align: vk::DeviceSize,
+    ) -> Result<MemoryPtr<PageInfo>> {
+        return match blocks.allocate(size, align) {
+            Some(BuddyBlock { offset, order }) => unsafe {
+                Ok(MemoryPtr::new(
+                    inner,
+                    PageInfo {
+                        range: offset..offset + size,
+                        order,
+                    },
+                ))
+            },
+            None => Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into()),
+        };
+    }
+}
+
+unsafe impl<D: DeviceRef> DeviceAllocator for Page<Raw<D>> {
+    type Device = D;
+    type Metadata = PageInfo;
+
+    #[inline]
+    fn owned_device(&self) -> Self::Device
+    where
+        Self::Device: Clone,
+    {
+        return self.backend.owned_device();
+    }
+
+    #[inline]
+    fn device(&self) -> &Device {
+        return self.backend.device();
+    }
+
+    #[inline]
+    fn allocate(
+        &self,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
+    ) -> Result<MemoryPtr<PageInfo>> {
+        if !self.supports(requirements, usage) {
+            return Err(Error::NoMemoryType(format!(
+                "the page's memory type {} ({:?}) can't back the allocation",
+                self.memory_type, self.flags
+            )));
         }
 
-        let ptr = ptr.as_ptr().byte_add(start);
-        debug_assert!(!ptr.is_null());
+        return self.allocate_block(requirements.size, requirements.align);
+    }
+
+    #[inline]
+    unsafe fn free(&self, ptr: MemoryPtr<PageInfo>) {
+        self.free_block(ptr)
+    }
 
-        return Ok(NonNull::new_unchecked(core::ptr::from_raw_parts_mut(
-            ptr.cast(),
-            end - start,
-        )));
+    #[inline]
+    unsafe fn map(
+        &self,
+        mem: &MemoryPtr<Self::Metadata>,
+        bounds: impl RangeBounds<usize>,
+    ) -> Result<NonNull<[u8]>> {
+        return self.map_block(mem, bounds);
     }
 
     #[inline]
     unsafe fn unmap(&self, _mem: &MemoryPtr<Self::Metadata>) {
         // noop
     }
+
+    #[inline]
+    unsafe fn flush(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return self.flush_block(mem, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, mem: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return self.invalidate_block(mem, range);
+    }
 }
 
-impl<D: DeviceRef> Drop for Page<D> {
+impl<B: MemoryBackend> Drop for Page<B> {
     #[inline]
     fn drop(&mut self) {
         unsafe {
             match *self.mapped_ptr.get_mut() {
                 UNINIT | INITIALIZING => {}
-                _ => self.alloc.unmap(&self.inner),
+                _ => self.backend.unmap(self.inner),
             }
-            self.alloc.free(ManuallyDrop::take(&mut self.inner))
+            self.backend.free(self.inner, self.size)
         }
     }
 }
 
-#[repr(transparent)]
-struct StandardDevice(*const Device);
-
-impl Deref for StandardDevice {
-    type Target = Device;
-
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*self.0 }
+/// Slices the `bounds` of `range` out of the mapping of a whole block of memory, starting at `ptr`
+unsafe fn slice_mapping(
+    ptr: NonNull<c_void>,
+    range: Range<vk::DeviceSize>,
+    bounds: impl RangeBounds<usize>,
+) -> Result<NonNull<[u8]>> {
+    // Calculate start & end points
+    let offset = u64_to_usize(range.start);
+    let start = offset
+        + match bounds.start_bound() {
+            Bound::Excluded(x) => *x + 1,
+            Bound::Included(x) => *x,
+            Bound::Unbounded => 0,
+        };
+    let end = match bounds.end_bound() {
+        Bound::Excluded(x) => offset + *x,
+        Bound::Included(x) => offset + *x + 1,
+        Bound::Unbounded => u64_to_usize(range.end),
+    };
+
+    // Check that mapped bounds are contained inside buffer
+    if start > end || (end as u64) > range.end {
+        #[cfg(debug_assertions)]
+        eprintln!("Bounds overflow");
+        return Err(vk::ERROR_MEMORY_MAP_FAILED.into());
     }
-}
 
-unsafe impl Send for StandardDevice where for<'a> &'a Device: Send {}
-unsafe impl Sync for StandardDevice where for<'a> &'a Device: Sync {}
+    let ptr = ptr.as_ptr().byte_add(start);
+    debug_assert!(!ptr.is_null());
 
-pub struct Book<D: DeviceRef> {
-    pages: UpQueue<once_cell::sync::OnceCell<Page<StandardDevice>>>,
-    device: Pin<D>,
-    range: Range<DeviceSize>
+    return Ok(NonNull::new_unchecked(core::ptr::from_raw_parts_mut(
+        ptr.cast(),
+        end - start,
+    )));
 }
 
-impl<D: DeviceRef> Book<D> {
-    #[inline]
-    pub fn new(device: D, min_size: Option<NonZeroU64>, max_pages: Option<NonZeroUsize>) -> Self
-    where
-        D: Unpin,
-    {
-        Self::new_pinned(Pin::new(device), min_size, max_pages)
-    }
-
-    #[inline]
-    pub unsafe fn new_unchecked(
-        device: D,
-        min_size: Option<NonZeroU64>,
-        max_pages: Option<NonZeroUsize>,
-    ) -> Self {
-        Self::new_pinned(Pin::new_unchecked(device), min_size, max_pages)
-    }
+/// Growable set of [`Page`]s, creating new ones as the existing ones fill up.
+/// Allocations too large to share a page get a block of memory of their own.
+pub struct Book<B: MemoryBackend> {
+    pages: UpQueue<OnceCell<Page<B>>>,
+    /// Flags of each of the backend's memory types
+    types: Vec<MemoryFlags>,
+    backend: B,
+    range: Range<DeviceSize>,
+    /// Live allocations, paged or dedicated
+    allocations: AtomicUsize,
+    dedicated: AtomicUsize,
+    dedicated_bytes: AtomicU64,
+    /// Incremented on every free, for the tasks waiting on memory to tell whether they missed one
+    frees: AtomicU64,
+    waiters: Mutex<Vec<Waker>>,
+}
 
-    #[inline]
-    pub fn new_pinned(
-        device: Pin<D>,
-        min_size: Option<NonZeroU64>,
-        max_pages: Option<NonZeroUsize>,
-    ) -> Self {
-        let props = once_cell::unsync::Lazy::new(|| device.physical().properties());
+impl<D: DeviceRef + Clone> Book<Raw<D>> {
+    /// Creates a book whose pages are at least `min_size` bytes, up to `max_pages` of them (the maximum allocation count, by default).
+    ///
+    /// By default, pages are [`DEFAULT_PAGE_SIZE`] bytes, or a quarter of the smallest heap budget if that's less,
+    /// so that a single page can't take up a heap.
+    pub fn new(device: D, min_size: Option<NonZeroU64>, max_pages: Option<NonZeroUsize>) -> Self {
+        let props = device.physical().properties();
 
         let max_pages = match max_pages {
             Some(x) => x.get(),
-            None => usize::max(
-                1,
-                props.limits().maxMemoryAllocationCount as usize,
-            )
+            None => usize::max(1, props.limits().maxMemoryAllocationCount as usize),
         };
 
         let max_size = u64::max(1, props.max_allocation_size());
         let min_size = match min_size {
             Some(x) => x.get(),
-            None => max_size,
+            None => device
+                .memory_budget()
+                .iter()
+                .filter(|x| x.budget > 0)
+                .map(|x| x.budget / 4)
+                .fold(DEFAULT_PAGE_SIZE, u64::min)
+                .max(MIN_BLOCK_SIZE)
+                .min(max_size),
         };
 
+        return Self::with_backend(Raw(device), min_size..max_size, max_pages);
+    }
+
+    /// Current budget of each of the device's memory heaps, see [`Device::memory_budget`](crate::device::Device::memory_budget)
+    #[inline]
+    pub fn budget(&self) -> Vec<HeapBudget> {
+        return self.backend.0.memory_budget();
+    }
+}
+
+impl<B: MemoryBackend> Book<B> {
+    /// Creates a book with pages of at least `sizes.start` bytes, that can fit allocations of up to `sizes.end` bytes
+    #[inline]
+    pub fn with_backend(backend: B, sizes: Range<DeviceSize>, max_pages: usize) -> Self {
         return Self {
-            pages: UpQueue::new(max_pages),
-            device,
-            range: min_size..max_size
+            pages: UpQueue::new(usize::max(1, max_pages)),
+            types: backend.memory_types(),
+            backend,
+            range: sizes,
+            allocations: AtomicUsize::new(0),
+            dedicated: AtomicUsize::new(0),
+            dedicated_bytes: AtomicU64::new(0),
+            frees: AtomicU64::new(0),
+            waiters: Mutex::new(Vec::new()),
         };
     }
-}
 
-unsafe impl<D: DeviceRef> DeviceAllocator for Book<D> {
-    type Device = Pin<D>;
-    type Metadata = BookInfo;
+    #[inline]
+    pub fn backend(&self) -> &B {
+        return &self.backend;
+    }
 
+    /// Flags of each of the backend's memory types, by memory type index
     #[inline]
-    fn owned_device(&self) -> Self::Device
+    pub fn memory_types(&self) -> &[MemoryFlags] {
+        return &self.types;
+    }
+
+    /// Pages created so far
+    #[inline]
+    pub fn pages(&self) -> impl Iterator<Item = &Page<B>> {
+        return self.pages.iter().filter_map(OnceCell::get);
+    }
+
+    /// Current usage of the book's memory
+    pub fn stats(&self) -> BookStats {
+        let mut stats = BookStats {
+            allocations: self.allocations.load(Ordering::Acquire),
+            dedicated: self.dedicated.load(Ordering::Acquire),
+            dedicated_bytes: self.dedicated_bytes.load(Ordering::Acquire),
+            ..Default::default()
+        };
+
+        let mut largest_free_blocks = 0;
+        for metrics in self.pages().map(Page::metrics) {
+            stats.pages += 1;
+            stats.page_bytes += metrics.size;
+            stats.used += metrics.used;
+            stats.requested += metrics.requested;
+            stats.largest_free_block = stats.largest_free_block.max(metrics.largest_free_block);
+            largest_free_blocks += metrics.largest_free_block;
+        }
+
+        stats.fragmentation = match stats.page_bytes - stats.used {
+            0 => 0.0,
+            free => 1.0 - (largest_free_blocks as f64 / free as f64),
+        };
+
+        return stats;
+    }
+
+    /// Allocates memory for `requirements`, from the first memory type selected for `usage` that isn't out of memory.
+    ///
+    /// Allocations of at least half the minimum page size are dedicated, unless `usage` says otherwise.
+    /// The others come from a page of the memory type, creating a new page if none has room.
+    pub fn allocate_block(
+        &self,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
+    ) -> Result<MemoryPtr<BookInfo>>
     where
-        Self::Device: Clone,
+        B: Clone,
     {
-        return self.device.clone();
+        let dedicated = usage.is_dedicated(requirements, self.range.start / 2);
+        let ptr = allocate_with_fallback(&self.types, requirements, usage, |memory_type| {
+            match dedicated {
+                true => self.allocate_dedicated(requirements, memory_type),
+                false => self.allocate_paged(requirements.size, requirements.align, memory_type),
+            }
+        })?;
+
+        self.allocations.fetch_add(1, Ordering::AcqRel);
+        return Ok(ptr);
     }
 
+    /// Allocates memory like [`allocate_block`](Book::allocate_block), but once every memory type is out of memory,
+    /// waits for other allocations to be freed and tries again, instead of failing.
+    ///
+    /// Still fails with `ERROR_OUT_OF_DEVICE_MEMORY` if no live allocation could make room once freed,
+    /// such as when the book only holds blocks of pages of other memory types, since pages are never released.
+    pub async fn allocate_block_async(
+        &self,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
+    ) -> Result<MemoryPtr<BookInfo>>
+    where
+        B: Clone,
+    {
+        loop {
+            let frees = self.frees.load(Ordering::Acquire);
+            match self.allocate_block(requirements, usage) {
+                Err(Error::Vulkan(vk::ERROR_OUT_OF_DEVICE_MEMORY))
+                    if self.frees.load(Ordering::Acquire) != frees || self.may_free(requirements, usage) => {}
+                other => return other,
+            }
+
+            core::future::poll_fn(|cx| {
+                let mut waiters = match self.waiters.lock() {
+                    Ok(x) => x,
+                    Err(e) => e.into_inner(),
+                };
+
+                // Checked with the lock held, so a free can't slip in between the check & the registration
+                if self.frees.load(Ordering::Acquire) != frees {
+                    return Poll::Ready(());
+                }
+
+                if !waiters.iter().any(|x| x.will_wake(cx.waker())) {
+                    waiters.push(cx.waker().clone());
+                }
+                return Poll::Pending;
+            })
+            .await;
+        }
+    }
+
+    /// Whether freeing one of the live allocations could make room for `requirements` & `usage`.
+    ///
+    /// Freeing a dedicated allocation returns its memory to the heap, which helps dedicated allocations,
+    /// and paged ones if a new page can still be created. Freeing a paged allocation only helps if its page's memory type fits.
+    fn may_free(&self, requirements: &MemoryRequirements, usage: &MemoryUsage) -> bool {
+        let dedicated = self.dedicated.load(Ordering::Acquire) > 0;
+        if usage.is_dedicated(requirements, self.range.start / 2) {
+            return dedicated;
+        }
+
+        let mut full = self.pages.len() == self.pages.capacity();
+        for page in self.pages.iter() {
+            match page.get() {
+                Some(page) if page.supports(requirements, usage) && page.metrics().used > 0 => return true,
+                Some(_) => {}
+                None => full = false,
+            }
+        }
+
+        return dedicated && !full;
+    }
+
+    /// # Safety
+    /// `ptr` must have been allocated by this book
     #[inline]
-    fn device(&self) -> &Device {
-        return &self.device;
+    pub unsafe fn free_block(&self, ptr: MemoryPtr<BookInfo>) {
+        match ptr._meta.location {
+            BookLocation::Page { page_idx, page_info } => {
+                match self.pages.get(page_idx).and_then(OnceCell::get) {
+                    Some(page) => page.free_block(MemoryPtr::new(ptr.inner, page_info)),
+                    #[cfg(debug_assertions)]
+                    None => eprintln!("Invalid page index provided"),
+                    #[cfg(not(debug_assertions))]
+                    None => {}
+                }
+            }
+            BookLocation::Dedicated { size, mapped, .. } => {
+                if mapped.is_some() {
+                    self.backend.unmap(ptr.inner);
+                }
+                self.backend.free(ptr.inner, size);
+                self.dedicated.fetch_sub(1, Ordering::AcqRel);
+                self.dedicated_bytes.fetch_sub(size, Ordering::AcqRel);
+            }
+        }
+
+        self.allocations.fetch_sub(1, Ordering::AcqRel);
+        self.frees.fetch_add(1, Ordering::AcqRel);
+
+        let waiters = match self.waiters.lock() {
+            Ok(mut x) => core::mem::take(&mut *x),
+            Err(e) => core::mem::take(&mut *e.into_inner()),
+        };
+        waiters.into_iter().for_each(Waker::wake);
     }
 
-    fn allocate(
+    /// # Safety
+    /// `ptr` must have been allocated by this book, and Rust's borrowing rules must be followed for the maps
+    pub unsafe fn map_block(
+        &self,
+        ptr: &MemoryPtr<BookInfo>,
+        bounds: impl RangeBounds<usize>,
+    ) -> Result<NonNull<[u8]>> {
+        match &ptr._meta.location {
+            BookLocation::Page { page_idx, page_info } => {
+                if let Some(page) = self.pages.get(*page_idx).and_then(OnceCell::get) {
+                    return page.map_block(&MemoryPtr::new(ptr.inner, page_info.clone()), bounds);
+                }
+
+                #[cfg(debug_assertions)]
+                eprintln!("Invalid page index provided");
+                return Err(vk::ERROR_MEMORY_MAP_FAILED.into());
+            }
+            BookLocation::Dedicated {
+                size,
+                mapped: Some(mapped),
+                ..
+            } => return slice_mapping(*mapped, 0..*size, bounds),
+            BookLocation::Dedicated { mapped: None, .. } => {
+                return Err(vk::ERROR_MEMORY_MAP_FAILED.into())
+            }
+        }
+    }
+
+    /// Makes host writes to `range` of `ptr` visible to the device, if its memory isn't host-coherent
+    ///
+    /// # Safety
+    /// `ptr` must have been allocated by this book, and mapped
+    pub unsafe fn flush_block(&self, ptr: &MemoryPtr<BookInfo>, range: Range<usize>) -> Result<()> {
+        return match &ptr._meta.location {
+            BookLocation::Page { page_idx, page_info } => match self.pages.get(*page_idx).and_then(OnceCell::get) {
+                Some(page) => page.flush_block(&MemoryPtr::new(ptr.inner, page_info.clone()), range),
+                None => Err(vk::ERROR_MEMORY_MAP_FAILED.into()),
+            },
+            BookLocation::Dedicated { size, flags, .. } => {
+                match non_coherent_range(*flags, 0, *size, range, || self.backend.non_coherent_atom_size()) {
+                    Some(range) => self.backend.flush(ptr.inner, *size, range),
+                    None => Ok(()),
+                }
+            }
+        };
+    }
+
+    /// Makes device writes to `range` of `ptr` visible to the host, if its memory isn't host-coherent
+    ///
+    /// # Safety
+    /// `ptr` must have been allocated by this book, and mapped
+    pub unsafe fn invalidate_block(&self, ptr: &MemoryPtr<BookInfo>, range: Range<usize>) -> Result<()> {
+        return match &ptr._meta.location {
+            BookLocation::Page { page_idx, page_info } => match self.pages.get(*page_idx).and_then(OnceCell::get) {
+                Some(page) => page.invalidate_block(&MemoryPtr::new(ptr.inner, page_info.clone()), range),
+                None => Err(vk::ERROR_MEMORY_MAP_FAILED.into()),
+            },
+            BookLocation::Dedicated { size, flags, .. } => {
+                match non_coherent_range(*flags, 0, *size, range, || self.backend.non_coherent_atom_size()) {
+                    Some(range) => self.backend.invalidate(ptr.inner, *size, range),
+                    None => Ok(()),
+                }
+            }
+        };
+    }
+
+    /// Allocates a block of `memory_type` of its own, which stays mapped for as long as it lives if it's host-visible
+    fn allocate_dedicated(
+        &self,
+        requirements: &MemoryRequirements,
+        memory_type: u32,
+    ) -> Result<MemoryPtr<BookInfo>> {
+        let size = requirements.size;
+        let inner = self.backend.allocate(size, memory_type, requirements.buffer)?;
+
+        let flags = self.types[memory_type as usize];
+        let mapped = match flags.contains(MemoryFlags::HOST_VISIBLE) {
+            true => match unsafe { self.backend.map(inner, size) } {
+                Ok(x) => Some(x),
+                Err(e) => {
+                    unsafe { self.backend.free(inner, size) };
+                    return Err(e);
+                }
+            },
+            false => None,
+        };
+
+        self.dedicated.fetch_add(1, Ordering::AcqRel);
+        self.dedicated_bytes.fetch_add(size, Ordering::AcqRel);
+        return unsafe {
+            Ok(MemoryPtr::new(
+                inner,
+                BookInfo {
+                    location: BookLocation::Dedicated {
+                        size,
+                        flags,
+                        mapped,
+                    },
+                },
+            ))
+        };
+    }
+
+    /// Allocates `size` bytes aligned to `align` from a page of `memory_type`, creating a new page if none has room
+    fn allocate_paged(
         &self,
         size: vk::DeviceSize,
         align: vk::DeviceSize,
-        flags: MemoryFlags,
-    ) -> Result<MemoryPtr<Self::Metadata>> {
-        if size > self.range.end {
-            #[cfg(debug_assertions)]
-            eprintln!("Tried to allocate too much memory: {} bytes of a maximum of {}", size, self.range.end);
-            return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into())
-        }
+        memory_type: u32,
+    ) -> Result<MemoryPtr<BookInfo>>
+    where
+        B: Clone,
+    {
+        let page_size = match Page::<B>::required_size(size, align) {
+            Some(x) if x <= self.range.end => u64::max(self.range.start, x),
+            _ => {
+                #[cfg(debug_assertions)]
+                eprintln!("Tried to allocate too much memory: {} bytes of a maximum of {}", size, self.range.end);
+                return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into())
+            }
+        };
 
         loop {
-            let mut all_without_mem = true;
+            // Pages other threads are using, only waited on once the others can't fit the allocation
+            let mut busy = Vec::new();
             let iter = self.pages.iter_indexed()
                 .filter_map(|(i, x)| x.get().map(|x| (i, x)))
-                .filter(|(_, x)| x.flags == flags);
+                .filter(|(_, x)| x.memory_type == memory_type);
 
             for (idx, page) in iter {
-                match page.try_allocate(size, align, flags) {
+                match page.try_allocate(size, align) {
                     Ok(Some(MemoryPtr { inner, _meta, .. })) => {
-                        return unsafe {
-                            Ok(MemoryPtr::new(
-                                inner,
-                                BookInfo {
-                                    page_idx: idx,
-                                    page_info: _meta,
-                                },
-                            ))
-                        }
+                        return unsafe { Ok(MemoryPtr::new(inner, BookInfo::page(idx, _meta))) }
                     }
-                    Ok(None) => all_without_mem = false,
+                    Ok(None) => busy.push((idx, page)),
                     Err(Error::Vulkan(vk::ERROR_OUT_OF_DEVICE_MEMORY)) => {}
                     Err(e) => return Err(e),
                 }
             }
 
-            if all_without_mem {
-                if let Ok((idx, cell)) = self.pages.try_push(OnceCell::new()) {
-                    unsafe {
-                        let mut page = Page::new(
-                            StandardDevice(self.device.deref()),
-                            u64::max(self.range.start, size),
-                            flags,
-                        )?;
-
-                        let MemoryPtr { inner, _meta, .. } = page.allocate_mut(size, align, flags)?;
-                        let _ = cell.set(page).unwrap_unchecked();
-
-                        return Ok(MemoryPtr::new(
-                            inner,
-                            BookInfo {
-                                page_idx: idx,
-                                page_info: _meta,
-                            },
-                        ))
+            for (idx, page) in busy {
+                match page.allocate_block(size, align) {
+                    Ok(MemoryPtr { inner, _meta, .. }) => {
+                        return unsafe { Ok(MemoryPtr::new(inner, BookInfo::page(idx, _meta))) }
                     }
+                    Err(Error::Vulkan(vk::ERROR_OUT_OF_DEVICE_MEMORY)) => {}
+                    Err(e) => return Err(e),
                 }
             }
 
-            core::hint::spin_loop();
+            // A page that doesn't fit in its heap's budget could fail or evict other memory, so the next memory type is tried instead
+            if self.backend.available(memory_type).map_or(false, |x| x < page_size) {
+                return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into());
+            }
+
+            // Slots whose page failed to be created are reused before growing the queue
+            let empty = self.pages.iter_indexed().find(|(_, x)| x.get().is_none());
+            let (idx, cell) = match empty.map_or_else(|| self.pages.try_push(OnceCell::new()).ok(), Some) {
+                Some(x) => x,
+                None => return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into()),
+            };
+
+            // Another thread may create the page first, in which case it may not have room (or be of another type) anymore
+            let page = cell.get_or_try_init(|| Page::with_backend(self.backend.clone(), page_size, memory_type))?;
+            if page.memory_type == memory_type {
+                match page.allocate_block(size, align) {
+                    Ok(MemoryPtr { inner, _meta, .. }) => {
+                        return unsafe { Ok(MemoryPtr::new(inner, BookInfo::page(idx, _meta))) }
+                    }
+                    Err(Error::Vulkan(vk::ERROR_OUT_OF_DEVICE_MEMORY)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
         }
     }
+}
+
+unsafe impl<D: DeviceRef + Clone> DeviceAllocator for Book<Raw<D>> {
+    type Device = D;
+    type Metadata = BookInfo;
+
+    #[inline]
+    fn owned_device(&self) -> Self::Device
+    where
+        Self::Device: Clone,
+    {
+        return self.backend.owned_device();
+    }
+
+    #[inline]
+    fn device(&self) -> &Device {
+        return self.backend.device();
+    }
+
+    #[inline]
+    fn allocate(
+        &self,
+        requirements: &MemoryRequirements,
+        usage: &MemoryUsage,
+    ) -> Result<MemoryPtr<Self::Metadata>> {
+        return self.allocate_block(requirements, usage);
+    }
 
     #[inline]
     unsafe fn free(&self, ptr: MemoryPtr<Self::Metadata>) {
-        if let Some(page) = self.pages.get(ptr._meta.page_idx).and_then(OnceCell::get) {
-            page.free(MemoryPtr { inner: ptr.inner, _meta: ptr._meta.page_info, _phtm: PhantomData });
-        }
-        #[cfg(debug_assertions)]
-        eprintln!("Invalid page index provided");
+        self.free_block(ptr)
     }
 
+    #[inline]
     unsafe fn map(
         &self,
         ptr: &MemoryPtr<Self::Metadata>,
         bounds: impl RangeBounds<usize>,
     ) -> Result<NonNull<[u8]>> {
-        if let Some(page) = self.pages.get(ptr._meta.page_idx).and_then(OnceCell::get) {
-            return page.map(&MemoryPtr { inner: ptr.inner, _meta: ptr._meta.page_info.clone(), _phtm: PhantomData }, bounds);
-        }
+        return self.map_block(ptr, bounds);
+    }
 
-        #[cfg(debug_assertions)]
-        eprintln!("Invalid page index provided");
-        return Err(vk::ERROR_MEMORY_MAP_FAILED.into())
+    #[inline]
+    unsafe fn unmap(&self, _ptr: &MemoryPtr<Self::Metadata>) {
+        // noop, pages & dedicated blocks stay mapped
     }
 
     #[inline]
-    unsafe fn unmap(&self, ptr: &MemoryPtr<Self::Metadata>) {
-        if let Some(page) = self.pages.get(ptr._meta.page_idx).and_then(OnceCell::get) {
-            page.unmap(&MemoryPtr { inner: ptr.inner, _meta: ptr._meta.page_info.clone(), _phtm: PhantomData });
-        }
-        #[cfg(debug_assertions)]
-        eprintln!("Invalid page index provided");
+    unsafe fn flush(&self, ptr: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return self.flush_block(ptr, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, ptr: &MemoryPtr<Self::Metadata>, range: Range<usize>) -> Result<()> {
+        return self.invalidate_block(ptr, range);
     }
 }
 
@@ -911,6 +1484,7 @@ unsafe impl<D: DeviceRef> DeviceAllocator for Book<D> {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RawInfo {
     size: vk::DeviceSize,
+    flags: MemoryFlags,
 }
 
 impl MemoryMetadata for RawInfo {
@@ -924,6 +1498,8 @@ impl MemoryMetadata for RawInfo {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PageInfo {
     range: Range<vk::DeviceSize>,
+    /// Order of the buddy block holding the range
+    order: u32,
 }
 
 impl MemoryMetadata for PageInfo {
@@ -936,14 +1512,76 @@ impl MemoryMetadata for PageInfo {
 /// Metadata for [`Book`]-allocated memory
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BookInfo {
-    page_idx: usize,
-    page_info: PageInfo,
+    location: BookLocation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BookLocation {
+    Page {
+        page_idx: usize,
+        page_info: PageInfo,
+    },
+    Dedicated {
+        size: vk::DeviceSize,
+        flags: MemoryFlags,
+        mapped: Option<NonNull<c_void>>,
+    },
+}
+
+impl BookInfo {
+    #[inline]
+    fn page(page_idx: usize, page_info: PageInfo) -> Self {
+        return Self {
+            location: BookLocation::Page {
+                page_idx,
+                page_info,
+            },
+        };
+    }
+
+    /// Whether the memory is a block of its own, instead of part of a page
+    #[inline]
+    pub fn is_dedicated(&self) -> bool {
+        return matches!(self.location, BookLocation::Dedicated { .. });
+    }
 }
 
 impl MemoryMetadata for BookInfo {
     #[inline]
     fn range(&self) -> Range<vk::DeviceSize> {
-        self.page_info.range()
+        match &self.location {
+            BookLocation::Page { page_info, .. } => page_info.range(),
+            BookLocation::Dedicated { size, .. } => 0..*size,
+        }
+    }
+}
+
+/// Snapshot of the memory held by a [`Book`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BookStats {
+    /// Live allocations, paged or dedicated
+    pub allocations: usize,
+    pub pages: usize,
+    /// Combined size of the pages
+    pub page_bytes: DeviceSize,
+    /// Bytes of the pages taken by allocated blocks
+    pub used: DeviceSize,
+    /// Bytes requested by the paged allocations, which may be less than their blocks
+    pub requested: DeviceSize,
+    /// Largest block that can be allocated from an existing page
+    pub largest_free_block: DeviceSize,
+    /// Share of the pages' free memory that can't be handed out in a single block, from 0 (none) to 1
+    pub fragmentation: f64,
+    /// Allocations with a block of memory of their own
+    pub dedicated: usize,
+    pub dedicated_bytes: DeviceSize,
+}
+
+impl BookStats {
+    /// Bytes allocated from the backend, by pages & dedicated allocations
+    #[inline]
+    pub fn allocated(&self) -> DeviceSize {
+        return self.page_bytes + self.dedicated_bytes;
     }
 }
 
@@ -954,7 +1592,7 @@ bitflags::bitflags! {
         const DEVICE_LOCAL = vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT;
         /// Memory is mappable by host
         const HOST_VISIBLE = vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT;
-        /// Memory will have i/o coherency. If not set, application may need to use `vkFlushMappedMemoryRanges` and `vkInvalidateMappedMemoryRanges` to flush/invalidate host cache
+        /// Memory will have i/o coherency. If not set, mapped ranges have to be flushed & invalidated, which [`Buffer`](crate::buffer::Buffer)'s map guards do
         const HOST_COHERENT = vk::MEMORY_PROPERTY_HOST_COHERENT_BIT;
         /// Memory will be cached by the host
         const HOST_CACHED = vk::MEMORY_PROPERTY_HOST_CACHED_BIT;
@@ -966,6 +1604,8 @@ bitflags::bitflags! {
         const DEVICE_UNCACHED_AMD = vk::MEMORY_PROPERTY_DEVICE_UNCACHED_BIT_AMD;
         const RDMA_CAPABLE_NV = vk::MEMORY_PROPERTY_RDMA_CAPABLE_BIT_NV;
 
+        /// Device-local memory the host can map. Outside of resizable BAR, GPUs only expose a small window of it,
+        /// so [`MemoryUsage::upload`] is usually the better choice.
         // https://gpuopen.com/learn/vulkan-device-memory/
         const MAPABLE = vk::MEMORY_PROPERTY_DEVICE_LOCAL_BIT | vk::MEMORY_PROPERTY_HOST_VISIBLE_BIT | vk::MEMORY_PROPERTY_HOST_COHERENT_BIT;
     }
@@ -0,0 +1,347 @@
+use super::MemoryFlags;
+use crate::{error::Error, Result};
+use std::{
+    alloc::Layout,
+    ffi::c_void,
+    num::NonZeroU64,
+    ops::Range,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use vk::DeviceSize;
+
+/// Source of the memory blocks [`Page`](super::Page)s sub-allocate from.
+///
+/// # Safety
+/// A handle returned by `allocate` must refer to at least `size` bytes, which stay valid until the handle is passed to `free`.
+/// `map` must return a pointer to all of them.
+pub unsafe trait MemoryBackend {
+    /// Property flags of each memory type, by memory type index
+    fn memory_types(&self) -> Vec<MemoryFlags>;
+
+    /// Allocates `size` bytes of `memory_type`. If `dedicated` is set, the memory is only ever bound to that buffer.
+    fn allocate(
+        &self,
+        size: DeviceSize,
+        memory_type: u32,
+        dedicated: Option<NonZeroU64>,
+    ) -> Result<NonZeroU64>;
+
+    /// # Safety
+    /// `memory` must have been returned by `allocate` for `size` bytes, and must not be used afterwards
+    unsafe fn free(&self, memory: NonZeroU64, size: DeviceSize);
+
+    /// Maps the whole of `memory` into the host's address space
+    ///
+    /// # Safety
+    /// `memory` must have been returned by `allocate` for `size` bytes, and must not already be mapped
+    unsafe fn map(&self, memory: NonZeroU64, size: DeviceSize) -> Result<NonNull<c_void>>;
+
+    /// # Safety
+    /// `memory` must be mapped
+    unsafe fn unmap(&self, memory: NonZeroU64);
+
+    /// Alignment of the ranges of non-coherent memory to flush & invalidate
+    fn non_coherent_atom_size(&self) -> DeviceSize;
+
+    /// Bytes that can still be allocated from `memory_type` within its budget, or `None` if unknown
+    #[inline]
+    fn available(&self, _memory_type: u32) -> Option<DeviceSize> {
+        return None;
+    }
+
+    /// Makes host writes to `range` of `memory` visible to the device
+    ///
+    /// # Safety
+    /// `memory` must have been returned by `allocate` for `size` bytes and be mapped.
+    /// `range` must be aligned to [`non_coherent_atom_size`](MemoryBackend::non_coherent_atom_size), except for an end equal to `size`
+    unsafe fn flush(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()>;
+
+    /// Makes device writes to `range` of `memory` visible to the host
+    ///
+    /// # Safety
+    /// Same as [`flush`](MemoryBackend::flush)
+    unsafe fn invalidate(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()>;
+}
+
+unsafe impl<T: ?Sized + MemoryBackend> MemoryBackend for &T {
+    #[inline]
+    fn memory_types(&self) -> Vec<MemoryFlags> {
+        return T::memory_types(*self);
+    }
+
+    #[inline]
+    fn allocate(
+        &self,
+        size: DeviceSize,
+        memory_type: u32,
+        dedicated: Option<NonZeroU64>,
+    ) -> Result<NonZeroU64> {
+        return T::allocate(*self, size, memory_type, dedicated);
+    }
+
+    #[inline]
+    unsafe fn free(&self, memory: NonZeroU64, size: DeviceSize) {
+        T::free(*self, memory, size)
+    }
+
+    #[inline]
+    unsafe fn map(&self, memory: NonZeroU64, size: DeviceSize) -> Result<NonNull<c_void>> {
+        return T::map(*self, memory, size);
+    }
+
+    #[inline]
+    unsafe fn unmap(&self, memory: NonZeroU64) {
+        T::unmap(*self, memory)
+    }
+
+    #[inline]
+    fn non_coherent_atom_size(&self) -> DeviceSize {
+        return T::non_coherent_atom_size(*self);
+    }
+
+    #[inline]
+    fn available(&self, memory_type: u32) -> Option<DeviceSize> {
+        return T::available(*self, memory_type);
+    }
+
+    #[inline]
+    unsafe fn flush(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        return T::flush(*self, memory, size, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        return T::invalidate(*self, memory, size, range);
+    }
+}
+
+unsafe impl<T: ?Sized + MemoryBackend> MemoryBackend for Arc<T> {
+    #[inline]
+    fn memory_types(&self) -> Vec<MemoryFlags> {
+        return T::memory_types(self);
+    }
+
+    #[inline]
+    fn allocate(
+        &self,
+        size: DeviceSize,
+        memory_type: u32,
+        dedicated: Option<NonZeroU64>,
+    ) -> Result<NonZeroU64> {
+        return T::allocate(self, size, memory_type, dedicated);
+    }
+
+    #[inline]
+    unsafe fn free(&self, memory: NonZeroU64, size: DeviceSize) {
+        T::free(self, memory, size)
+    }
+
+    #[inline]
+    unsafe fn map(&self, memory: NonZeroU64, size: DeviceSize) -> Result<NonNull<c_void>> {
+        return T::map(self, memory, size);
+    }
+
+    #[inline]
+    unsafe fn unmap(&self, memory: NonZeroU64) {
+        T::unmap(self, memory)
+    }
+
+    #[inline]
+    fn non_coherent_atom_size(&self) -> DeviceSize {
+        return T::non_coherent_atom_size(self);
+    }
+
+    #[inline]
+    fn available(&self, memory_type: u32) -> Option<DeviceSize> {
+        return T::available(self, memory_type);
+    }
+
+    #[inline]
+    unsafe fn flush(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        return T::flush(self, memory, size, range);
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        return T::invalidate(self, memory, size, range);
+    }
+}
+
+/// Backend allocating from host memory, so the allocators can be exercised without a device.
+/// Memory types only differ by their flags, and every block is mappable.
+///
+/// Flushes & invalidations do nothing, but are counted, and rejected if they aren't aligned to [`HostMemory::NON_COHERENT_ATOM_SIZE`].
+#[derive(Debug)]
+pub struct HostMemory {
+    allocated: AtomicU64,
+    limit: DeviceSize,
+    types: &'static [MemoryFlags],
+    flushes: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl HostMemory {
+    /// Alignment of every block, standing in for the alignment guaranteed by `vkAllocateMemory`
+    pub const ALIGN: usize = 4096;
+
+    pub const NON_COHERENT_ATOM_SIZE: DeviceSize = 64;
+
+    /// Memory types of a typical discrete GPU, with a small host-visible window into device-local memory
+    pub const DISCRETE_TYPES: &'static [MemoryFlags] = &[
+        MemoryFlags::DEVICE_LOCAL,
+        MemoryFlags::HOST_VISIBLE.union(MemoryFlags::HOST_COHERENT),
+        MemoryFlags::HOST_VISIBLE
+            .union(MemoryFlags::HOST_COHERENT)
+            .union(MemoryFlags::HOST_CACHED),
+        MemoryFlags::DEVICE_LOCAL
+            .union(MemoryFlags::HOST_VISIBLE)
+            .union(MemoryFlags::HOST_COHERENT),
+    ];
+
+    #[inline]
+    pub const fn new() -> Self {
+        return Self::with_limit(DeviceSize::MAX);
+    }
+
+    /// Creates a backend that fails with `ERROR_OUT_OF_DEVICE_MEMORY` once `limit` bytes are allocated
+    #[inline]
+    pub const fn with_limit(limit: DeviceSize) -> Self {
+        return Self {
+            allocated: AtomicU64::new(0),
+            limit,
+            types: Self::DISCRETE_TYPES,
+            flushes: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        };
+    }
+
+    /// Replaces the memory types the backend reports
+    #[inline]
+    pub const fn with_types(mut self, types: &'static [MemoryFlags]) -> Self {
+        self.types = types;
+        self
+    }
+
+    /// Bytes currently allocated
+    #[inline]
+    pub fn allocated(&self) -> DeviceSize {
+        return self.allocated.load(Ordering::Acquire);
+    }
+
+    /// Number of ranges flushed so far
+    #[inline]
+    pub fn flushes(&self) -> u64 {
+        return self.flushes.load(Ordering::Acquire);
+    }
+
+    /// Number of ranges invalidated so far
+    #[inline]
+    pub fn invalidations(&self) -> u64 {
+        return self.invalidations.load(Ordering::Acquire);
+    }
+
+    /// Checks that `range` of a block of `size` bytes follows the alignment rules of `vkFlushMappedMemoryRanges`
+    fn check_range(size: DeviceSize, range: &Range<DeviceSize>) -> Result<()> {
+        let atom = Self::NON_COHERENT_ATOM_SIZE;
+        if range.start % atom != 0 || (range.end % atom != 0 && range.end != size) || range.start > range.end || range.end > size {
+            return Err(Error::Validation(format!(
+                "range {range:?} of a {size} byte block isn't aligned to {atom} bytes"
+            )));
+        }
+        return Ok(());
+    }
+
+    #[inline]
+    fn layout(size: DeviceSize) -> Option<Layout> {
+        return Layout::from_size_align(usize::try_from(size).ok()?, Self::ALIGN).ok();
+    }
+}
+
+impl Default for HostMemory {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl MemoryBackend for HostMemory {
+    #[inline]
+    fn memory_types(&self) -> Vec<MemoryFlags> {
+        return self.types.to_vec();
+    }
+
+    fn allocate(
+        &self,
+        size: DeviceSize,
+        memory_type: u32,
+        _dedicated: Option<NonZeroU64>,
+    ) -> Result<NonZeroU64> {
+        let layout = match Self::layout(size) {
+            Some(x) if size > 0 && (memory_type as usize) < self.types.len() => x,
+            _ => return Err(vk::ERROR_OUT_OF_HOST_MEMORY.into()),
+        };
+
+        let reserved = self.allocated.fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+            x.checked_add(size).filter(|x| *x <= self.limit)
+        });
+
+        if reserved.is_err() {
+            return Err(vk::ERROR_OUT_OF_DEVICE_MEMORY.into());
+        }
+
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        return match NonZeroU64::new(ptr as u64) {
+            Some(x) => Ok(x),
+            None => {
+                self.allocated.fetch_sub(size, Ordering::AcqRel);
+                Err(vk::ERROR_OUT_OF_HOST_MEMORY.into())
+            }
+        };
+    }
+
+    #[inline]
+    unsafe fn free(&self, memory: NonZeroU64, size: DeviceSize) {
+        if let Some(layout) = Self::layout(size) {
+            std::alloc::dealloc(memory.get() as *mut u8, layout);
+            self.allocated.fetch_sub(size, Ordering::AcqRel);
+        }
+    }
+
+    #[inline]
+    unsafe fn map(&self, memory: NonZeroU64, _size: DeviceSize) -> Result<NonNull<c_void>> {
+        return Ok(NonNull::new_unchecked(memory.get() as *mut c_void));
+    }
+
+    #[inline]
+    unsafe fn unmap(&self, _memory: NonZeroU64) {
+        // noop
+    }
+
+    #[inline]
+    fn non_coherent_atom_size(&self) -> DeviceSize {
+        return Self::NON_COHERENT_ATOM_SIZE;
+    }
+
+    #[inline]
+    fn available(&self, _memory_type: u32) -> Option<DeviceSize> {
+        return Some(self.limit.saturating_sub(self.allocated.load(Ordering::Acquire)));
+    }
+
+    #[inline]
+    unsafe fn flush(&self, _memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        Self::check_range(size, &range)?;
+        self.flushes.fetch_add(1, Ordering::AcqRel);
+        return Ok(());
+    }
+
+    #[inline]
+    unsafe fn invalidate(&self, _memory: NonZeroU64, size: DeviceSize, range: Range<DeviceSize>) -> Result<()> {
+        Self::check_range(size, &range)?;
+        self.invalidations.fetch_add(1, Ordering::AcqRel);
+        return Ok(());
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+use vk::DeviceSize;
+
+/// Block handed out by a [`Buddy`] allocator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BuddyBlock {
+    pub offset: DeviceSize,
+    /// The block spans `min_block << order` bytes
+    pub order: u32,
+}
+
+/// Binary buddy sub-allocator over the byte range `0..size`.
+///
+/// Blocks are power-of-two multiples of the minimum block size, and are aligned to their own size,
+/// so any alignment up to the block size is honored for free. Allocating & freeing take O(log n).
+#[derive(Debug, Clone)]
+pub struct Buddy {
+    size: DeviceSize,
+    min_shift: u32,
+    /// Offsets of the free blocks of each order
+    free: Vec<BTreeSet<DeviceSize>>,
+    used: DeviceSize,
+    requested: DeviceSize,
+}
+
+impl Buddy {
+    /// Creates an allocator over `0..size`, rounded down to a multiple of `min_block`, which must be a power of two
+    pub fn new(size: DeviceSize, min_block: DeviceSize) -> Self {
+        assert!(min_block.is_power_of_two());
+        let min_shift = min_block.trailing_zeros();
+        let size = size - size % min_block;
+
+        let orders = match size >> min_shift {
+            0 => 0,
+            blocks => (63 - blocks.leading_zeros()) as usize + 1,
+        };
+
+        // Sizes that aren't a power of two are split into decreasing blocks, each aligned to its own size
+        let mut free = vec![BTreeSet::new(); orders];
+        let mut offset = 0;
+        while offset < size {
+            let order = 63 - ((size - offset) >> min_shift).leading_zeros();
+            free[order as usize].insert(offset);
+            offset += min_block << order;
+        }
+
+        return Self {
+            size,
+            min_shift,
+            free,
+            used: 0,
+            requested: 0,
+        };
+    }
+
+    /// Size of the block that would be handed out for an allocation of `size` bytes aligned to `align`
+    #[inline]
+    pub fn block_size(size: DeviceSize, align: DeviceSize, min_block: DeviceSize) -> Option<DeviceSize> {
+        return size.max(align).max(min_block).checked_next_power_of_two();
+    }
+
+    #[inline]
+    pub fn size(&self) -> DeviceSize {
+        return self.size;
+    }
+
+    #[inline]
+    pub fn min_block(&self) -> DeviceSize {
+        return 1 << self.min_shift;
+    }
+
+    /// Allocates a block of at least `size` bytes, with its offset aligned to `align` (a power of two)
+    pub fn allocate(&mut self, size: DeviceSize, align: DeviceSize) -> Option<BuddyBlock> {
+        debug_assert!(align == 0 || align.is_power_of_two());
+        let block = Self::block_size(size.max(1), align, self.min_block())?;
+        let order = (block >> self.min_shift).trailing_zeros();
+
+        let found = (order as usize..self.free.len()).find(|x| !self.free[*x].is_empty())?;
+        let offset = self.free[found].pop_first()?;
+
+        // Split the block, keeping the lower halves & freeing the upper ones
+        for split in (order..found as u32).rev() {
+            self.free[split as usize].insert(offset + (self.min_block() << split));
+        }
+
+        self.used += block;
+        self.requested += size;
+        return Some(BuddyBlock { offset, order });
+    }
+
+    /// Frees `block`, previously returned by [`allocate`](Self::allocate) for `size` bytes, merging it with its free buddies
+    pub fn free(&mut self, block: BuddyBlock, size: DeviceSize) {
+        let BuddyBlock { mut offset, mut order } = block;
+        self.used -= self.min_block() << order;
+        self.requested -= size;
+
+        while (order as usize) + 1 < self.free.len() {
+            let buddy = offset ^ (self.min_block() << order);
+            if !self.free[order as usize].remove(&buddy) {
+                break;
+            }
+
+            offset = offset.min(buddy);
+            order += 1;
+        }
+
+        let inserted = self.free[order as usize].insert(offset);
+        debug_assert!(inserted, "block at offset {offset} freed twice");
+    }
+
+    /// Size of the largest block that can currently be allocated
+    #[inline]
+    pub fn largest_free_block(&self) -> DeviceSize {
+        return match self.free.iter().rposition(|x| !x.is_empty()) {
+            Some(order) => self.min_block() << order,
+            None => 0,
+        };
+    }
+
+    #[inline]
+    pub fn metrics(&self) -> BuddyMetrics {
+        return BuddyMetrics {
+            size: self.size,
+            used: self.used,
+            requested: self.requested,
+            largest_free_block: self.largest_free_block(),
+            free_blocks: self.free.iter().map(BTreeSet::len).sum(),
+        };
+    }
+}
+
+/// Snapshot of the occupancy of a [`Buddy`] allocator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BuddyMetrics {
+    /// Bytes managed by the allocator
+    pub size: DeviceSize,
+    /// Bytes taken by allocated blocks
+    pub used: DeviceSize,
+    /// Bytes requested by the allocations, which may be less than their blocks
+    pub requested: DeviceSize,
+    pub largest_free_block: DeviceSize,
+    /// Number of free blocks, of any order
+    pub free_blocks: usize,
+}
+
+impl BuddyMetrics {
+    #[inline]
+    pub fn free(&self) -> DeviceSize {
+        return self.size - self.used;
+    }
+
+    /// Bytes lost to rounding allocations up to their block size
+    #[inline]
+    pub fn internal_waste(&self) -> DeviceSize {
+        return self.used - self.requested;
+    }
+
+    /// Share of the free memory that can't be handed out in a single block, from 0 (none) to 1
+    #[inline]
+    pub fn fragmentation(&self) -> f64 {
+        return match self.free() {
+            0 => 0.0,
+            free => 1.0 - (self.largest_free_block as f64 / free as f64),
+        };
+    }
+}
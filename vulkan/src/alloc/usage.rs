@@ -0,0 +1,242 @@
+use super::MemoryFlags;
+use crate::{device::Device, error::Error, Entry, Result};
+use std::{
+    mem::MaybeUninit,
+    num::NonZeroU64,
+    ptr::{addr_of, addr_of_mut},
+};
+use vk::DeviceSize;
+
+/// What the resource an allocation is for asks of its memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MemoryRequirements {
+    pub size: DeviceSize,
+    pub align: DeviceSize,
+    /// Bit `i` is set if the resource can be bound to memory type `i`
+    pub type_bits: u32,
+    /// The implementation would rather give the resource an allocation of its own
+    pub prefers_dedicated: bool,
+    /// The resource must have an allocation of its own
+    pub requires_dedicated: bool,
+    /// Buffer the memory is for, handed to the driver when the allocation is dedicated
+    pub buffer: Option<NonZeroU64>,
+}
+
+impl MemoryRequirements {
+    /// Requirements of `size` bytes aligned to `align`, that may live in any memory type
+    #[inline]
+    pub const fn new(size: DeviceSize, align: DeviceSize) -> Self {
+        return Self {
+            size,
+            align,
+            type_bits: u32::MAX,
+            prefers_dedicated: false,
+            requires_dedicated: false,
+            buffer: None,
+        };
+    }
+
+    /// Queries the requirements of `buffer`, including whether it should get a dedicated allocation
+    pub fn for_buffer(device: &Device, buffer: NonZeroU64) -> Self {
+        let info = vk::BufferMemoryRequirementsInfo2 {
+            sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_REQUIREMENTS_INFO_2,
+            pNext: core::ptr::null(),
+            buffer: buffer.get(),
+        };
+
+        let mut dedicated = MaybeUninit::<vk::MemoryDedicatedRequirements>::uninit();
+        let mut result = MaybeUninit::<vk::MemoryRequirements2>::uninit();
+        unsafe {
+            let ptr = dedicated.as_mut_ptr();
+            addr_of_mut!((*ptr).sType).write(vk::STRUCTURE_TYPE_MEMORY_DEDICATED_REQUIREMENTS);
+            addr_of_mut!((*ptr).pNext).write(core::ptr::null_mut());
+
+            let ptr = result.as_mut_ptr();
+            addr_of_mut!((*ptr).sType).write(vk::STRUCTURE_TYPE_MEMORY_REQUIREMENTS_2);
+            addr_of_mut!((*ptr).pNext).write(dedicated.as_mut_ptr().cast());
+        }
+
+        (Entry::get().get_buffer_memory_requirements2)(device.id(), addr_of!(info), result.as_mut_ptr());
+
+        let (result, dedicated) = unsafe { (result.assume_init(), dedicated.assume_init()) };
+        return Self {
+            size: result.memoryRequirements.size,
+            align: result.memoryRequirements.alignment,
+            type_bits: result.memoryRequirements.memoryTypeBits,
+            prefers_dedicated: dedicated.prefersDedicatedAllocation != vk::FALSE,
+            requires_dedicated: dedicated.requiresDedicatedAllocation != vk::FALSE,
+            buffer: Some(buffer),
+        };
+    }
+}
+
+/// Whether an allocation gets a block of device memory of its own, instead of being sub-allocated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Dedicated {
+    /// Dedicated if the implementation prefers it, or the allocation is large compared to the allocator's blocks
+    #[default]
+    Auto,
+    Always,
+    /// Sub-allocated, unless the implementation requires a dedicated allocation
+    Never,
+}
+
+/// Memory properties an allocation asks for.
+///
+/// Each candidate has flags every memory type must have, and flags that rank the types that have them first.
+/// Candidates are tried in order, moving to the next memory type when one runs out of memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemoryUsage {
+    /// `(required, preferred)` flags of each candidate
+    candidates: Vec<(MemoryFlags, MemoryFlags)>,
+    dedicated: Dedicated,
+}
+
+impl MemoryUsage {
+    #[inline]
+    pub fn new(required: MemoryFlags) -> Self {
+        return Self {
+            candidates: vec![(required, MemoryFlags::empty())],
+            dedicated: Dedicated::Auto,
+        };
+    }
+
+    /// Memory only the device accesses, falling back to any memory type once device-local memory runs out
+    #[inline]
+    pub fn device_local() -> Self {
+        return Self::new(MemoryFlags::DEVICE_LOCAL).or(MemoryFlags::empty());
+    }
+
+    /// Memory the host writes & the device reads, placed in device-local memory when the host can map it
+    #[inline]
+    pub fn upload() -> Self {
+        return Self::new(MemoryFlags::HOST_VISIBLE | MemoryFlags::HOST_COHERENT)
+            .prefer(MemoryFlags::DEVICE_LOCAL);
+    }
+
+    /// Memory the device writes & the host reads, cached by the host when possible.
+    /// Cached memory often isn't host-coherent, so maps of it are invalidated before being read.
+    #[inline]
+    pub fn readback() -> Self {
+        return Self::new(MemoryFlags::HOST_VISIBLE).prefer(MemoryFlags::HOST_CACHED);
+    }
+
+    /// Ranks the memory types of the last candidate with `flags` first
+    #[inline]
+    pub fn prefer(mut self, flags: MemoryFlags) -> Self {
+        if let Some((_, preferred)) = self.candidates.last_mut() {
+            *preferred |= flags;
+        }
+        self
+    }
+
+    /// Adds a candidate, tried once the memory types of the previous ones are unavailable or out of memory
+    #[inline]
+    pub fn or(mut self, required: MemoryFlags) -> Self {
+        self.candidates.push((required, MemoryFlags::empty()));
+        self
+    }
+
+    #[inline]
+    pub fn dedicated(mut self, dedicated: Dedicated) -> Self {
+        self.dedicated = dedicated;
+        self
+    }
+
+    /// `(required, preferred)` flags of each candidate, in the order they're tried
+    #[inline]
+    pub fn candidates(&self) -> &[(MemoryFlags, MemoryFlags)] {
+        return &self.candidates;
+    }
+
+    /// Whether an allocation for `requirements` should be dedicated, with `threshold` being the size from which [`Dedicated::Auto`] dedicates it
+    #[inline]
+    pub fn is_dedicated(&self, requirements: &MemoryRequirements, threshold: DeviceSize) -> bool {
+        return requirements.requires_dedicated
+            || match self.dedicated {
+                Dedicated::Auto => requirements.prefers_dedicated || requirements.size >= threshold,
+                Dedicated::Always => true,
+                Dedicated::Never => false,
+            };
+    }
+
+    /// Whether a memory type with `flags` satisfies any of the candidates
+    #[inline]
+    pub fn accepts(&self, flags: MemoryFlags) -> bool {
+        return self.candidates.iter().any(|(required, _)| flags.contains(*required));
+    }
+}
+
+impl Default for MemoryUsage {
+    #[inline]
+    fn default() -> Self {
+        Self::new(MemoryFlags::default())
+    }
+}
+
+impl From<MemoryFlags> for MemoryUsage {
+    #[inline]
+    fn from(required: MemoryFlags) -> Self {
+        Self::new(required)
+    }
+}
+
+/// Indices of the memory `types` (the flags of each type, by index) allowed by `type_bits` that satisfy `usage`, in the order they should be tried.
+///
+/// Within a candidate, types with more of the preferred flags come first, then the ones with fewer flags nobody asked for,
+/// so that scarce memory (like host-visible device-local memory) isn't used up by allocations that don't need it.
+pub fn select_memory_types(types: &[MemoryFlags], type_bits: u32, usage: &MemoryUsage) -> Vec<u32> {
+    let mut result = Vec::with_capacity(types.len());
+    // Types already in `result`, so the ones satisfying several candidates are only tried once
+    let mut selected = 0u32;
+
+    for (required, preferred) in usage.candidates() {
+        let start = result.len();
+        for i in 0..types.len().min(32) as u32 {
+            let bit = 1 << i;
+            if type_bits & bit != 0 && selected & bit == 0 && types[i as usize].contains(*required) {
+                selected |= bit;
+                result.push(i);
+            }
+        }
+
+        result[start..].sort_by_key(|i| {
+            let flags = types[*i as usize];
+            let matched = (flags & *preferred).bits().count_ones();
+            let extra = (flags - *required - *preferred).bits().count_ones();
+            (u32::MAX - matched, extra, *i)
+        });
+    }
+
+    return result;
+}
+
+/// Calls `f` with each memory type selected for `requirements` & `usage`, until one of them doesn't run out of memory
+pub(crate) fn allocate_with_fallback<T>(
+    types: &[MemoryFlags],
+    requirements: &MemoryRequirements,
+    usage: &MemoryUsage,
+    mut f: impl FnMut(u32) -> Result<T>,
+) -> Result<T> {
+    let candidates = select_memory_types(types, requirements.type_bits, usage);
+    if candidates.is_empty() {
+        return Err(Error::NoMemoryType(format!(
+            "none of {types:?} allowed by {:#b} satisfies {:?}",
+            requirements.type_bits,
+            usage.candidates()
+        )));
+    }
+
+    let mut last_error = None;
+    for memory_type in candidates {
+        match f(memory_type) {
+            Ok(x) => return Ok(x),
+            Err(e @ Error::Vulkan(vk::ERROR_OUT_OF_DEVICE_MEMORY | vk::ERROR_OUT_OF_HOST_MEMORY)) => {
+                last_error = Some(e)
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    return Err(last_error.unwrap_or_else(|| vk::ERROR_OUT_OF_DEVICE_MEMORY.into()));
+}
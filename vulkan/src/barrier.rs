@@ -0,0 +1,159 @@
+use std::{marker::PhantomData, ops::{RangeBounds, Bound}};
+use crate::{buffer::Buffer, alloc::DeviceAllocator, physical_dev::Family};
+
+/// Global memory barrier, affecting every memory access of the specified types
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct MemoryBarrier {
+    inner: vk::MemoryBarrier
+}
+
+impl MemoryBarrier {
+    #[inline]
+    pub fn new (src: AccessFlags, dst: AccessFlags) -> Self {
+        return Self {
+            inner: vk::MemoryBarrier {
+                sType: vk::STRUCTURE_TYPE_MEMORY_BARRIER,
+                pNext: core::ptr::null(),
+                srcAccessMask: src.bits(),
+                dstAccessMask: dst.bits(),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn src_access (&self) -> AccessFlags {
+        return AccessFlags::from_bits_truncate(self.inner.srcAccessMask)
+    }
+
+    #[inline]
+    pub fn dst_access (&self) -> AccessFlags {
+        return AccessFlags::from_bits_truncate(self.inner.dstAccessMask)
+    }
+}
+
+/// Memory barrier affecting only a range of a buffer.
+/// Also used to transfer the buffer's ownership between queue families.
+#[derive(Clone)]
+#[repr(transparent)]
+pub struct BufferMemoryBarrier<'a> {
+    inner: vk::BufferMemoryBarrier,
+    _phtm: PhantomData<&'a ()>
+}
+
+impl<'a> BufferMemoryBarrier<'a> {
+    /// Creates a barrier over the whole buffer
+    #[inline]
+    pub fn new<T, A: DeviceAllocator> (buffer: &'a Buffer<T, A>, src: AccessFlags, dst: AccessFlags) -> Self {
+        return Self::with_range(buffer, .., src, dst)
+    }
+
+    /// Creates a barrier over the elements of `buffer` inside `bounds`
+    #[inline]
+    pub fn with_range<T, A: DeviceAllocator> (buffer: &'a Buffer<T, A>, bounds: impl RangeBounds<u64>, src: AccessFlags, dst: AccessFlags) -> Self {
+        let element_size = core::mem::size_of::<T>() as u64;
+
+        let offset = match bounds.start_bound() {
+            Bound::Excluded(x) => *x + 1,
+            Bound::Included(x) => *x,
+            Bound::Unbounded => 0
+        };
+
+        let size = match bounds.end_bound() {
+            Bound::Excluded(x) => (*x - offset) * element_size,
+            Bound::Included(x) => (*x + 1 - offset) * element_size,
+            Bound::Unbounded => vk::WHOLE_SIZE
+        };
+
+        return Self {
+            inner: vk::BufferMemoryBarrier {
+                sType: vk::STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER,
+                pNext: core::ptr::null(),
+                srcAccessMask: src.bits(),
+                dstAccessMask: dst.bits(),
+                srcQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                dstQueueFamilyIndex: vk::QUEUE_FAMILY_IGNORED,
+                buffer: buffer.id(),
+                offset: offset * element_size,
+                size,
+            },
+            _phtm: PhantomData
+        }
+    }
+
+    /// Transfers ownership of the buffer range from the `src` queue family to the `dst` queue family.
+    /// The same barrier must be recorded on both families: as a release on `src`, and as an acquire on `dst`.
+    #[inline]
+    pub fn queue_transfer (mut self, src: Family, dst: Family) -> Self {
+        if src != dst {
+            self.inner.srcQueueFamilyIndex = src.idx();
+            self.inner.dstQueueFamilyIndex = dst.idx();
+        }
+        return self
+    }
+
+    #[inline]
+    pub fn buffer (&self) -> vk::Buffer {
+        return self.inner.buffer
+    }
+
+    #[inline]
+    pub fn src_access (&self) -> AccessFlags {
+        return AccessFlags::from_bits_truncate(self.inner.srcAccessMask)
+    }
+
+    #[inline]
+    pub fn dst_access (&self) -> AccessFlags {
+        return AccessFlags::from_bits_truncate(self.inner.dstAccessMask)
+    }
+}
+
+bitflags::bitflags! {
+    #[repr(transparent)]
+    pub struct AccessFlags: vk::AccessFlags {
+        /// Controls coherency of indirect command reads
+        const INDIRECT_COMMAND_READ = vk::ACCESS_INDIRECT_COMMAND_READ_BIT;
+        /// Controls coherency of index reads
+        const INDEX_READ = vk::ACCESS_INDEX_READ_BIT;
+        /// Controls coherency of vertex attribute reads
+        const VERTEX_ATTRIBUTE_READ = vk::ACCESS_VERTEX_ATTRIBUTE_READ_BIT;
+        /// Controls coherency of uniform buffer reads
+        const UNIFORM_READ = vk::ACCESS_UNIFORM_READ_BIT;
+        /// Controls coherency of input attachment reads
+        const INPUT_ATTACHMENT_READ = vk::ACCESS_INPUT_ATTACHMENT_READ_BIT;
+        /// Controls coherency of shader reads
+        const SHADER_READ = vk::ACCESS_SHADER_READ_BIT;
+        /// Controls coherency of shader writes
+        const SHADER_WRITE = vk::ACCESS_SHADER_WRITE_BIT;
+        /// Controls coherency of color attachment reads
+        const COLOR_ATTACHMENT_READ = vk::ACCESS_COLOR_ATTACHMENT_READ_BIT;
+        /// Controls coherency of color attachment writes
+        const COLOR_ATTACHMENT_WRITE = vk::ACCESS_COLOR_ATTACHMENT_WRITE_BIT;
+        /// Controls coherency of depth/stencil attachment reads
+        const DEPTH_STENCIL_ATTACHMENT_READ = vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_READ_BIT;
+        /// Controls coherency of depth/stencil attachment writes
+        const DEPTH_STENCIL_ATTACHMENT_WRITE = vk::ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT;
+        /// Controls coherency of transfer reads
+        const TRANSFER_READ = vk::ACCESS_TRANSFER_READ_BIT;
+        /// Controls coherency of transfer writes
+        const TRANSFER_WRITE = vk::ACCESS_TRANSFER_WRITE_BIT;
+        /// Controls coherency of host reads
+        const HOST_READ = vk::ACCESS_HOST_READ_BIT;
+        /// Controls coherency of host writes
+        const HOST_WRITE = vk::ACCESS_HOST_WRITE_BIT;
+        /// Controls coherency of memory reads
+        const MEMORY_READ = vk::ACCESS_MEMORY_READ_BIT;
+        /// Controls coherency of memory writes
+        const MEMORY_WRITE = vk::ACCESS_MEMORY_WRITE_BIT;
+        const NONE = vk::ACCESS_NONE;
+    }
+
+    #[repr(transparent)]
+    pub struct DependencyFlags: vk::DependencyFlags {
+        /// Dependency is per pixel region
+        const BY_REGION = vk::DEPENDENCY_BY_REGION_BIT;
+        /// Dependency is across devices
+        const DEVICE_GROUP = vk::DEPENDENCY_DEVICE_GROUP_BIT;
+        const VIEW_LOCAL = vk::DEPENDENCY_VIEW_LOCAL_BIT;
+    }
+}
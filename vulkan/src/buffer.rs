@@ -1,6 +1,6 @@
-use std::{marker::PhantomData, num::{NonZeroU64}, ptr::{addr_of, addr_of_mut, NonNull}, mem::{MaybeUninit, ManuallyDrop}, ops::{Deref, DerefMut, RangeBounds, Bound}, fmt::Debug};
+use std::{marker::PhantomData, num::{NonZeroU64}, ptr::{addr_of, addr_of_mut, NonNull}, mem::{MaybeUninit, ManuallyDrop}, ops::{Deref, DerefMut, Range, RangeBounds, Bound}, fmt::Debug, ffi::CStr};
 use vk::{DeviceSize};
-use crate::{Result, Entry, device::{Device}, alloc::{DeviceAllocator, MemoryPtr, MemoryFlags}, utils::u64_to_usize};
+use crate::{Result, Entry, device::{Device}, alloc::{DeviceAllocator, MemoryPtr, MemoryRequirements, MemoryUsage}, utils::u64_to_usize};
 
 pub struct Buffer<T, A: DeviceAllocator> {
     buffer: NonZeroU64,
@@ -13,7 +13,8 @@ pub struct Buffer<T, A: DeviceAllocator> {
 impl<T, A: DeviceAllocator> Buffer<T, A> {
     const BYTES_PER_ELEMENT: vk::DeviceSize = core::mem::size_of::<T>() as vk::DeviceSize;
 
-    pub fn new_uninit (capacity: DeviceSize, usage: UsageFlags, flags: BufferFlags, memory_flags: MemoryFlags, alloc: A) -> Result<Buffer<MaybeUninit<T>, A>> {
+    /// Creates a buffer of `capacity` elements, bound to memory picked by `alloc` for `memory`, which is either a [`MemoryUsage`] or the [`MemoryFlags`](crate::alloc::MemoryFlags) it requires
+    pub fn new_uninit (capacity: DeviceSize, usage: UsageFlags, flags: BufferFlags, memory: impl Into<MemoryUsage>, alloc: A) -> Result<Buffer<MaybeUninit<T>, A>> {
         let entry = Entry::get();
         let info = vk::BufferCreateInfo {
             sType: vk::STRUCTURE_TYPE_BUFFER_CREATE_INFO,
@@ -32,19 +33,24 @@ impl<T, A: DeviceAllocator> Buffer<T, A> {
         };
 
         if let Some(buffer) = NonZeroU64::new(inner) {
-            let mut result = MaybeUninit::<vk::MemoryRequirements>::uninit();
-            (Entry::get().get_buffer_memory_requirements)(
-                alloc.device().id(),
-                buffer.get(),
-                result.as_mut_ptr()
-            );
-
-            let requirements = unsafe { result.assume_init() };
-            let memory = alloc.allocate(requirements.size, requirements.alignment, memory_flags)?;
-            tri! {
-                (entry.bind_buffer_memory)(alloc.device().id(), buffer.get(), memory.id(), memory.range().start)
+            let requirements = MemoryRequirements::for_buffer(alloc.device(), buffer);
+            let memory = match alloc.allocate(&requirements, &memory.into()) {
+                Ok(x) => x,
+                Err(e) => {
+                    (entry.destroy_buffer)(alloc.device().id(), buffer.get(), core::ptr::null());
+                    return Err(e)
+                }
             };
 
+            match (entry.bind_buffer_memory)(alloc.device().id(), buffer.get(), memory.id(), memory.range().start) {
+                vk::SUCCESS => {},
+                e => unsafe {
+                    alloc.free(memory);
+                    (entry.destroy_buffer)(alloc.device().id(), buffer.get(), core::ptr::null());
+                    return Err(e.into())
+                }
+            }
+
             return Ok(Buffer { buffer, size: info.size, memory: ManuallyDrop::new(memory), alloc, _phtm: PhantomData })
         }
 
@@ -52,9 +58,9 @@ impl<T, A: DeviceAllocator> Buffer<T, A> {
     }
     
     #[inline]
-    pub fn from_sized_iter<I: IntoIterator<Item = T>> (iter: I, usage: UsageFlags, flags: BufferFlags, memory_flags: MemoryFlags, alloc: A) -> Result<Buffer<T, A>> where I::IntoIter: ExactSizeIterator {
+    pub fn from_sized_iter<I: IntoIterator<Item = T>> (iter: I, usage: UsageFlags, flags: BufferFlags, memory: impl Into<MemoryUsage>, alloc: A) -> Result<Buffer<T, A>> where I::IntoIter: ExactSizeIterator {
         let iter = iter.into_iter();
-        let mut this = Self::new_uninit(iter.len() as u64, usage, flags, memory_flags, alloc)?;
+        let mut this = Self::new_uninit(iter.len() as u64, usage, flags, memory, alloc)?;
         
         let mut map = this.map_mut(..)?;
         for (map, value) in map.iter_mut().zip(iter) {
@@ -85,6 +91,12 @@ impl<T, A: DeviceAllocator> Buffer<T, A> {
         return self.alloc.device()
     }
 
+    /// Gives the buffer a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_BUFFER, self.id(), name)
+    }
+
     #[inline]
     pub fn alloc (&self) -> &A {
         return &self.alloc
@@ -99,41 +111,49 @@ impl<T, A: DeviceAllocator> Buffer<T, A> {
         }
     }
 
+    /// Maps the elements in `bounds` for reading, invalidating them first if the memory isn't host-coherent
     #[inline]
     pub fn map (&self, bounds: impl RangeBounds<usize>) -> Result<MapGuard<'_, T, A>> {
         unsafe {
-            let ptr = self.map_ptr(bounds)?;
-            return Ok(MapGuard { ptr, buffer: self })
+            let (ptr, range) = self.map_ptr(bounds)?;
+            let guard = MapGuard { ptr, range, buffer: self };
+            self.alloc.invalidate(&self.memory, guard.range.clone())?;
+            return Ok(guard)
         }
     }
 
+    /// Maps the elements in `bounds` for reading & writing. If the memory isn't host-coherent, they're invalidated first,
+    /// and flushed once the guard is dropped.
     #[inline]
     pub fn map_mut (&mut self, bounds: impl RangeBounds<usize>) -> Result<MapMutGuard<'_, T, A>> {
         unsafe {
-            let ptr = self.map_ptr(bounds)?;
-            return Ok(MapMutGuard { ptr, buffer: self })
+            let (ptr, range) = self.map_ptr(bounds)?;
+            let guard = MapMutGuard { ptr, range, buffer: self };
+            guard.buffer.alloc.invalidate(&guard.buffer.memory, guard.range.clone())?;
+            return Ok(guard)
         }
     }
 
+    /// Maps the elements in `bounds`, returning them along with their range in bytes
     #[inline]
-    unsafe fn map_ptr (&self, bounds: impl RangeBounds<usize>) -> Result<NonNull<[T]>> {
+    unsafe fn map_ptr (&self, bounds: impl RangeBounds<usize>) -> Result<(NonNull<[T]>, Range<usize>)> {
         let start = match bounds.start_bound() {
-            Bound::Excluded(x) => Bound::Excluded(*x * core::mem::size_of::<T>()),
-            Bound::Included(x) => Bound::Included(*x * core::mem::size_of::<T>()),
-            Bound::Unbounded => Bound::Unbounded
+            Bound::Excluded(x) => (*x + 1) * core::mem::size_of::<T>(),
+            Bound::Included(x) => *x * core::mem::size_of::<T>(),
+            Bound::Unbounded => 0
         };
 
         let end = match bounds.end_bound() {
-            Bound::Excluded(x) => Bound::Excluded(*x * core::mem::size_of::<T>()),
-            Bound::Included(x) => Bound::Included(*x * core::mem::size_of::<T>()),
-            Bound::Unbounded => Bound::Excluded(u64_to_usize(self.size()))
+            Bound::Excluded(x) => *x * core::mem::size_of::<T>(),
+            Bound::Included(x) => (*x + 1) * core::mem::size_of::<T>(),
+            Bound::Unbounded => u64_to_usize(self.size())
         };
         
-        let (ptr, size) = self.alloc.map(&self.memory, (start, end))?.to_raw_parts();        
-        return Ok(NonNull::new_unchecked(core::ptr::from_raw_parts_mut::<[T]>(
+        let (ptr, size) = self.alloc.map(&self.memory, start..end)?.to_raw_parts();        
+        return Ok((NonNull::new_unchecked(core::ptr::from_raw_parts_mut::<[T]>(
             ptr.as_ptr(),
             size / core::mem::size_of::<T>()
-        )));
+        )), start..end));
     }
 }
 
@@ -172,6 +192,8 @@ impl<T, A: DeviceAllocator> Drop for Buffer<T, A> {
 
 pub struct MapGuard<'a, T, A: DeviceAllocator> {
     ptr: NonNull<[T]>,
+    /// Mapped bytes of the buffer's memory
+    range: Range<usize>,
     buffer: &'a Buffer<T, A>,
 }
 
@@ -193,9 +215,21 @@ impl<T, A: DeviceAllocator> Drop for MapGuard<'_, T, A> {
 
 pub struct MapMutGuard<'a, T, A: DeviceAllocator> {
     ptr: NonNull<[T]>,
+    /// Mapped bytes of the buffer's memory
+    range: Range<usize>,
     buffer: &'a mut Buffer<T, A>,
 }
 
+impl<T, A: DeviceAllocator> MapMutGuard<'_, T, A> {
+    /// Makes the writes done so far visible to the device. Does nothing if the memory is host-coherent.
+    ///
+    /// Dropping the guard also flushes it, but only logs errors in debug builds, so callers who need to handle them must call this method explicitly.
+    #[inline]
+    pub fn flush (&self) -> Result<()> {
+        return unsafe { self.buffer.alloc.flush(&self.buffer.memory, self.range.clone()) }
+    }
+}
+
 impl<T, A: DeviceAllocator> MapMutGuard<'_, MaybeUninit<T>, A> {
     #[inline]
     pub fn init_from_slice (&mut self, slice: &[T]) where T: Copy {
@@ -224,6 +258,10 @@ impl<T, A: DeviceAllocator> DerefMut for MapMutGuard<'_, T, A> {
 impl<T, A: DeviceAllocator> Drop for MapMutGuard<'_, T, A> {
     #[inline]
     fn drop(&mut self) {
+        if let Err(_e) = self.flush() {
+            #[cfg(debug_assertions)]
+            eprintln!("Failed to flush mapped memory: {_e}");
+        }
         unsafe { self.buffer.alloc.unmap(&self.buffer.memory) }
     }
 }
@@ -0,0 +1,229 @@
+use std::{ffi::{CStr, c_void, c_char}, mem::transmute, ptr::{addr_of, addr_of_mut}, sync::Mutex};
+use once_cell::sync::OnceCell;
+use crate::{Result, Entry, error::Error, device::Device};
+
+mod printf;
+pub use printf::{PrintfRecord, take_printf};
+pub(crate) use printf::{register_pipeline, unregister_pipeline, VALIDATION_FEATURES, ENABLED_FEATURES};
+
+pub(crate) const VALIDATION_LAYER: &CStr = unsafe { proc::cstr!("VK_LAYER_KHRONOS_validation") };
+pub(crate) const DEBUG_UTILS: &CStr = unsafe { proc::cstr!("VK_EXT_debug_utils") };
+
+static DEBUG_UTILS_FNS: OnceCell<DebugUtils> = OnceCell::new();
+static ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Validation errors kept until the next [`check`], past which they're only logged
+const MAX_ERRORS: usize = 64;
+
+/// Functions of `VK_EXT_debug_utils`, loaded if the extension was enabled
+pub(crate) struct DebugUtils {
+    messenger: vk::DebugUtilsMessengerEXT,
+    destroy_messenger: vk::FnDestroyDebugUtilsMessengerEXT,
+    set_object_name: Option<vk::FnSetDebugUtilsObjectNameEXT>,
+    collect_errors: bool,
+    printf: bool
+}
+
+/// Returns the debug utils functions, if the extension is enabled
+#[inline]
+pub(crate) fn debug_utils () -> Option<&'static DebugUtils> {
+    return DEBUG_UTILS_FNS.get()
+}
+
+/// Returns an error with every validation error reported since the last call (up to 64 of them), if any.
+/// Should be called regularly (e.g. after waiting on each submission), since errors are kept until then.
+///
+/// Validation errors are only collected in debug builds, after enabling them with [`Builder::validation_errors`](crate::Builder::validation_errors).
+#[inline]
+pub fn check () -> Result<()> {
+    #[cfg(debug_assertions)]
+    if debug_utils().is_some_and(|x| x.collect_errors) {
+        let mut errors = match ERRORS.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner()
+        };
+
+        if !errors.is_empty() {
+            let msg = errors.join("\n");
+            errors.clear();
+            return Err(Error::Validation(msg))
+        }
+    }
+
+    return Ok(())
+}
+
+/// Gives a debug name to the object `handle` of type `typ`, shown in validation messages & captures.
+/// Does nothing if `VK_EXT_debug_utils` isn't enabled.
+pub(crate) fn set_name (device: &Device, typ: vk::ObjectType, handle: u64, name: &CStr) -> Result<()> {
+    if let Some(set_object_name) = debug_utils().and_then(|x| x.set_object_name) {
+        let info = vk::DebugUtilsObjectNameInfoEXT {
+            sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+            pNext: core::ptr::null(),
+            objectType: typ,
+            objectHandle: handle,
+            pObjectName: name.as_ptr(),
+        };
+
+        tri! {
+            (set_object_name)(device.id(), addr_of!(info))
+        }
+    }
+
+    return Ok(())
+}
+
+/// Returns `true` if the instance-level `layer` is available
+pub(crate) unsafe fn has_layer (get_instance_proc_addr: vk::FnGetInstanceProcAddr, layer: &CStr) -> bool {
+    let enumerate: Option<vk::FnEnumerateInstanceLayerProperties> = transmute(get_instance_proc_addr(0, proc::cstr!("vkEnumerateInstanceLayerProperties").as_ptr()));
+    let Some(enumerate) = enumerate else { return false };
+
+    let mut len = 0;
+    if enumerate(addr_of_mut!(len), core::ptr::null_mut()) != vk::SUCCESS { return false }
+
+    let mut props = Vec::<vk::LayerProperties>::with_capacity(len as usize);
+    if enumerate(addr_of_mut!(len), props.as_mut_ptr()) != vk::SUCCESS { return false }
+    props.set_len(len as usize);
+
+    return props.iter().any(|x| CStr::from_ptr(x.layerName.as_ptr()) == layer)
+}
+
+/// Returns `true` if the instance-level `extension` is available, either from the implementation or from `layer`
+pub(crate) unsafe fn has_extension (get_instance_proc_addr: vk::FnGetInstanceProcAddr, layer: Option<&CStr>, extension: &CStr) -> bool {
+    let enumerate: Option<vk::FnEnumerateInstanceExtensionProperties> = transmute(get_instance_proc_addr(0, proc::cstr!("vkEnumerateInstanceExtensionProperties").as_ptr()));
+    let Some(enumerate) = enumerate else { return false };
+    let layer = layer.map_or(core::ptr::null(), CStr::as_ptr);
+
+    let mut len = 0;
+    if enumerate(layer, addr_of_mut!(len), core::ptr::null_mut()) != vk::SUCCESS { return false }
+
+    let mut props = Vec::<vk::ExtensionProperties>::with_capacity(len as usize);
+    if enumerate(layer, addr_of_mut!(len), props.as_mut_ptr()) != vk::SUCCESS { return false }
+    props.set_len(len as usize);
+
+    return props.iter().any(|x| CStr::from_ptr(x.extensionName.as_ptr()) == extension)
+}
+
+/// Info of the messenger that routes messages into the `log` crate
+pub(crate) fn messenger_info () -> vk::DebugUtilsMessengerCreateInfoEXT {
+    return vk::DebugUtilsMessengerCreateInfoEXT {
+        sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+        pNext: core::ptr::null(),
+        flags: 0,
+        messageSeverity: MessageSeverity::all().bits(),
+        messageType: MessageTypes::all().bits(),
+        pfnUserCallback: callback,
+        pUserData: core::ptr::null_mut(),
+    }
+}
+
+/// Info enabling the validation layer's debug printf, chained ahead of `next`
+pub(crate) fn printf_features (next: *const c_void) -> vk::ValidationFeaturesEXT {
+    return vk::ValidationFeaturesEXT {
+        sType: vk::STRUCTURE_TYPE_VALIDATION_FEATURES_EXT,
+        pNext: next,
+        enabledValidationFeatureCount: ENABLED_FEATURES.len() as u32,
+        pEnabledValidationFeatures: ENABLED_FEATURES.as_ptr(),
+        disabledValidationFeatureCount: 0,
+        pDisabledValidationFeatures: core::ptr::null(),
+    }
+}
+
+/// Loads the debug utils functions & registers the messenger on `instance`
+pub(crate) unsafe fn init (get_instance_proc_addr: vk::FnGetInstanceProcAddr, instance: vk::Instance, collect_errors: bool, printf: bool) -> Result<()> {
+    let create: Option<vk::FnCreateDebugUtilsMessengerEXT> = transmute(get_instance_proc_addr(instance, proc::cstr!("vkCreateDebugUtilsMessengerEXT").as_ptr()));
+    let destroy: Option<vk::FnDestroyDebugUtilsMessengerEXT> = transmute(get_instance_proc_addr(instance, proc::cstr!("vkDestroyDebugUtilsMessengerEXT").as_ptr()));
+    let (Some(create), Some(destroy_messenger)) = (create, destroy) else {
+        return Err(vk::ERROR_EXTENSION_NOT_PRESENT.into())
+    };
+
+    let info = messenger_info();
+    let mut messenger = 0;
+    tri! {
+        create(instance, addr_of!(info), core::ptr::null(), addr_of_mut!(messenger))
+    }
+
+    let set_object_name = transmute(get_instance_proc_addr(instance, proc::cstr!("vkSetDebugUtilsObjectNameEXT").as_ptr()));
+    let _ = DEBUG_UTILS_FNS.set(DebugUtils { messenger, destroy_messenger, set_object_name, collect_errors, printf });
+    return Ok(())
+}
+
+/// Destroys the messenger, before the instance is destroyed
+pub(crate) fn destroy (entry: &Entry) {
+    if let Some(utils) = debug_utils() {
+        (utils.destroy_messenger)(entry.instance.get(), utils.messenger, core::ptr::null())
+    }
+}
+
+unsafe extern "system" fn callback (
+    severity: vk::DebugUtilsMessageSeverityFlagBitsEXT,
+    types: vk::DebugUtilsMessageTypeFlagsEXT,
+    data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _user: *mut c_void
+) -> vk::Bool32 {
+    let Some(data) = data.as_ref() else { return vk::FALSE };
+    let id = cstr_or_empty(data.pMessageIdName);
+    let msg = cstr_or_empty(data.pMessage);
+
+    // Shader printf messages are reported as (info) validation messages
+    if id.contains("DEBUG-PRINTF") {
+        let record = PrintfRecord::parse(&msg);
+        log::info!(target: "vulkan::printf", "{record}");
+        printf::push(record);
+        return vk::FALSE
+    }
+
+    let severity = MessageSeverity::from_bits_truncate(severity);
+    let level = match severity {
+        x if x.contains(MessageSeverity::ERROR) => log::Level::Error,
+        x if x.contains(MessageSeverity::WARNING) => log::Level::Warn,
+        x if x.contains(MessageSeverity::INFO) => log::Level::Debug,
+        _ => log::Level::Trace
+    };
+
+    let types = MessageTypes::from_bits_truncate(types);
+    let target = match types {
+        x if x.contains(MessageTypes::VALIDATION) => "vulkan::validation",
+        x if x.contains(MessageTypes::PERFORMANCE) => "vulkan::performance",
+        _ => "vulkan"
+    };
+
+    log::log!(target: target, level, "[{id}] {msg}");
+
+    #[cfg(debug_assertions)]
+    if level == log::Level::Error && debug_utils().map_or(true, |x| x.collect_errors) {
+        let mut errors = match ERRORS.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner()
+        };
+
+        if errors.len() < MAX_ERRORS {
+            errors.push(format!("[{id}] {msg}"))
+        }
+    }
+
+    // Returning true would abort the call that triggered the message
+    return vk::FALSE
+}
+
+#[inline]
+unsafe fn cstr_or_empty<'a> (ptr: *const c_char) -> std::borrow::Cow<'a, str> {
+    if ptr.is_null() { return std::borrow::Cow::Borrowed("") }
+    return CStr::from_ptr(ptr).to_string_lossy()
+}
+
+bitflags::bitflags! {
+    #[repr(transparent)]
+    struct MessageSeverity: vk::DebugUtilsMessageSeverityFlagBitsEXT {
+        const VERBOSE = vk::DEBUG_UTILS_MESSAGE_SEVERITY_VERBOSE_BIT_EXT;
+        const INFO = vk::DEBUG_UTILS_MESSAGE_SEVERITY_INFO_BIT_EXT;
+        const WARNING = vk::DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT;
+        const ERROR = vk::DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT;
+    }
+
+    #[repr(transparent)]
+    struct MessageTypes: vk::DebugUtilsMessageTypeFlagsEXT {
+        const GENERAL = vk::DEBUG_UTILS_MESSAGE_TYPE_GENERAL_BIT_EXT;
+        const VALIDATION = vk::DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT;
+        const PERFORMANCE = vk::DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT;
+    }
+}
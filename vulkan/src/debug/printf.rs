@@ -0,0 +1,130 @@
+use std::{collections::{HashMap, VecDeque}, ffi::{CStr, CString}, fmt::Display, sync::Mutex};
+use once_cell::sync::Lazy;
+
+pub(crate) const VALIDATION_FEATURES: &CStr = unsafe { proc::cstr!("VK_EXT_validation_features") };
+pub(crate) const ENABLED_FEATURES: [vk::ValidationFeatureEnableEXT; 1] = [vk::VALIDATION_FEATURE_ENABLE_DEBUG_PRINTF_EXT];
+
+/// Maximum number of records kept until they're taken
+const CAPACITY: usize = 4096;
+
+static RECORDS: Mutex<VecDeque<PrintfRecord>> = Mutex::new(VecDeque::new());
+static ENTRY_POINTS: Lazy<Mutex<HashMap<u64, CString>>> = Lazy::new(Default::default);
+
+/// Message printed by a shader through `debug_printf`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PrintfRecord {
+    /// Handle of the pipeline that printed the message
+    pub pipeline: Option<u64>,
+    /// Entry point of the pipeline that printed the message
+    pub entry_point: Option<CString>,
+    /// Global invocation id of the invocation that printed the message
+    pub invocation: Option<[u32; 3]>,
+    pub message: String
+}
+
+impl PrintfRecord {
+    /// Parses a message reported by the validation layer's debug printf
+    pub fn parse (msg: &str) -> Self {
+        let pipeline = after(msg, "Pipeline ")
+            .and_then(|x| after(x, "(0x"))
+            .and_then(|x| u64::from_str_radix(take_while(x, |c| c.is_ascii_hexdigit()), 16).ok());
+
+        let invocation = after(msg, "Global invocation ID (x, y, z) = (").and_then(|x| {
+            let mut iter = take_while(x, |c| c != ')').split(',').map(|x| x.trim().parse::<u32>());
+            match (iter.next(), iter.next(), iter.next()) {
+                (Some(Ok(x)), Some(Ok(y)), Some(Ok(z))) => Some([x, y, z]),
+                _ => None
+            }
+        });
+
+        // Verbose messages place the printed text after the location header
+        let message = match invocation.is_some() || pipeline.is_some() {
+            true => msg.rsplit('\n').map(str::trim).find(|x| !x.is_empty()).unwrap_or(""),
+            false => msg.trim()
+        };
+
+        let entry_point = pipeline.and_then(entry_point);
+        return Self { pipeline, entry_point, invocation, message: message.to_string() }
+    }
+}
+
+impl Display for PrintfRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(entry) = &self.entry_point {
+            write!(f, "{}", entry.to_string_lossy())?;
+        }
+        if let Some([x, y, z]) = self.invocation {
+            write!(f, "({x}, {y}, {z})")?;
+        }
+        if self.entry_point.is_some() || self.invocation.is_some() {
+            f.write_str(": ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Takes every message printed by shaders since the last call
+#[inline]
+pub fn take_printf () -> Vec<PrintfRecord> {
+    let mut records = match RECORDS.lock() {
+        Ok(x) => x,
+        Err(e) => e.into_inner()
+    };
+    return records.drain(..).collect()
+}
+
+#[inline]
+pub(crate) fn push (record: PrintfRecord) {
+    let mut records = match RECORDS.lock() {
+        Ok(x) => x,
+        Err(e) => e.into_inner()
+    };
+
+    if records.len() >= CAPACITY {
+        records.pop_front();
+    }
+    records.push_back(record);
+}
+
+/// Remembers the entry point of `pipeline`, to be attached to its messages
+#[inline]
+pub(crate) fn register_pipeline (pipeline: u64, entry: &CStr) {
+    if super::debug_utils().is_some_and(|x| x.printf) {
+        let mut entries = match ENTRY_POINTS.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner()
+        };
+        entries.insert(pipeline, entry.to_owned());
+    }
+}
+
+#[inline]
+pub(crate) fn unregister_pipeline (pipeline: u64) {
+    if super::debug_utils().is_some_and(|x| x.printf) {
+        let mut entries = match ENTRY_POINTS.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner()
+        };
+        entries.remove(&pipeline);
+    }
+}
+
+#[inline]
+fn entry_point (pipeline: u64) -> Option<CString> {
+    let entries = match ENTRY_POINTS.lock() {
+        Ok(x) => x,
+        Err(e) => e.into_inner()
+    };
+    return entries.get(&pipeline).cloned()
+}
+
+#[inline]
+fn after<'a> (s: &'a str, pat: &str) -> Option<&'a str> {
+    return s.find(pat).map(|i| &s[i + pat.len()..])
+}
+
+#[inline]
+fn take_while (s: &str, f: impl Fn(char) -> bool) -> &str {
+    let end = s.find(|c| !f(c)).unwrap_or(s.len());
+    return &s[..end]
+}
@@ -1,4 +1,4 @@
-use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, ops::{Deref, DerefMut}};
+use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, ops::{Deref, DerefMut}, ffi::CStr};
 use crate::{utils::usize_to_u32, Result, device::{Device, DeviceRef}, Entry, shader::Shader, buffer::Buffer, alloc::DeviceAllocator};
 
 pub struct Builder<D> {
@@ -100,17 +100,22 @@ pub struct DescriptorSets<D: DeviceRef> {
 }
 
 impl<D: DeviceRef> DescriptorSets<D> {
+    #[inline]
     pub fn new<U: DeviceRef> (pool: DescriptorPool<D>, shaders: &[Shader<U>]) -> Result<Self> {
         let layouts = shaders.iter().map(Shader::layout).collect::<Vec<_>>();
+        return Self::from_layouts(pool, &layouts)
+    }
+
+    pub fn from_layouts (pool: DescriptorPool<D>, layouts: &[vk::DescriptorSetLayout]) -> Result<Self> {
         let info = vk::DescriptorSetAllocateInfo {
             sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO,
             pNext: core::ptr::null(),
             descriptorPool: pool.id(),
-            descriptorSetCount: usize_to_u32(shaders.len()),
+            descriptorSetCount: usize_to_u32(layouts.len()),
             pSetLayouts: layouts.as_ptr(),
         };
 
-        let mut sets = Box::<[vk::DescriptorSet]>::new_uninit_slice(shaders.len());
+        let mut sets = Box::<[vk::DescriptorSet]>::new_uninit_slice(layouts.len());
         tri! {
             (Entry::get().allocate_descriptor_sets)(
                 pool.device.id(),
@@ -140,6 +145,12 @@ impl<D: DeviceRef> DescriptorSets<D> {
 }
 
 impl<D: DeviceRef> DescriptorSets<D> {
+    /// Gives the descriptor set at `idx` a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, idx: usize, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_DESCRIPTOR_SET, self[idx].id(), name)
+    }
+
     pub fn update<'b> (&mut self, write: impl IntoIterator<Item = &'b WriteDescriptorSet>) {
         let write = write.into_iter()
             .zip(0u32..)
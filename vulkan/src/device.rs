@@ -1,12 +1,14 @@
-use std::{num::NonZeroU64, marker::PhantomData, ptr::{addr_of_mut, addr_of}, hash::Hash, ffi::CStr, ops::Deref};
-use crate::{Result, Entry, queue::{Queue}, physical_dev::{PhysicalDevice, Family}, utils::usize_to_u32};
+use std::{num::NonZeroU64, marker::PhantomData, ptr::{addr_of_mut, addr_of}, hash::Hash, ffi::{CStr, CString}, ops::Deref};
+use crate::{Result, Entry, queue::{Queue}, physical_dev::{PhysicalDevice, Family, HeapBudget, MEMORY_BUDGET}, features::{Features, Feature}, error::Error, utils::usize_to_u32};
 
 pub trait DeviceRef = Deref<Target = Device>;
 
 #[derive(Debug)]
 pub struct Device {
     inner: NonZeroU64,
-    parent: PhysicalDevice
+    parent: PhysicalDevice,
+    features: Box<Features>,
+    extensions: Vec<CString>
 }
 
 impl PartialEq for Device {
@@ -41,6 +43,39 @@ impl Device {
     pub fn physical (&self) -> PhysicalDevice {
         return self.parent
     }
+
+    /// Features enabled on the device
+    #[inline]
+    pub fn features (&self) -> &Features {
+        return &self.features
+    }
+
+    /// Returns `true` if `feature` was enabled on the device
+    #[inline]
+    pub fn has_feature (&self, feature: Feature) -> bool {
+        return self.features.get(feature)
+    }
+
+    /// Extensions enabled on the device
+    #[inline]
+    pub fn extensions (&self) -> impl Iterator<Item = &CStr> {
+        return self.extensions.iter().map(CString::as_c_str)
+    }
+
+    /// Returns `true` if `extension` was enabled on the device
+    #[inline]
+    pub fn has_extension (&self, extension: &CStr) -> bool {
+        return self.extensions.iter().any(|x| x.as_c_str() == extension)
+    }
+
+    /// Current budget of each of the device's memory heaps, by heap index.
+    ///
+    /// Budgets & usages come from `VK_EXT_memory_budget` if it was enabled on the device.
+    /// Otherwise, each heap's budget is its size, and its usage is unknown (zero).
+    #[inline]
+    pub fn memory_budget (&self) -> Vec<HeapBudget> {
+        return self.parent.heap_budgets(self.has_extension(MEMORY_BUDGET))
+    }
 }
 
 impl Drop for Device {
@@ -52,8 +87,13 @@ impl Drop for Device {
 
 pub struct Builder<'a> {
     inner: vk::DeviceCreateInfo,
+    queues: Vec<vk::DeviceQueueCreateInfo>,
+    required_features: Vec<Feature>,
+    preferred_features: Vec<Feature>,
+    required_extensions: Vec<&'a CStr>,
+    preferred_extensions: Vec<&'a CStr>,
     parent: PhysicalDevice,
-    _phtm: PhantomData<(&'a vk::PhysicalDeviceFeatures, &'a CStr)>
+    _phtm: PhantomData<&'a CStr>
 }
 
 impl<'a> Builder<'a> {
@@ -69,8 +109,13 @@ impl<'a> Builder<'a> {
                 ppEnabledLayerNames: core::ptr::null_mut(), // depr
                 enabledExtensionCount: 0,
                 ppEnabledExtensionNames: core::ptr::null_mut(),
-                pEnabledFeatures: Box::into_raw(parent.features()).cast(),
+                pEnabledFeatures: core::ptr::null_mut(), // features are chained through `pNext`
             },
+            queues: Vec::new(),
+            required_features: Vec::new(),
+            preferred_features: Vec::new(),
+            required_extensions: Vec::new(),
+            preferred_extensions: Vec::new(),
             parent,
             _phtm: PhantomData
         }
@@ -82,89 +127,170 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Requires `feature`, failing to build the device if it isn't supported
+    #[inline]
+    pub fn require (mut self, feature: Feature) -> Self {
+        self.required_features.push(feature);
+        self
+    }
+
+    /// Requires every feature in `iter`, failing to build the device if any of them isn't supported
+    #[inline]
+    pub fn require_features<I: IntoIterator<Item = Feature>> (mut self, iter: I) -> Self {
+        self.required_features.extend(iter);
+        self
+    }
+
+    /// Enables `feature` if it's supported
+    #[inline]
+    pub fn prefer (mut self, feature: Feature) -> Self {
+        self.preferred_features.push(feature);
+        self
+    }
+
+    /// Enables every feature in `iter` that is supported
+    #[inline]
+    pub fn prefer_features<I: IntoIterator<Item = Feature>> (mut self, iter: I) -> Self {
+        self.preferred_features.extend(iter);
+        self
+    }
+
+    /// Enables timeline semaphores (Vulkan 1.2)
+    #[inline]
+    pub fn timeline_semaphores (self) -> Self {
+        return self.require(Feature::TimelineSemaphore)
+    }
+
+    /// Requests `priorities.len()` queues from a single family, which defaults to the best compute family.
+    /// May be called once per family, to request queues from several families.
     #[inline]
     pub fn queues (self, priorities: &'a [f32]) -> QueueBuilder<'a> {
         return QueueBuilder::new(self, priorities)
     }
 
+    /// Requires every extension in `iter`, failing to build the device if any of them isn't supported
     #[inline]
     pub fn extensions<I: IntoIterator<Item = &'a CStr>> (mut self, iter: I) -> Self {
-        if self.inner.enabledExtensionCount > 0 && !self.inner.ppEnabledExtensionNames.is_null() {
-            unsafe {
-                let _ = Box::from_raw(core::slice::from_raw_parts_mut(
-                    self.inner.ppEnabledExtensionNames.cast_mut(),
-                    self.inner.enabledExtensionCount as usize
-                ));
+        self.required_extensions.extend(iter);
+        self
+    }
+
+    /// Enables every extension in `iter` that is supported
+    #[inline]
+    pub fn prefer_extensions<I: IntoIterator<Item = &'a CStr>> (mut self, iter: I) -> Self {
+        self.preferred_extensions.extend(iter);
+        self
+    }
+
+    /// Resolves the features to enable, failing if any of the required ones isn't supported
+    fn negotiate_features (&self) -> Result<Box<Features>> {
+        let available = self.parent.features();
+        let (major, minor) = available.version();
+        let mut enabled = Box::new(Features::new(major, minor));
+
+        if let Some(feature) = self.required_features.iter().find(|x| !available.is_supported(**x)) {
+            let (req_major, req_minor) = feature.version();
+            return Err(Error::NoSuitableDevice(format!("`{}` requires Vulkan {req_major}.{req_minor}, the device is used as Vulkan {major}.{minor}", feature.name())))
+        }
+
+        let missing = self.required_features.iter()
+            .filter(|x| !available.get(**x))
+            .map(|x| x.name())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(Error::MissingFeatures(missing))
+        }
+
+        for feature in self.required_features.iter().chain(self.preferred_features.iter()) {
+            if available.get(*feature) {
+                enabled.set(*feature, true);
             }
         }
 
-        let ext = iter.into_iter().map(CStr::as_ptr).collect::<Box<[_]>>();
-        self.inner.enabledExtensionCount = usize_to_u32(ext.len());
-        self.inner.ppEnabledExtensionNames = Box::into_raw(ext).cast();
-        self
+        return Ok(enabled)
+    }
+
+    /// Resolves the extensions to enable, failing if any of the required ones isn't supported
+    fn negotiate_extensions (&self) -> Result<Vec<&'a CStr>> {
+        if self.required_extensions.is_empty() && self.preferred_extensions.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        let available = self.parent.extensions()?;
+        let is_available = |ext: &CStr| available.iter().any(|x| x.name() == ext);
+
+        let missing = self.required_extensions.iter()
+            .filter(|x| !is_available(**x))
+            .map(|x| x.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(Error::MissingExtensions(missing))
+        }
+
+        let mut enabled = self.required_extensions.clone();
+        for ext in self.preferred_extensions.iter() {
+            if is_available(ext) && !enabled.contains(ext) {
+                enabled.push(*ext);
+            }
+        }
+
+        return Ok(enabled)
     }
 
     pub fn build (self) -> Result<(Device, Vec<Queue>)> {
         let entry = Entry::get();
+        let mut features = self.negotiate_features()?;
+        let extensions = self.negotiate_extensions()?;
+        let extension_ptrs = extensions.iter().map(|x| x.as_ptr()).collect::<Vec<_>>();
+
+        let mut info = self.inner;
+        info.pNext = features.chain(self.inner.pNext.cast_mut()).cast();
+        info.queueCreateInfoCount = usize_to_u32(self.queues.len());
+        info.pQueueCreateInfos = self.queues.as_ptr();
+        info.enabledExtensionCount = usize_to_u32(extension_ptrs.len());
+        info.ppEnabledExtensionNames = extension_ptrs.as_ptr();
 
         let mut result: vk::Device = 0;
         tri! {
-            (entry.create_device)(self.parent.id(), addr_of!(self.inner), core::ptr::null_mut(), addr_of_mut!(result))
+            (entry.create_device)(self.parent.id(), addr_of!(info), core::ptr::null_mut(), addr_of_mut!(result))
         };
 
         if let Some(inner) = NonZeroU64::new(result) {
-            let mut queues = Vec::new();
-            if self.inner.queueCreateInfoCount > 0 && !self.inner.pQueueCreateInfos.is_null() {
-                let infos = unsafe {
-                    core::slice::from_raw_parts(
-                        self.inner.pQueueCreateInfos,
-                        self.inner.queueCreateInfoCount as usize
-                    )
-                };
-
-                for info in infos {
-                    queues.reserve(info.queueCount as usize);
-
-                    for i in 0..info.queueCount {
-                        let mut queue = 0;
-                        (entry.get_device_queue)(inner.get(), info.queueFamilyIndex, i, addr_of_mut!(queue));
-
-                        if let Some(inner) = NonZeroU64::new(queue) {
-                            queues.push(Queue { inner, index: i });
-                        } else {
-                            return Err(vk::ERROR_UNKNOWN.into())
-                        }
+            let device = Device {
+                inner,
+                parent: self.parent,
+                features,
+                extensions: extensions.into_iter().map(CStr::to_owned).collect()
+            };
+
+            // Queues are returned in the order their families were requested
+            let families = self.parent.families().collect::<Vec<_>>();
+            let mut queues = Vec::with_capacity(self.queues.iter().map(|x| x.queueCount as usize).sum());
+
+            for info in self.queues.iter() {
+                let family = families[info.queueFamilyIndex as usize];
+
+                for i in 0..info.queueCount {
+                    let mut queue = 0;
+                    (entry.get_device_queue)(inner.get(), info.queueFamilyIndex, i, addr_of_mut!(queue));
+
+                    if let Some(inner) = NonZeroU64::new(queue) {
+                        queues.push(Queue { inner, index: i, family });
+                    } else {
+                        return Err(vk::ERROR_UNKNOWN.into())
                     }
                 }
             }
 
-            return Ok((Device { inner, parent: self.parent }, queues))
+            return Ok((device, queues))
         }
 
         return Err(vk::ERROR_UNKNOWN.into())
     }
 }
 
-impl Drop for Builder<'_> {
-    #[inline]
-    fn drop(&mut self) {
-        unsafe {
-            if !self.inner.pQueueCreateInfos.is_null() {
-                let _ = Box::from_raw(self.inner.pQueueCreateInfos.cast_mut());
-            }
-
-            if self.inner.enabledExtensionCount > 0 && !self.inner.ppEnabledExtensionNames.is_null() {
-                let _ = Box::from_raw(core::slice::from_raw_parts_mut(
-                    self.inner.ppEnabledExtensionNames.cast_mut(),
-                    self.inner.enabledExtensionCount as usize
-                ));
-            }
-
-            let _ = Box::from_raw(self.inner.pEnabledFeatures.cast_mut());
-        }
-    }
-}
-
 bitflags::bitflags! {
     #[repr(transparent)]
     pub struct DeviceFlags: vk::DeviceCreateFlags {
@@ -173,7 +299,7 @@ bitflags::bitflags! {
 }
 
 pub struct QueueBuilder<'a> {
-    inner: Box<vk::DeviceQueueCreateInfo>,
+    inner: vk::DeviceQueueCreateInfo,
     parent: Builder<'a>,
     _phtm: PhantomData<&'a [f32]>
 }
@@ -181,16 +307,18 @@ pub struct QueueBuilder<'a> {
 impl<'a> QueueBuilder<'a> {
     #[inline]
     pub fn new (parent: Builder<'a>, priorities: &'a [f32]) -> Self {
-        debug_assert!(f32::abs(priorities.iter().sum::<f32>() - 1f32) < f32::EPSILON);
+        debug_assert!(priorities.iter().all(|x| (0f32..=1f32).contains(x)));
+        let family = parent.parent.best_compute_family().map_or(0, Family::idx);
+
         return Self {
-            inner: Box::new(vk::DeviceQueueCreateInfo {
+            inner: vk::DeviceQueueCreateInfo {
                 sType: vk::STRUCTURE_TYPE_DEVICE_QUEUE_CREATE_INFO,
                 pNext: core::ptr::null_mut(),
                 flags: 0,
-                queueFamilyIndex: 0,
+                queueFamilyIndex: family,
                 queueCount: usize_to_u32(priorities.len()),
                 pQueuePriorities: priorities.as_ptr(),
-            }),
+            },
             parent,
             _phtm: PhantomData
         }
@@ -215,13 +343,22 @@ impl<'a> QueueBuilder<'a> {
         Ok(self)
     }
 
-    #[inline]
-    pub fn build (mut self) -> Builder<'a> {
-        self.parent.inner.queueCreateInfoCount = 1;
-        let prev = core::mem::replace(&mut self.parent.inner.pQueueCreateInfos, Box::into_raw(self.inner));
-        if !prev.is_null() {
-            let _ = unsafe { Box::from_raw(prev.cast_mut()) };
+    /// Adds the queues to the device, replacing any previous request for the same family.
+    /// Fails if the family doesn't have as many queues as requested.
+    pub fn build (mut self) -> Result<Builder<'a>> {
+        let family_idx = self.inner.queueFamilyIndex;
+        let available = self.parent.parent.families()
+            .find(|x| x.idx() == family_idx)
+            .map_or(0, |x| x.queue_count());
+
+        if self.inner.queueCount > available {
+            return Err(Error::Validation(format!("requested {} queues from family {family_idx}, which only has {available}", self.inner.queueCount)))
+        }
+
+        match self.parent.queues.iter_mut().find(|x| x.queueFamilyIndex == self.inner.queueFamilyIndex) {
+            Some(prev) => *prev = self.inner,
+            None => self.parent.queues.push(self.inner)
         }
-        self.parent
+        return Ok(self.parent)
     }
 }
\ No newline at end of file
@@ -3,7 +3,27 @@ pub enum Error {
     #[error("{} ({0})", result_name(*.0).unwrap_or(""))]
     Vulkan (super::vk::Result),
     #[error("{0}")]
-    Library (#[from] libloading::Error)
+    Library (#[from] libloading::Error),
+    #[error("{0}")]
+    Io (#[from] std::io::Error),
+    #[error("Validation error: {0}")]
+    Validation (String),
+    #[error("Missing required device features: {}", .0.join(", "))]
+    MissingFeatures (Vec<&'static str>),
+    #[error("Missing required device extensions: {}", .0.join(", "))]
+    MissingExtensions (Vec<String>),
+    #[error("No suitable physical device: {0}")]
+    NoSuitableDevice (String),
+    #[error("No compatible memory type: {0}")]
+    NoMemoryType (String),
+    #[error("Command buffers can't be reset individually, their pool wasn't created with `RESET_COMMAND_BUFFER`")]
+    NotResettable,
+    #[error("Shader assertion failed in `{entry}` at invocation {invocation:?}: {message}")]
+    Shader {
+        entry: String,
+        invocation: [u32; 3],
+        message: String
+    }
 }
 
 impl Error {
@@ -0,0 +1,356 @@
+use std::{ffi::c_void, fmt::Debug};
+
+/// Features of a physical device, covering the Vulkan 1.0 to 1.3 feature structs.
+///
+/// The structs are chained through their `pNext` pointers right before being handed to Vulkan,
+/// only up to the version supported by both the device & the instance.
+/// On Vulkan 1.1, which has no grouped struct for its features, the individual 1.1 feature structs are chained instead.
+#[derive(Clone)]
+#[repr(C)]
+pub struct Features {
+    v10: vk::PhysicalDeviceFeatures2,
+    v11: vk::PhysicalDeviceVulkan11Features,
+    v12: vk::PhysicalDeviceVulkan12Features,
+    v13: vk::PhysicalDeviceVulkan13Features,
+    v11_structs: V11Structs,
+    version: (u32, u32)
+}
+
+/// Individual Vulkan 1.1 feature structs, mirroring `v11` on Vulkan 1.1 devices
+#[derive(Clone)]
+#[repr(C)]
+struct V11Structs {
+    storage_16bit: vk::PhysicalDevice16BitStorageFeatures,
+    multiview: vk::PhysicalDeviceMultiviewFeatures,
+    variable_pointers: vk::PhysicalDeviceVariablePointersFeatures,
+    protected_memory: vk::PhysicalDeviceProtectedMemoryFeatures,
+    sampler_ycbcr_conversion: vk::PhysicalDeviceSamplerYcbcrConversionFeatures,
+    shader_draw_parameters: vk::PhysicalDeviceShaderDrawParametersFeatures
+}
+
+/// Calls `$f!` with every Vulkan 1.1 feature, alongside the individual struct holding it
+macro_rules! v11_fields {
+    ($f:ident) => {
+        $f! {
+            storage_16bit.storageBuffer16BitAccess,
+            storage_16bit.uniformAndStorageBuffer16BitAccess,
+            storage_16bit.storagePushConstant16,
+            storage_16bit.storageInputOutput16,
+            multiview.multiview,
+            multiview.multiviewGeometryShader,
+            multiview.multiviewTessellationShader,
+            variable_pointers.variablePointersStorageBuffer,
+            variable_pointers.variablePointers,
+            protected_memory.protectedMemory,
+            sampler_ycbcr_conversion.samplerYcbcrConversion,
+            shader_draw_parameters.shaderDrawParameters
+        }
+    };
+}
+
+impl Features {
+    /// Creates a set of features with everything disabled, for a device supporting Vulkan `major.minor`
+    pub fn new (major: u32, minor: u32) -> Self {
+        unsafe {
+            let mut this = Self {
+                v10: core::mem::zeroed(),
+                v11: core::mem::zeroed(),
+                v12: core::mem::zeroed(),
+                v13: core::mem::zeroed(),
+                v11_structs: core::mem::zeroed(),
+                version: (major, minor)
+            };
+
+            this.v10.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2;
+            this.v11.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_1_FEATURES;
+            this.v12.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_2_FEATURES;
+            this.v13.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_3_FEATURES;
+
+            let structs = &mut this.v11_structs;
+            structs.storage_16bit.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_16BIT_STORAGE_FEATURES;
+            structs.multiview.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MULTIVIEW_FEATURES;
+            structs.variable_pointers.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_VARIABLE_POINTERS_FEATURES;
+            structs.protected_memory.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROTECTED_MEMORY_FEATURES;
+            structs.sampler_ycbcr_conversion.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SAMPLER_YCBCR_CONVERSION_FEATURES;
+            structs.shader_draw_parameters.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_SHADER_DRAW_PARAMETERS_FEATURES;
+            return this
+        }
+    }
+
+    /// Vulkan version the features were created for
+    #[inline]
+    pub fn version (&self) -> (u32, u32) {
+        return self.version
+    }
+
+    /// Returns `true` if the feature struct holding `feature` is chained for this version
+    #[inline]
+    pub fn is_supported (&self, feature: Feature) -> bool {
+        return self.version >= feature.version()
+    }
+
+    /// Enables `feature`
+    #[inline]
+    pub fn with (mut self, feature: Feature) -> Self {
+        self.set(feature, true);
+        self
+    }
+
+    /// Iterates over every enabled feature
+    #[inline]
+    pub fn iter (&self) -> impl '_ + Iterator<Item = Feature> {
+        return Feature::ALL.iter().copied().filter(|x| self.get(*x))
+    }
+
+    /// Links the feature structs supported by this version, placing `next` at the end of the chain.
+    /// `self` mustn't be moved while the returned pointer is in use, and [`unchain`](Self::unchain)
+    /// must be called once Vulkan has written the features to it.
+    pub(crate) fn chain (&mut self, next: *mut c_void) -> *mut vk::PhysicalDeviceFeatures2 {
+        self.v13.pNext = next;
+        self.v12.pNext = next;
+        self.v11.pNext = next;
+        self.v10.pNext = next;
+
+        if self.version == (1, 1) {
+            macro_rules! copy {
+                ($($s:ident.$field:ident),+) => {
+                    $(self.v11_structs.$s.$field = self.v11.$field;)+
+                };
+            }
+            v11_fields!(copy);
+
+            let structs = &mut self.v11_structs;
+            structs.storage_16bit.pNext = core::ptr::addr_of_mut!(structs.multiview).cast();
+            structs.multiview.pNext = core::ptr::addr_of_mut!(structs.variable_pointers).cast();
+            structs.variable_pointers.pNext = core::ptr::addr_of_mut!(structs.protected_memory).cast();
+            structs.protected_memory.pNext = core::ptr::addr_of_mut!(structs.sampler_ycbcr_conversion).cast();
+            structs.sampler_ycbcr_conversion.pNext = core::ptr::addr_of_mut!(structs.shader_draw_parameters).cast();
+            structs.shader_draw_parameters.pNext = next;
+            self.v10.pNext = core::ptr::addr_of_mut!(structs.storage_16bit).cast();
+        }
+
+        if self.version >= (1, 2) {
+            self.v10.pNext = core::ptr::addr_of_mut!(self.v11).cast();
+            self.v11.pNext = core::ptr::addr_of_mut!(self.v12).cast();
+        }
+
+        if self.version >= (1, 3) {
+            self.v12.pNext = core::ptr::addr_of_mut!(self.v13).cast();
+        }
+
+        return core::ptr::addr_of_mut!(self.v10)
+    }
+
+    /// Reads back the features Vulkan wrote to the chain returned by [`chain`](Self::chain)
+    pub(crate) fn unchain (&mut self) {
+        if self.version == (1, 1) {
+            macro_rules! copy {
+                ($($s:ident.$field:ident),+) => {
+                    $(self.v11.$field = self.v11_structs.$s.$field;)+
+                };
+            }
+            v11_fields!(copy);
+        }
+    }
+}
+
+impl Debug for Features {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+macro_rules! features {
+    ($([$($path:ident).+] $vk:ident => $variant:ident),+) => {
+        /// Individual device feature, named after its field in the Vulkan feature structs
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum Feature {
+            $($variant),+
+        }
+
+        impl Feature {
+            pub const ALL: &'static [Feature] = &[$(Feature::$variant),+];
+
+            /// Name of the feature in the Vulkan specification
+            #[inline]
+            pub const fn name (self) -> &'static str {
+                return match self {
+                    $(Self::$variant => stringify!($vk)),+
+                }
+            }
+        }
+
+        impl Features {
+            #[inline]
+            pub fn get (&self, feature: Feature) -> bool {
+                return match feature {
+                    $(Feature::$variant => self.$($path).+.$vk == vk::TRUE),+
+                }
+            }
+
+            #[inline]
+            pub fn set (&mut self, feature: Feature, enabled: bool) {
+                let value = if enabled { vk::TRUE } else { vk::FALSE };
+                match feature {
+                    $(Feature::$variant => self.$($path).+.$vk = value),+
+                }
+            }
+        }
+    };
+}
+
+impl Feature {
+    /// Minimum Vulkan version needed to query or enable this feature through [`Features`]
+    #[inline]
+    pub fn version (self) -> (u32, u32) {
+        let idx = self as usize;
+        return match idx {
+            _ if idx < V11_START => (1, 0),
+            _ if idx < V12_START => (1, 1),
+            _ if idx < V13_START => (1, 2),
+            _ => (1, 3)
+        }
+    }
+}
+
+const V11_START: usize = Feature::StorageBuffer16BitAccess as usize;
+const V12_START: usize = Feature::SamplerMirrorClampToEdge as usize;
+const V13_START: usize = Feature::RobustImageAccess as usize;
+
+features! {
+    // Vulkan 1.0
+    [v10.features] robustBufferAccess => RobustBufferAccess,
+    [v10.features] fullDrawIndexUint32 => FullDrawIndexUint32,
+    [v10.features] imageCubeArray => ImageCubeArray,
+    [v10.features] independentBlend => IndependentBlend,
+    [v10.features] geometryShader => GeometryShader,
+    [v10.features] tessellationShader => TessellationShader,
+    [v10.features] sampleRateShading => SampleRateShading,
+    [v10.features] dualSrcBlend => DualSrcBlend,
+    [v10.features] logicOp => LogicOp,
+    [v10.features] multiDrawIndirect => MultiDrawIndirect,
+    [v10.features] drawIndirectFirstInstance => DrawIndirectFirstInstance,
+    [v10.features] depthClamp => DepthClamp,
+    [v10.features] depthBiasClamp => DepthBiasClamp,
+    [v10.features] fillModeNonSolid => FillModeNonSolid,
+    [v10.features] depthBounds => DepthBounds,
+    [v10.features] wideLines => WideLines,
+    [v10.features] largePoints => LargePoints,
+    [v10.features] alphaToOne => AlphaToOne,
+    [v10.features] multiViewport => MultiViewport,
+    [v10.features] samplerAnisotropy => SamplerAnisotropy,
+    [v10.features] textureCompressionETC2 => TextureCompressionETC2,
+    [v10.features] textureCompressionASTC_LDR => TextureCompressionASTCLDR,
+    [v10.features] textureCompressionBC => TextureCompressionBC,
+    [v10.features] occlusionQueryPrecise => OcclusionQueryPrecise,
+    [v10.features] pipelineStatisticsQuery => PipelineStatisticsQuery,
+    [v10.features] vertexPipelineStoresAndAtomics => VertexPipelineStoresAndAtomics,
+    [v10.features] fragmentStoresAndAtomics => FragmentStoresAndAtomics,
+    [v10.features] shaderTessellationAndGeometryPointSize => ShaderTessellationAndGeometryPointSize,
+    [v10.features] shaderImageGatherExtended => ShaderImageGatherExtended,
+    [v10.features] shaderStorageImageExtendedFormats => ShaderStorageImageExtendedFormats,
+    [v10.features] shaderStorageImageMultisample => ShaderStorageImageMultisample,
+    [v10.features] shaderStorageImageReadWithoutFormat => ShaderStorageImageReadWithoutFormat,
+    [v10.features] shaderStorageImageWriteWithoutFormat => ShaderStorageImageWriteWithoutFormat,
+    [v10.features] shaderUniformBufferArrayDynamicIndexing => ShaderUniformBufferArrayDynamicIndexing,
+    [v10.features] shaderSampledImageArrayDynamicIndexing => ShaderSampledImageArrayDynamicIndexing,
+    [v10.features] shaderStorageBufferArrayDynamicIndexing => ShaderStorageBufferArrayDynamicIndexing,
+    [v10.features] shaderStorageImageArrayDynamicIndexing => ShaderStorageImageArrayDynamicIndexing,
+    [v10.features] shaderClipDistance => ShaderClipDistance,
+    [v10.features] shaderCullDistance => ShaderCullDistance,
+    [v10.features] shaderFloat64 => ShaderFloat64,
+    [v10.features] shaderInt64 => ShaderInt64,
+    [v10.features] shaderInt16 => ShaderInt16,
+    [v10.features] shaderResourceResidency => ShaderResourceResidency,
+    [v10.features] shaderResourceMinLod => ShaderResourceMinLod,
+    [v10.features] sparseBinding => SparseBinding,
+    [v10.features] sparseResidencyBuffer => SparseResidencyBuffer,
+    [v10.features] sparseResidencyImage2D => SparseResidencyImage2D,
+    [v10.features] sparseResidencyImage3D => SparseResidencyImage3D,
+    [v10.features] sparseResidency2Samples => SparseResidency2Samples,
+    [v10.features] sparseResidency4Samples => SparseResidency4Samples,
+    [v10.features] sparseResidency8Samples => SparseResidency8Samples,
+    [v10.features] sparseResidency16Samples => SparseResidency16Samples,
+    [v10.features] sparseResidencyAliased => SparseResidencyAliased,
+    [v10.features] variableMultisampleRate => VariableMultisampleRate,
+    [v10.features] inheritedQueries => InheritedQueries,
+    // Vulkan 1.1
+    [v11] storageBuffer16BitAccess => StorageBuffer16BitAccess,
+    [v11] uniformAndStorageBuffer16BitAccess => UniformAndStorageBuffer16BitAccess,
+    [v11] storagePushConstant16 => StoragePushConstant16,
+    [v11] storageInputOutput16 => StorageInputOutput16,
+    [v11] multiview => Multiview,
+    [v11] multiviewGeometryShader => MultiviewGeometryShader,
+    [v11] multiviewTessellationShader => MultiviewTessellationShader,
+    [v11] variablePointersStorageBuffer => VariablePointersStorageBuffer,
+    [v11] variablePointers => VariablePointers,
+    [v11] protectedMemory => ProtectedMemory,
+    [v11] samplerYcbcrConversion => SamplerYcbcrConversion,
+    [v11] shaderDrawParameters => ShaderDrawParameters,
+    // Vulkan 1.2
+    [v12] samplerMirrorClampToEdge => SamplerMirrorClampToEdge,
+    [v12] drawIndirectCount => DrawIndirectCount,
+    [v12] storageBuffer8BitAccess => StorageBuffer8BitAccess,
+    [v12] uniformAndStorageBuffer8BitAccess => UniformAndStorageBuffer8BitAccess,
+    [v12] storagePushConstant8 => StoragePushConstant8,
+    [v12] shaderBufferInt64Atomics => ShaderBufferInt64Atomics,
+    [v12] shaderSharedInt64Atomics => ShaderSharedInt64Atomics,
+    [v12] shaderFloat16 => ShaderFloat16,
+    [v12] shaderInt8 => ShaderInt8,
+    [v12] descriptorIndexing => DescriptorIndexing,
+    [v12] shaderInputAttachmentArrayDynamicIndexing => ShaderInputAttachmentArrayDynamicIndexing,
+    [v12] shaderUniformTexelBufferArrayDynamicIndexing => ShaderUniformTexelBufferArrayDynamicIndexing,
+    [v12] shaderStorageTexelBufferArrayDynamicIndexing => ShaderStorageTexelBufferArrayDynamicIndexing,
+    [v12] shaderUniformBufferArrayNonUniformIndexing => ShaderUniformBufferArrayNonUniformIndexing,
+    [v12] shaderSampledImageArrayNonUniformIndexing => ShaderSampledImageArrayNonUniformIndexing,
+    [v12] shaderStorageBufferArrayNonUniformIndexing => ShaderStorageBufferArrayNonUniformIndexing,
+    [v12] shaderStorageImageArrayNonUniformIndexing => ShaderStorageImageArrayNonUniformIndexing,
+    [v12] shaderInputAttachmentArrayNonUniformIndexing => ShaderInputAttachmentArrayNonUniformIndexing,
+    [v12] shaderUniformTexelBufferArrayNonUniformIndexing => ShaderUniformTexelBufferArrayNonUniformIndexing,
+    [v12] shaderStorageTexelBufferArrayNonUniformIndexing => ShaderStorageTexelBufferArrayNonUniformIndexing,
+    [v12] descriptorBindingUniformBufferUpdateAfterBind => DescriptorBindingUniformBufferUpdateAfterBind,
+    [v12] descriptorBindingSampledImageUpdateAfterBind => DescriptorBindingSampledImageUpdateAfterBind,
+    [v12] descriptorBindingStorageImageUpdateAfterBind => DescriptorBindingStorageImageUpdateAfterBind,
+    [v12] descriptorBindingStorageBufferUpdateAfterBind => DescriptorBindingStorageBufferUpdateAfterBind,
+    [v12] descriptorBindingUniformTexelBufferUpdateAfterBind => DescriptorBindingUniformTexelBufferUpdateAfterBind,
+    [v12] descriptorBindingStorageTexelBufferUpdateAfterBind => DescriptorBindingStorageTexelBufferUpdateAfterBind,
+    [v12] descriptorBindingUpdateUnusedWhilePending => DescriptorBindingUpdateUnusedWhilePending,
+    [v12] descriptorBindingPartiallyBound => DescriptorBindingPartiallyBound,
+    [v12] descriptorBindingVariableDescriptorCount => DescriptorBindingVariableDescriptorCount,
+    [v12] runtimeDescriptorArray => RuntimeDescriptorArray,
+    [v12] samplerFilterMinmax => SamplerFilterMinmax,
+    [v12] scalarBlockLayout => ScalarBlockLayout,
+    [v12] imagelessFramebuffer => ImagelessFramebuffer,
+    [v12] uniformBufferStandardLayout => UniformBufferStandardLayout,
+    [v12] shaderSubgroupExtendedTypes => ShaderSubgroupExtendedTypes,
+    [v12] separateDepthStencilLayouts => SeparateDepthStencilLayouts,
+    [v12] hostQueryReset => HostQueryReset,
+    [v12] timelineSemaphore => TimelineSemaphore,
+    [v12] bufferDeviceAddress => BufferDeviceAddress,
+    [v12] bufferDeviceAddressCaptureReplay => BufferDeviceAddressCaptureReplay,
+    [v12] bufferDeviceAddressMultiDevice => BufferDeviceAddressMultiDevice,
+    [v12] vulkanMemoryModel => VulkanMemoryModel,
+    [v12] vulkanMemoryModelDeviceScope => VulkanMemoryModelDeviceScope,
+    [v12] vulkanMemoryModelAvailabilityVisibilityChains => VulkanMemoryModelAvailabilityVisibilityChains,
+    [v12] shaderOutputViewportIndex => ShaderOutputViewportIndex,
+    [v12] shaderOutputLayer => ShaderOutputLayer,
+    [v12] subgroupBroadcastDynamicId => SubgroupBroadcastDynamicId,
+    // Vulkan 1.3
+    [v13] robustImageAccess => RobustImageAccess,
+    [v13] inlineUniformBlock => InlineUniformBlock,
+    [v13] descriptorBindingInlineUniformBlockUpdateAfterBind => DescriptorBindingInlineUniformBlockUpdateAfterBind,
+    [v13] pipelineCreationCacheControl => PipelineCreationCacheControl,
+    [v13] privateData => PrivateData,
+    [v13] shaderDemoteToHelperInvocation => ShaderDemoteToHelperInvocation,
+    [v13] shaderTerminateInvocation => ShaderTerminateInvocation,
+    [v13] subgroupSizeControl => SubgroupSizeControl,
+    [v13] computeFullSubgroups => ComputeFullSubgroups,
+    [v13] synchronization2 => Synchronization2,
+    [v13] textureCompressionASTC_HDR => TextureCompressionASTCHDR,
+    [v13] shaderZeroInitializeWorkgroupMemory => ShaderZeroInitializeWorkgroupMemory,
+    [v13] dynamicRendering => DynamicRendering,
+    [v13] shaderIntegerDotProduct => ShaderIntegerDotProduct,
+    [v13] maintenance4 => Maintenance4
+}
@@ -28,9 +28,11 @@ pub(crate) extern crate vulkan_bindings as vk;
 
 pub mod error;
 pub mod physical_dev;
+pub mod features;
 pub mod device;
 pub mod queue;
 pub mod shader;
+pub mod library;
 pub mod buffer;
 pub mod alloc;
 pub mod utils;
@@ -38,6 +40,10 @@ pub mod pipeline;
 pub mod descriptor;
 pub mod pool;
 pub mod sync;
+pub mod barrier;
+pub mod query;
+pub mod debug;
+pub mod staging;
 
 //flat_mod! { alloc }
 
@@ -73,18 +79,25 @@ proc::entry! {
     "vkCreateDevice",
     "vkGetPhysicalDeviceQueueFamilyProperties",
     "vkGetPhysicalDeviceFeatures",
+    "vkGetPhysicalDeviceFeatures2",
+    "vkEnumerateDeviceExtensionProperties",
     "vkGetDeviceQueue",
     "vkCreateShaderModule",
     "vkCreateBuffer",
     "vkGetBufferMemoryRequirements",
+    "vkGetBufferMemoryRequirements2",
     "vkGetPhysicalDeviceMemoryProperties",
+    "vkGetPhysicalDeviceMemoryProperties2",
     "vkAllocateMemory",
     "vkMapMemory",
     "vkUnmapMemory",
+    "vkFlushMappedMemoryRanges",
+    "vkInvalidateMappedMemoryRanges",
     "vkBindBufferMemory",
     "vkCreateDescriptorSetLayout",
     "vkCreatePipelineLayout",
     "vkCreatePipelineCache",
+    "vkGetPipelineCacheData",
     "vkCreateComputePipelines",
     "vkCreateDescriptorPool",
     "vkAllocateDescriptorSets",
@@ -96,10 +109,19 @@ proc::entry! {
     "vkCmdBindPipeline",
     "vkCmdBindDescriptorSets",
     "vkCmdDispatch",
+    "vkCmdDispatchIndirect",
+    "vkCmdDispatchBase",
+    "vkCmdCopyBuffer",
+    "vkCmdFillBuffer",
+    "vkCmdUpdateBuffer",
+    "vkCmdPipelineBarrier",
     "vkCreateFence",
     "vkCreateSemaphore",
     "vkQueueSubmit",
     "vkWaitForFences",
+    "vkGetSemaphoreCounterValue",
+    "vkWaitSemaphores",
+    "vkSignalSemaphore",
     "vkEnumerateInstanceExtensionProperties",
     "vkCmdPushConstants",
     "vkCreateEvent",
@@ -109,9 +131,19 @@ proc::entry! {
     "vkDestroyEvent",
     "vkCmdSetEvent",
     "vkCmdResetEvent",
+    "vkCmdWaitEvents",
+    "vkCreateQueryPool",
+    "vkGetQueryPoolResults",
+    "vkCmdResetQueryPool",
+    "vkCmdBeginQuery",
+    "vkCmdEndQuery",
+    "vkCmdWriteTimestamp",
     "vkResetFences",
     "vkGetFenceStatus",
     "vkQueueBindSparse",
+    "vkResetCommandPool",
+    "vkResetCommandBuffer",
+    "vkCmdExecuteCommands",
     // Destructors
     "vkDestroyInstance",
     "vkDestroyDevice",
@@ -125,6 +157,7 @@ proc::entry! {
     "vkDestroyCommandPool",
     "vkDestroyFence",
     "vkDestroySemaphore",
+    "vkDestroyQueryPool",
     "vkFreeDescriptorSets",
     "vkFreeMemory",
     "vkFreeCommandBuffers",
@@ -136,6 +169,13 @@ impl Entry {
         return Builder::new(api_major, api_minor, api_patch);
     }
 
+    /// Highest Vulkan version the instance was created for.
+    /// Devices can only be used up to the lowest of this version & their own.
+    #[inline]
+    pub fn api_version (&self) -> (u32, u32, u32) {
+        return get_version(self.api_version)
+    }
+
     #[inline]
     pub fn get () -> &'static Self {
         unsafe {
@@ -157,6 +197,7 @@ impl Debug for Entry {
 impl Drop for Entry {
     #[inline]
     fn drop(&mut self) {
+        debug::destroy(self);
         (self.destroy_instance)(self.instance.get(), core::ptr::null_mut())
     }
 }
@@ -164,6 +205,9 @@ impl Drop for Entry {
 pub struct Builder<'a> {
     app: vk::ApplicationInfo,
     instance: vk::InstanceCreateInfo,
+    debug: bool,
+    collect_errors: bool,
+    printf: bool,
     _phtm: PhantomData<&'a CStr>
 }
 
@@ -192,6 +236,9 @@ impl<'a> Builder<'a> {
                 ppEnabledExtensionNames: core::ptr::null_mut(),
             },
 
+            debug: false,
+            collect_errors: false,
+            printf: false,
             _phtm: PhantomData
         }
     }
@@ -234,6 +281,31 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Enables the validation layer (if present), and routes its messages into the `log` crate through `VK_EXT_debug_utils`
+    #[inline]
+    pub fn debug (mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    /// Enables debug mode, also collecting validation errors to be returned by [`debug::check`].
+    /// Errors are only collected in debug builds, and are never checked implicitly.
+    #[inline]
+    pub fn validation_errors (mut self) -> Self {
+        self.collect_errors = true;
+        self.debug()
+    }
+
+    /// Enables debug mode, also enabling the validation layer's debug printf.
+    /// Messages printed by shaders are logged under the `vulkan::printf` target, and can be taken with [`debug::take_printf`].
+    ///
+    /// Shaders using `debug_printf` require the `VK_KHR_shader_non_semantic_info` device extension.
+    #[inline]
+    pub fn debug_printf (mut self) -> Self {
+        self.printf = true;
+        self.debug()
+    }
+
     #[inline]
     pub unsafe fn build (self) -> Result<&'static Entry> {
         self.build_in(LIB_PATH)
@@ -249,6 +321,49 @@ impl<'a> Builder<'a> {
         let lib = Library::new(path)?;
         let get_instance_proc_addr = lib.get::<vk::FnGetInstanceProcAddr>(ENTRY_POINT)?.into_raw();
 
+        let mut layers = match info.ppEnabledLayerNames.is_null() {
+            true => Vec::new(),
+            false => core::slice::from_raw_parts(info.ppEnabledLayerNames, info.enabledLayerCount as usize).to_vec()
+        };
+
+        let mut extensions = match info.ppEnabledExtensionNames.is_null() {
+            true => Vec::new(),
+            false => core::slice::from_raw_parts(info.ppEnabledExtensionNames, info.enabledExtensionCount as usize).to_vec()
+        };
+        let messenger_info = debug::messenger_info();
+        let printf_features = debug::printf_features(addr_of!(messenger_info).cast());
+        let mut debug_utils = false;
+        let mut printf = false;
+
+        if self.debug {
+            let layer = match debug::has_layer(*get_instance_proc_addr, debug::VALIDATION_LAYER) {
+                true => {
+                    layers.push(debug::VALIDATION_LAYER.as_ptr());
+                    Some(debug::VALIDATION_LAYER)
+                },
+                false => None
+            };
+
+            if debug::has_extension(*get_instance_proc_addr, None, debug::DEBUG_UTILS) || layer.is_some_and(|x| debug::has_extension(*get_instance_proc_addr, Some(x), debug::DEBUG_UTILS)) {
+                extensions.push(debug::DEBUG_UTILS.as_ptr());
+                info.pNext = addr_of!(messenger_info).cast();
+                debug_utils = true;
+            }
+
+            if let Some(layer) = layer.filter(|_| self.printf && debug_utils) {
+                if debug::has_extension(*get_instance_proc_addr, Some(layer), debug::VALIDATION_FEATURES) {
+                    extensions.push(debug::VALIDATION_FEATURES.as_ptr());
+                    info.pNext = addr_of!(printf_features).cast();
+                    printf = true;
+                }
+            }
+        }
+
+        info.enabledLayerCount = usize_to_u32(layers.len());
+        info.ppEnabledLayerNames = layers.as_ptr();
+        info.enabledExtensionCount = usize_to_u32(extensions.len());
+        info.ppEnabledExtensionNames = extensions.as_ptr();
+
         let create_instance: vk::FnCreateInstance = transmute(get_instance_proc_addr(NULL_INSTANCE, CREATE_INSTANCE.as_ptr()));
         let mut instance: vk::Instance = 0;
         tri! {
@@ -256,7 +371,11 @@ impl<'a> Builder<'a> {
         }
 
         if let Some(instance) = NonZeroU64::new(instance) {
-            CURRENT_ENTRY = Some(Entry::new(instance, lib, create_instance, get_instance_proc_addr));
+            if debug_utils {
+                debug::init(*get_instance_proc_addr, instance.get(), self.collect_errors, printf)?;
+            }
+
+            CURRENT_ENTRY = Some(Entry::new(instance, self.app.apiVersion, lib, create_instance, get_instance_proc_addr));
             return Ok(Entry::get())
         }
 
@@ -0,0 +1,218 @@
+use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, sync::{Arc, Mutex}, ffi::CStr};
+use crate::{Result, Entry, device::{Device, DeviceRef}, utils::usize_to_u32, pipeline::{ComputeBuilder, Pipeline, PipelineCache, PipelineLayout}, shader::ShaderStages, descriptor::DescriptorSets};
+
+/// A single shader module, shared by every pipeline created from its entry points.
+/// Pipelines with compatible bindings & push constants also share their layouts.
+pub struct ShaderLibrary<D: DeviceRef> {
+    module: NonZeroU64,
+    layouts: Mutex<Vec<Arc<PipelineLayout<D>>>>,
+    device: D
+}
+
+impl<D: Clone + DeviceRef> ShaderLibrary<D> {
+    pub fn new (device: D, words: &[u32]) -> Result<Self> {
+        let info = vk::ShaderModuleCreateInfo {
+            sType: vk::STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO,
+            pNext: core::ptr::null_mut(),
+            flags: 0,
+            codeSize: words.len() * core::mem::size_of::<u32>(),
+            pCode: words.as_ptr().cast(),
+        };
+
+        let mut module = 0;
+        tri! {
+            (Entry::get().create_shader_module)(device.id(), addr_of!(info), core::ptr::null(), addr_of_mut!(module))
+        }
+
+        if let Some(module) = NonZeroU64::new(module) {
+            return Ok(Self { module, layouts: Mutex::new(Vec::new()), device })
+        }
+        return Err(vk::ERROR_INITIALIZATION_FAILED.into())
+    }
+
+    #[inline]
+    pub fn module (&self) -> u64 {
+        return self.module.get()
+    }
+
+    #[inline]
+    pub fn device (&self) -> &Device {
+        return &self.device
+    }
+
+    #[inline]
+    pub fn owned_device (&self) -> D {
+        return self.device.clone()
+    }
+
+    /// Gives the shader module a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_SHADER_MODULE, self.module(), name)
+    }
+
+    /// Starts building a compute pipeline for the specified entry point
+    #[inline]
+    pub fn compute<'a> (&self, entry: &'a CStr) -> ComputeBuilder<'a, D> {
+        return ComputeBuilder::new(self.device.clone()).entry(entry)
+    }
+
+    #[inline]
+    pub fn build (&self, builder: ComputeBuilder<'_, D>) -> Result<Pipeline<D>> {
+        let [pipeline] = self.build_array([builder])?;
+        return Ok(pipeline)
+    }
+
+    #[inline]
+    pub fn build_array<const N: usize> (&self, builders: [ComputeBuilder<'_, D>; N]) -> Result<[Pipeline<D>; N]> {
+        return match <[Pipeline<D>; N]>::try_from(self.build_all(builders)?) {
+            Ok(x) => Ok(x),
+            Err(_) => unreachable!()
+        }
+    }
+
+    /// Builds all the pipelines, batching the ones that share the same cache into a single `vkCreateComputePipelines` call,
+    /// wherever they are in `builders`
+    pub fn build_all<'a, I: IntoIterator<Item = ComputeBuilder<'a, D>>> (&self, builders: I) -> Result<Vec<Pipeline<D>>> {
+        let entry = Entry::get();
+        let builders = builders.into_iter().collect::<Vec<_>>();
+        debug_assert!(builders.iter().all(|x| x.device.id() == self.device.id()));
+
+        let layouts = builders.iter()
+            .map(|x| self.layout_for(x))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Builders with cache flags but no shared cache get a cache of their own, which lives until the pipelines are built
+        let mut own_caches = Vec::new();
+        let mut caches = Vec::with_capacity(builders.len());
+        for builder in builders.iter() {
+            match builder.cache_flags {
+                Some(flags) if builder.cache == vk::NULL_HANDLE => {
+                    let cache = PipelineCache::new(self.device.clone(), flags)?;
+                    caches.push(cache.id());
+                    own_caches.push(cache);
+                },
+                _ => caches.push(builder.cache)
+            }
+        }
+
+        let specializations = builders.iter()
+            .map(|x| x.specialization.info())
+            .collect::<Vec<_>>();
+
+        // Builders sharing a cache are grouped together (keeping their relative order), so each cache takes a single call
+        let mut order = (0..builders.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| caches.iter().position(|x| *x == caches[*i]));
+
+        let infos = order.iter()
+            .map(|i| (&builders[*i], &layouts[*i], &specializations[*i]))
+            .map(|(builder, layout, specialization)| vk::ComputePipelineCreateInfo {
+                sType: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
+                pNext: core::ptr::null(),
+                flags: builder.pipe_flags.bits(),
+                stage: vk::PipelineShaderStageCreateInfo {
+                    sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
+                    pNext: core::ptr::null(),
+                    flags: 0,
+                    stage: ShaderStages::COMPUTE.bits(),
+                    module: self.module(),
+                    pName: builder.entry.as_ptr(),
+                    pSpecializationInfo: specialization.as_ref().map_or(core::ptr::null(), |x| x as *const _),
+                },
+                layout: layout.id(),
+                basePipelineHandle: vk::NULL_HANDLE,
+                basePipelineIndex: 0,
+            })
+            .collect::<Vec<_>>();
+
+        // Create pipelines, one call per cache (in the grouped order)
+        let mut grouped = vec![vk::NULL_HANDLE; infos.len()];
+        let mut start = 0;
+        while start < infos.len() {
+            let cache = caches[order[start]];
+            let len = order[start..].iter().take_while(|i| caches[**i] == cache).count();
+
+            match (entry.create_compute_pipelines)(
+                self.device.id(),
+                cache,
+                usize_to_u32(len),
+                infos[start..].as_ptr(),
+                core::ptr::null(),
+                grouped[start..].as_mut_ptr()
+            ) {
+                vk::SUCCESS => {},
+                e => {
+                    self.destroy_pipelines(&grouped);
+                    return Err(e.into())
+                }
+            }
+
+            start += len;
+        }
+
+        // Back to the order of the builders
+        let mut handles = vec![vk::NULL_HANDLE; grouped.len()];
+        for (i, handle) in order.into_iter().zip(grouped) {
+            handles[i] = handle;
+        }
+
+        // Create descriptor sets
+        let mut pipelines = Vec::with_capacity(handles.len());
+        for (i, (mut builder, layout)) in builders.into_iter().zip(layouts).enumerate() {
+            let sets = match NonZeroU64::new(handles[i]) {
+                Some(_) => builder.build_descriptor_pool()
+                    .and_then(|pool| DescriptorSets::from_layouts(pool, &[layout.set_layout()])),
+                None => Err(vk::ERROR_UNKNOWN.into())
+            };
+
+            match sets {
+                Ok(sets) => {
+                    crate::debug::register_pipeline(handles[i], builder.entry);
+                    pipelines.push(Pipeline {
+                        inner: unsafe { NonZeroU64::new_unchecked(handles[i]) },
+                        layout,
+                        sets,
+                        workgroup_size: builder.workgroup_size.unwrap_or([1, 1, 1])
+                    })
+                },
+                Err(e) => {
+                    // Pipelines already in `pipelines` are destroyed when it's dropped
+                    self.destroy_pipelines(&handles[i..]);
+                    return Err(e)
+                }
+            }
+        }
+
+        return Ok(pipelines)
+    }
+
+    fn layout_for (&self, builder: &ComputeBuilder<'_, D>) -> Result<Arc<PipelineLayout<D>>> {
+        let key = builder.layout_key();
+        let mut layouts = match self.layouts.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner()
+        };
+
+        if let Some(layout) = layouts.iter().find(|x| *x.key() == key) {
+            return Ok(layout.clone())
+        }
+
+        let layout = Arc::new(PipelineLayout::new(self.device.clone(), builder)?);
+        layouts.push(layout.clone());
+        return Ok(layout)
+    }
+
+    #[inline]
+    fn destroy_pipelines (&self, handles: &[vk::Pipeline]) {
+        for handle in handles.iter().filter(|x| **x != vk::NULL_HANDLE) {
+            (Entry::get().destroy_pipeline)(self.device.id(), *handle, core::ptr::null());
+        }
+    }
+}
+
+impl<D: DeviceRef> Drop for ShaderLibrary<D> {
+    #[inline]
+    fn drop(&mut self) {
+        (Entry::get().destroy_shader_module)(self.device.id(), self.module.get(), core::ptr::null())
+    }
+}
@@ -1,5 +1,30 @@
-use std::{num::NonZeroU64, ptr::addr_of_mut, ffi::{CStr, c_void}, fmt::Debug, mem::MaybeUninit, hash::Hash, marker::PhantomPinned, pin::Pin, sync::Arc};
-use crate::{vk, Entry, Result};
+use std::{num::NonZeroU64, ptr::addr_of_mut, ffi::CStr, fmt::Debug, mem::MaybeUninit, hash::Hash, marker::PhantomPinned, pin::Pin, sync::Arc};
+use crate::{vk, Entry, Result, ExtensionProperty, features::Features, alloc::MemoryFlags};
+
+mod select;
+pub use select::*;
+
+pub(crate) const MEMORY_BUDGET: &CStr = unsafe { proc::cstr!("VK_EXT_memory_budget") };
+
+/// Memory budget of a memory heap, as reported by [`PhysicalDevice::memory_budget`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HeapBudget {
+    /// Total size of the heap, in bytes
+    pub size: u64,
+    /// Bytes the process can allocate from the heap before allocations may fail or degrade performance
+    pub budget: u64,
+    /// Bytes currently allocated from the heap by the process
+    pub usage: u64,
+    pub device_local: bool
+}
+
+impl HeapBudget {
+    /// Bytes that can still be allocated within the budget
+    #[inline]
+    pub fn available (&self) -> u64 {
+        return self.budget.saturating_sub(self.usage)
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
@@ -8,6 +33,12 @@ pub struct PhysicalDevice {
 }
 
 impl PhysicalDevice {
+    /// Starts selecting the best physical device matching a set of requirements
+    #[inline]
+    pub fn select<'a> () -> Selector<'a> {
+        return Selector::new()
+    }
+
     #[inline]
     pub fn first () -> Result<PhysicalDevice> {
         Self::first_from_entry(Entry::get())
@@ -63,32 +94,144 @@ impl PhysicalDevice {
     pub fn properties (self) -> Pin<Arc<Properties>> {
         let mut props_arc = Arc::<Properties>::new_uninit();
         unsafe {
-            let props = Arc::get_mut_unchecked(&mut props_arc).as_mut_ptr().cast::<vk::PhysicalDeviceProperties2>();
-            let maintainence = props.add(1).cast::<vk::PhysicalDeviceMaintenance3Properties>();
-
-            // Set `maintainence` as next property instyance
-            props.byte_add(core::mem::size_of::<vk::StructureType>())
-                .cast::<*mut c_void>()
-                .write(maintainence.cast());
+            let this = Arc::get_mut_unchecked(&mut props_arc).as_mut_ptr();
+            let props = addr_of_mut!((*this).props);
+            let maintainence = addr_of_mut!((*this).maintainence);
+            let id = addr_of_mut!((*this).id);
+
+            // Initialize identifiers
+            id.write(vk::PhysicalDeviceIDProperties {
+                sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_ID_PROPERTIES,
+                pNext: core::ptr::null_mut(),
+                deviceUUID: [0; vk::UUID_SIZE as usize],
+                driverUUID: [0; vk::UUID_SIZE as usize],
+                deviceLUID: [0; vk::LUID_SIZE as usize],
+                deviceNodeMask: 0,
+                deviceLUIDValid: vk::FALSE,
+            });
 
             // Initialize maintainence
             maintainence.write(vk::PhysicalDeviceMaintenance3Properties {
                 sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MAINTENANCE_3_PROPERTIES,
-                pNext: core::ptr::null_mut(),
+                pNext: id.cast(),
                 maxPerSetDescriptors: 0,
                 maxMemoryAllocationSize: 0,
             });
+
+            // Set `maintainence` as next property instance
+            addr_of_mut!((*props).sType).write(vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2);
+            addr_of_mut!((*props).pNext).write(maintainence.cast());
     
             (Entry::get().get_physical_device_properties2)(self.inner.get(), props);
             return Pin::new_unchecked(props_arc.assume_init())
         }
     }
 
-    #[inline]
+    /// Features supported by the device, up to the Vulkan version both the device & the instance support
     pub fn features (self) -> Box<Features> {
-        let mut features = Box::<Features>::new_uninit();
-        (Entry::get().get_physical_device_features)(self.inner.get(), features.as_mut_ptr().cast());
-        return unsafe { features.assume_init() }
+        let (major, minor, _) = self.properties().api_version().min(Entry::get().api_version());
+        let mut features = Box::new(Features::new(major, minor));
+        (Entry::get().get_physical_device_features2)(self.inner.get(), features.chain(core::ptr::null_mut()));
+        features.unchain();
+        return features
+    }
+
+    /// Total size of the device's device-local memory heaps, in bytes
+    pub fn device_local_memory (self) -> u64 {
+        let mut props = MaybeUninit::uninit();
+        (Entry::get().get_physical_device_memory_properties)(self.inner.get(), props.as_mut_ptr());
+        let props = unsafe { props.assume_init() };
+
+        return props.memoryHeaps[..props.memoryHeapCount as usize].iter()
+            .filter(|x| x.flags & vk::MEMORY_HEAP_DEVICE_LOCAL_BIT != 0)
+            .map(|x| x.size)
+            .sum()
+    }
+
+    /// Property flags of each of the device's memory types, by memory type index
+    pub fn memory_types (self) -> Vec<MemoryFlags> {
+        let mut props = MaybeUninit::uninit();
+        (Entry::get().get_physical_device_memory_properties)(self.inner.get(), props.as_mut_ptr());
+        let props = unsafe { props.assume_init() };
+
+        return props.memoryTypes[..props.memoryTypeCount as usize].iter()
+            .map(|x| MemoryFlags::from_bits_truncate(x.propertyFlags))
+            .collect()
+    }
+
+    /// Heap index of each of the device's memory types, by memory type index
+    pub fn memory_type_heaps (self) -> Vec<u32> {
+        let mut props = MaybeUninit::uninit();
+        (Entry::get().get_physical_device_memory_properties)(self.inner.get(), props.as_mut_ptr());
+        let props = unsafe { props.assume_init() };
+
+        return props.memoryTypes[..props.memoryTypeCount as usize].iter()
+            .map(|x| x.heapIndex)
+            .collect()
+    }
+
+    /// Current budget of each of the device's memory heaps, by heap index.
+    ///
+    /// Budgets & usages come from `VK_EXT_memory_budget` if the device supports it.
+    /// Otherwise, each heap's budget is its size, and its usage is unknown (zero).
+    pub fn memory_budget (self) -> Result<Vec<HeapBudget>> {
+        let supported = self.extensions()?.iter().any(|x| x.name() == MEMORY_BUDGET);
+        return Ok(self.heap_budgets(supported))
+    }
+
+    /// Current budget of each of the device's memory heaps, querying `VK_EXT_memory_budget` only if `supported` is set
+    pub(crate) fn heap_budgets (self, supported: bool) -> Vec<HeapBudget> {
+        let mut budget = MaybeUninit::<vk::PhysicalDeviceMemoryBudgetPropertiesEXT>::uninit();
+        let mut props = MaybeUninit::<vk::PhysicalDeviceMemoryProperties2>::uninit();
+        unsafe {
+            let ptr = budget.as_mut_ptr();
+            addr_of_mut!((*ptr).sType).write(vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES_EXT);
+            addr_of_mut!((*ptr).pNext).write(core::ptr::null_mut());
+
+            let ptr = props.as_mut_ptr();
+            addr_of_mut!((*ptr).sType).write(vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_MEMORY_PROPERTIES_2);
+            addr_of_mut!((*ptr).pNext).write(match supported {
+                true => budget.as_mut_ptr().cast(),
+                false => core::ptr::null_mut()
+            });
+        }
+
+        (Entry::get().get_physical_device_memory_properties2)(self.inner.get(), props.as_mut_ptr());
+        let props = unsafe { props.assume_init() }.memoryProperties;
+        let budget = match supported {
+            true => Some(unsafe { budget.assume_init() }),
+            false => None
+        };
+
+        let heaps = props.memoryHeaps[..props.memoryHeapCount as usize].iter()
+            .enumerate()
+            .map(|(i, heap)| HeapBudget {
+                size: heap.size,
+                budget: budget.as_ref().map_or(heap.size, |x| x.heapBudget[i]),
+                usage: budget.as_ref().map_or(0, |x| x.heapUsage[i]),
+                device_local: heap.flags & vk::MEMORY_HEAP_DEVICE_LOCAL_BIT != 0
+            })
+            .collect();
+
+        return heaps
+    }
+
+    /// Device-level extensions supported by the device
+    pub fn extensions (self) -> Result<Vec<ExtensionProperty>> {
+        let entry = Entry::get();
+
+        let mut len = 0;
+        tri! {
+            (entry.enumerate_device_extension_properties)(self.inner.get(), core::ptr::null(), addr_of_mut!(len), core::ptr::null_mut())
+        }
+
+        let mut result = Vec::<ExtensionProperty>::with_capacity(len as usize);
+        tri! {
+            (entry.enumerate_device_extension_properties)(self.inner.get(), core::ptr::null(), addr_of_mut!(len), result.as_mut_ptr().cast())
+        }
+        unsafe { result.set_len(len as usize) };
+
+        return Ok(result)
     }
 
     pub fn families (self) -> impl Iterator<Item = Family> {
@@ -105,6 +248,24 @@ impl PhysicalDevice {
                 Family { idx: idx as u32, inner, parent: self }
             )
     }
+
+    /// Best family for compute work, preferring dedicated (non-graphics) compute families
+    pub fn best_compute_family (self) -> Option<Family> {
+        return self.families()
+            .filter(|x| x.queue_flags().contains(FamilyQueueFlags::COMPUTE))
+            .min_by_key(|x| x.queue_flags().contains(FamilyQueueFlags::GRAPHICS))
+    }
+
+    /// Best family for transfers, preferring dedicated transfer families, then non-graphics ones.
+    /// Graphics & compute families are considered too, since they implicitly support transfers.
+    pub fn best_transfer_family (self) -> Option<Family> {
+        return self.families()
+            .filter(|x| x.queue_flags().intersects(FamilyQueueFlags::TRANSFER | FamilyQueueFlags::COMPUTE | FamilyQueueFlags::GRAPHICS))
+            .min_by_key(|x| {
+                let flags = x.queue_flags();
+                (flags.contains(FamilyQueueFlags::GRAPHICS), flags.contains(FamilyQueueFlags::COMPUTE), !flags.contains(FamilyQueueFlags::TRANSFER))
+            })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -127,6 +288,7 @@ pub enum Type {
 pub struct Properties {
     props: vk::PhysicalDeviceProperties2,
     maintainence: vk::PhysicalDeviceMaintenance3Properties,
+    id: vk::PhysicalDeviceIDProperties,
     _pin: PhantomPinned
 }
 
@@ -141,6 +303,11 @@ impl Properties {
         vk::get_version(self.props.properties.driverVersion)
     }
 
+    #[inline]
+    pub(crate) fn raw_driver_version (&self) -> u32 {
+        self.props.properties.driverVersion
+    }
+
     #[inline]
     pub fn vendor_id (&self) -> u32 {
         self.props.properties.vendorID
@@ -151,6 +318,24 @@ impl Properties {
         self.props.properties.deviceID
     }
 
+    /// Universally unique identifier of the device
+    #[inline]
+    pub fn device_uuid (&self) -> [u8; vk::UUID_SIZE as usize] {
+        self.id.deviceUUID
+    }
+
+    /// Universally unique identifier of the driver build in use by the device
+    #[inline]
+    pub fn driver_uuid (&self) -> [u8; vk::UUID_SIZE as usize] {
+        self.id.driverUUID
+    }
+
+    /// Universally unique identifier for the device's pipeline cache data
+    #[inline]
+    pub fn pipeline_cache_uuid (&self) -> [u8; vk::UUID_SIZE as usize] {
+        self.props.properties.pipelineCacheUUID
+    }
+
     #[inline]
     pub fn name (&self) -> &'_ CStr {
         return unsafe { CStr::from_ptr(self.props.properties.deviceName.as_ptr()) }
@@ -203,19 +388,6 @@ impl Debug for Properties {
     }
 }
 
-#[derive(Clone)]
-#[repr(transparent)]
-pub struct Features {
-    inner: vk::PhysicalDeviceFeatures
-}
-
-impl Features {
-    #[inline]
-    pub fn into_raw (self) -> vk::PhysicalDeviceFeatures {
-        return self.inner
-    }
-}
-
 #[derive(Clone, Copy)]
 pub struct Family {
     idx: u32,
@@ -230,6 +402,16 @@ impl PartialEq for Family {
     }
 }
 
+impl Hash for Family {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.idx.hash(state);
+        self.parent.hash(state);
+    }
+}
+
+impl Eq for Family {}
+
 impl Family {
     #[inline]
     pub fn parent (self) -> PhysicalDevice {
@@ -241,6 +423,24 @@ impl Family {
         return self.idx
     }
 
+    /// Number of queues available in the family
+    #[inline]
+    pub fn queue_count (&self) -> u32 {
+        return self.inner.queueCount
+    }
+
+    /// Number of meaningful bits in the timestamps written by the family's queues, or zero if they don't support timestamps
+    #[inline]
+    pub fn timestamp_valid_bits (&self) -> u32 {
+        return self.inner.timestampValidBits
+    }
+
+    /// Whether the family's queues can write timestamps, which requires them to have valid timestamp bits
+    #[inline]
+    pub fn supports_timestamps (&self) -> bool {
+        return self.timestamp_valid_bits() > 0
+    }
+
     #[inline]
     pub fn queue_flags (&self) -> FamilyQueueFlags {
         #[cfg(debug_assertions)]
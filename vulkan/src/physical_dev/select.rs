@@ -0,0 +1,229 @@
+use std::{ffi::CStr, cmp::Ordering};
+use crate::{Result, error::Error, features::Feature};
+use super::{PhysicalDevice, Properties, Type, FamilyQueueFlags};
+
+/// Physical device chosen by the user, instead of the best ranked one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeviceOverride {
+    /// Device whose name contains the string (case insensitive)
+    Name (String),
+    /// Device at the specified index, in the order the loader lists them
+    Index (usize),
+    /// Device with the specified UUID
+    Uuid ([u8; vk::UUID_SIZE as usize])
+}
+
+impl DeviceOverride {
+    /// Parses an override, as either an index, a UUID (32 hex digits, optionally dash-separated) or a name
+    pub fn parse (s: &str) -> Self {
+        let s = s.trim();
+        if let Ok(idx) = s.parse::<usize>() {
+            return Self::Index(idx)
+        }
+
+        let hex = s.chars().filter(|x| *x != '-').collect::<String>();
+        if hex.len() == 2 * vk::UUID_SIZE as usize {
+            let mut uuid = [0; vk::UUID_SIZE as usize];
+            let parsed = uuid.iter_mut()
+                .enumerate()
+                .all(|(i, x)| match hex.get(2 * i..2 * i + 2).map(|x| u8::from_str_radix(x, 16)) {
+                    Some(Ok(v)) => { *x = v; true },
+                    _ => false
+                });
+
+            if parsed {
+                return Self::Uuid(uuid)
+            }
+        }
+
+        return Self::Name(s.to_string())
+    }
+
+    #[inline]
+    fn matches (&self, idx: usize, props: &Properties) -> bool {
+        return match self {
+            Self::Name(name) => props.name().to_string_lossy().to_lowercase().contains(&name.to_lowercase()),
+            Self::Index(i) => *i == idx,
+            Self::Uuid(uuid) => props.device_uuid() == *uuid
+        }
+    }
+}
+
+/// Filters the available physical devices by their capabilities, and picks the best ranked one.
+///
+/// Devices are ranked by their [`Type`] (discrete > integrated > virtual > cpu), then by their device-local memory and compute limits.
+pub struct Selector<'a> {
+    features: Vec<Feature>,
+    extensions: Vec<&'a CStr>,
+    min_memory: u64,
+    queue_flags: FamilyQueueFlags,
+    device_override: Option<DeviceOverride>
+}
+
+impl<'a> Selector<'a> {
+    #[inline]
+    pub fn new () -> Self {
+        return Self {
+            features: Vec::new(),
+            extensions: Vec::new(),
+            min_memory: 0,
+            queue_flags: FamilyQueueFlags::empty(),
+            device_override: None
+        }
+    }
+
+    /// Only accepts devices supporting every feature in `iter`
+    #[inline]
+    pub fn require_features<I: IntoIterator<Item = Feature>> (mut self, iter: I) -> Self {
+        self.features.extend(iter);
+        self
+    }
+
+    /// Only accepts devices supporting every extension in `iter`
+    #[inline]
+    pub fn require_extensions<I: IntoIterator<Item = &'a CStr>> (mut self, iter: I) -> Self {
+        self.extensions.extend(iter);
+        self
+    }
+
+    /// Only accepts devices with at least `bytes` of device-local memory
+    #[inline]
+    pub fn min_memory (mut self, bytes: u64) -> Self {
+        self.min_memory = bytes;
+        self
+    }
+
+    /// Only accepts devices with a queue family supporting every operation in `flags`
+    #[inline]
+    pub fn queue_flags (mut self, flags: FamilyQueueFlags) -> Self {
+        self.queue_flags |= flags;
+        self
+    }
+
+    /// Picks the device matching `device_override` instead of the best ranked one
+    #[inline]
+    pub fn with_override (mut self, device_override: DeviceOverride) -> Self {
+        self.device_override = Some(device_override);
+        self
+    }
+
+    /// Reads the override from the environment variable `var`, if it's set
+    #[inline]
+    pub fn env_override (self, var: &str) -> Self {
+        return match std::env::var(var) {
+            Ok(x) if !x.trim().is_empty() => self.with_override(DeviceOverride::parse(&x)),
+            _ => self
+        }
+    }
+
+    pub fn build (self) -> Result<PhysicalDevice> {
+        let devices = PhysicalDevice::get_all()?
+            .into_iter()
+            .map(|x| (x, x.properties(), x.device_local_memory()))
+            .collect::<Vec<_>>();
+
+        if let Some(device_override) = &self.device_override {
+            let (device, _, _) = devices.iter()
+                .enumerate()
+                .find(|(i, (_, props, _))| device_override.matches(*i, props))
+                .map(|(_, x)| x)
+                .ok_or_else(|| Error::NoSuitableDevice(format!("no device matches {device_override:?}")))?;
+
+            self.check(*device)?;
+            return Ok(*device)
+        }
+
+        let mut rejected = Vec::new();
+        let mut best = None::<(PhysicalDevice, &Properties, u64)>;
+
+        for (device, props, memory) in devices.iter() {
+            if let Err(e) = self.check(*device) {
+                rejected.push(format!("{}: {e}", props.name().to_string_lossy()));
+                continue
+            }
+
+            if best.map_or(true, |(_, best, best_memory)| rank((&**props, *memory), (best, best_memory)) == Ordering::Greater) {
+                best = Some((*device, &**props, *memory));
+            }
+        }
+
+        return match best {
+            Some((device, _, _)) => Ok(device),
+            None if rejected.is_empty() => Err(Error::NoSuitableDevice("no devices available".to_string())),
+            None => Err(Error::NoSuitableDevice(rejected.join("; ")))
+        }
+    }
+
+    /// Checks that `device` meets every requirement
+    fn check (&self, device: PhysicalDevice) -> Result<()> {
+        let features = device.features();
+        if let Some(feature) = self.features.iter().find(|x| !features.is_supported(**x)) {
+            let (major, minor) = features.version();
+            let (req_major, req_minor) = feature.version();
+            return Err(Error::NoSuitableDevice(format!("`{}` requires Vulkan {req_major}.{req_minor}, the device is used as Vulkan {major}.{minor}", feature.name())))
+        }
+
+        let missing = self.features.iter()
+            .filter(|x| !features.get(**x))
+            .map(|x| x.name())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(Error::MissingFeatures(missing))
+        }
+
+        if !self.extensions.is_empty() {
+            let available = device.extensions()?;
+            let missing = self.extensions.iter()
+                .filter(|ext| !available.iter().any(|x| x.name() == **ext))
+                .map(|x| x.to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                return Err(Error::MissingExtensions(missing))
+            }
+        }
+
+        let memory = device.device_local_memory();
+        if memory < self.min_memory {
+            return Err(Error::NoSuitableDevice(format!("{memory} bytes of device-local memory, {} required", self.min_memory)))
+        }
+
+        if !device.families().any(|x| x.queue_flags().contains(self.queue_flags)) {
+            return Err(Error::NoSuitableDevice(format!("no queue family supports {:?}", self.queue_flags)))
+        }
+
+        return Ok(())
+    }
+}
+
+impl Default for Selector<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compares two devices by their type, then by their device-local memory & limits
+fn rank ((lhs, lhs_memory): (&Properties, u64), (rhs, rhs_memory): (&Properties, u64)) -> Ordering {
+    #[inline]
+    fn type_rank (ty: Type) -> u8 {
+        return match ty {
+            Type::DiscreteGpu => 4,
+            Type::IntegratedGpu => 3,
+            Type::VirtualGpu => 2,
+            Type::Cpu => 1,
+            Type::Other => 0
+        }
+    }
+
+    #[inline]
+    fn limits (props: &Properties) -> (u32, u32, u32) {
+        let limits = props.limits();
+        return (limits.maxComputeWorkGroupInvocations, limits.maxComputeSharedMemorySize, limits.maxStorageBufferRange)
+    }
+
+    return type_rank(lhs.ty()).cmp(&type_rank(rhs.ty()))
+        .then_with(|| lhs_memory.cmp(&rhs_memory))
+        .then_with(|| limits(lhs).cmp(&limits(rhs)))
+}
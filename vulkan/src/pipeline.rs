@@ -1,18 +1,27 @@
-use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, ffi::CStr};
-use crate::{shader::{LayoutCreateFlags, ShaderStages, Shader}, Entry, Result, device::{Device, DeviceRef}, utils::usize_to_u32, descriptor::{DescriptorType, DescriptorPool, DescriptorPoolFlags, DescriptorSets}};
+use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, ffi::CStr, path::Path, io::ErrorKind, sync::Arc, fmt::Debug};
+use crate::{physical_dev::Properties, shader::{LayoutCreateFlags, ShaderStages}, library::ShaderLibrary, Entry, Result, device::{Device, DeviceRef}, utils::usize_to_u32, descriptor::{DescriptorType, DescriptorPool, DescriptorPoolFlags, DescriptorSets}};
 use proc::cstr;
 
 const DEFAULT_ENTRY: &CStr = cstr!("main");
+const CACHE_MAGIC: [u8; 4] = *b"SSPC";
+const CACHE_HEADER_SIZE: usize = CACHE_MAGIC.len() + vk::UUID_SIZE as usize + core::mem::size_of::<u32>();
+const VK_CACHE_HEADER_SIZE: usize = 16 + vk::UUID_SIZE as usize;
+/// Ids of the specialization constants the `WorkgroupSize` built-in of a shader module must be made of, for [`ComputeBuilder::workgroup_size`] to apply
+pub const WORKGROUP_SIZE_IDS: [u32; 3] = [1000, 1001, 1002];
 
 pub struct ComputeBuilder<'a, D> {
-    pipe_flags: PipelineFlags,
-    pipe_layout_flags: PipelineLayoutFlags,
-    cache_flags: Option<PipelineCacheFlags>,
-    layout_flags: LayoutCreateFlags,
-    bindings: Vec<vk::DescriptorSetLayoutBinding>,
-    pool_sizes: Vec<vk::DescriptorPoolSize>,
-    device: D,
-    entry: &'a CStr
+    pub(crate) pipe_flags: PipelineFlags,
+    pub(crate) pipe_layout_flags: PipelineLayoutFlags,
+    pub(crate) cache: vk::PipelineCache,
+    pub(crate) cache_flags: Option<PipelineCacheFlags>,
+    pub(crate) layout_flags: LayoutCreateFlags,
+    pub(crate) bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    pub(crate) pool_sizes: Vec<vk::DescriptorPoolSize>,
+    pub(crate) push_constant_size: u32,
+    pub(crate) specialization: Specialization,
+    pub(crate) workgroup_size: Option<[u32; 3]>,
+    pub(crate) device: D,
+    pub(crate) entry: &'a CStr
 }
 
 impl<'a, D: Clone + DeviceRef> ComputeBuilder<'a, D> {
@@ -22,9 +31,13 @@ impl<'a, D: Clone + DeviceRef> ComputeBuilder<'a, D> {
             pipe_flags: PipelineFlags::empty(),
             pipe_layout_flags: PipelineLayoutFlags::empty(),
             layout_flags: LayoutCreateFlags::empty(),
+            cache: vk::NULL_HANDLE,
             cache_flags: None,
             bindings: Vec::new(),
             pool_sizes: Vec::new(),
+            push_constant_size: 0,
+            specialization: Specialization::new(),
+            workgroup_size: None,
             entry: DEFAULT_ENTRY,
             device,
         }
@@ -61,6 +74,13 @@ impl<'a, D: Clone + DeviceRef> ComputeBuilder<'a, D> {
 
         self
     }
+
+    /// Reserves a push constant range big enough to hold a `T`
+    #[inline]
+    pub fn push_constant<T: Copy> (mut self) -> Self {
+        self.push_constant_size = usize_to_u32(core::mem::size_of::<T>());
+        self
+    }
     
     #[inline]
     pub fn flags (mut self, flags: PipelineFlags) -> Self {
@@ -74,142 +94,294 @@ impl<'a, D: Clone + DeviceRef> ComputeBuilder<'a, D> {
         self
     }
 
+    /// Sets the value of the specialization constant with the specified `id`
+    #[inline]
+    pub fn constant<T: SpecializationConstant> (mut self, id: u32, value: T) -> Self {
+        self.specialization = self.specialization.constant(id, value);
+        self
+    }
+
+    /// Replaces every specialization constant, including the workgroup size
+    #[inline]
+    pub fn specialization (mut self, specialization: Specialization) -> Self {
+        self.specialization = specialization;
+        self.workgroup_size = None;
+        self
+    }
+
+    /// Sets the workgroup size of the pipeline, overriding the one the shader was compiled with.
+    /// The shader module must declare its `WorkgroupSize` built-in with the specialization constants [`WORKGROUP_SIZE_IDS`].
+    #[inline]
+    pub fn workgroup_size (mut self, x: u32, y: u32, z: u32) -> Self {
+        for (id, size) in WORKGROUP_SIZE_IDS.into_iter().zip([x, y, z]) {
+            self.specialization = self.specialization.constant(id, size);
+        }
+        self.workgroup_size = Some([x, y, z]);
+        self
+    }
+
+    /// Builds the pipeline through a cache of its own, created with `flags`. Ignored if a shared [`cache`](ComputeBuilder::cache) is set.
     #[inline]
     pub fn cache_flags (mut self, flags: PipelineCacheFlags) -> Self {
         self.cache_flags = Some(flags);
         self
     }
 
-    pub fn build (mut self, words: &[u32]) -> Result<Pipeline<D>> {
-        let entry = Entry::get();
-        let shader = self.build_shader(words)?;
-
-        // Create pipeline cache
-        let mut cache = None;
-        if let Some(cache_flags) = self.cache_flags {
-            let cache_info = vk::PipelineCacheCreateInfo {
-                sType: vk::STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO,
-                pNext: core::ptr::null(),
-                flags: cache_flags.bits(),
-                initialDataSize: 0,
-                pInitialData: core::ptr::null(),
-            };
-            let mut my_cache = 0;
-            tri! {
-                (entry.create_pipeline_cache)(self.device.id(), addr_of!(cache_info), core::ptr::null(), addr_of_mut!(my_cache))
-            }
-            if let Some(my_cache) = NonZeroU64::new(my_cache) {
-                cache = Some(PipelineCache { inner: my_cache, device: self.device.clone() });
-            } else {
-                return Err(vk::ERROR_UNKNOWN.into())
+    /// Builds the pipeline through `cache`, reusing (and adding to) its compiled state
+    #[inline]
+    pub fn cache<C: DeviceRef> (mut self, cache: &'a PipelineCache<C>) -> Self {
+        self.cache = cache.id();
+        self
+    }
+
+    /// Builds the pipeline with its own shader module.
+    /// When creating various pipelines from the same SPIR-V, prefer a [`ShaderLibrary`].
+    #[inline]
+    pub fn build (self, words: &[u32]) -> Result<Pipeline<D>> {
+        let library = ShaderLibrary::new(self.device.clone(), words)?;
+        return library.build(self)
+    }
+
+    pub(crate) fn build_descriptor_pool (&mut self) -> Result<DescriptorPool<D>> {
+        let builder = crate::descriptor::Builder {
+            flags: DescriptorPoolFlags::empty(),
+            capacity: 1,
+            pool_sizes: core::mem::take(&mut self.pool_sizes),
+            device: self.device.clone(),
+        };
+
+        return builder.build()
+    }
+}
+
+impl<D> ComputeBuilder<'_, D> {
+    #[inline]
+    pub(crate) fn layout_key (&self) -> LayoutKey {
+        return LayoutKey {
+            flags: self.layout_flags.bits(),
+            pipe_flags: self.pipe_layout_flags.bits(),
+            bindings: self.bindings.iter()
+                .map(|x| (x.binding, x.descriptorType, x.descriptorCount, x.stageFlags))
+                .collect(),
+            push_constant_size: self.push_constant_size,
+        }
+    }
+}
+
+/// Values for the specialization constants of a shader, indexed by their constant id
+#[derive(Clone, Default)]
+pub struct Specialization {
+    entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>
+}
+
+impl Debug for Specialization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self.entries.iter()
+            .map(|x| (x.constantID, &self.data[x.offset as usize..x.offset as usize + x.size]));
+        f.debug_map().entries(values).finish()
+    }
+}
+
+impl Specialization {
+    #[inline]
+    pub const fn new () -> Self {
+        return Self { entries: Vec::new(), data: Vec::new() }
+    }
+
+    /// Sets the value of the specialization constant with the specified `id`, replacing any previous value
+    pub fn constant<T: SpecializationConstant> (mut self, id: u32, value: T) -> Self {
+        let bytes = value.to_bytes();
+        let bytes = bytes.as_ref();
+
+        if let Some(entry) = self.entries.iter_mut().find(|x| x.constantID == id) {
+            if entry.size == bytes.len() {
+                let offset = entry.offset as usize;
+                self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+                return self
             }
+
+            // Value changed size, so append its new value (the old bytes are simply left unused)
+            entry.offset = usize_to_u32(self.data.len());
+            entry.size = bytes.len();
+        } else {
+            self.entries.push(vk::SpecializationMapEntry {
+                constantID: id,
+                offset: usize_to_u32(self.data.len()),
+                size: bytes.len(),
+            });
         }
 
-        // Create pipeline layout
-        let mut my_layout = 0;
-        let layout_info = vk::PipelineLayoutCreateInfo {
-            sType: vk::STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO,
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    #[inline]
+    pub fn is_empty (&self) -> bool {
+        return self.entries.is_empty()
+    }
+
+    /// The returned value borrows `self`'s buffers, so it mustn't outlive it
+    #[inline]
+    pub(crate) fn info (&self) -> Option<vk::SpecializationInfo> {
+        if self.is_empty() {
+            return None
+        }
+
+        return Some(vk::SpecializationInfo {
+            mapEntryCount: usize_to_u32(self.entries.len()),
+            pMapEntries: self.entries.as_ptr(),
+            dataSize: self.data.len(),
+            pData: self.data.as_ptr().cast(),
+        })
+    }
+}
+
+/// A type that can be used as the value of a specialization constant
+pub trait SpecializationConstant: Copy {
+    type Bytes: AsRef<[u8]>;
+    fn to_bytes (self) -> Self::Bytes;
+}
+
+macro_rules! impl_spec_constant {
+    ($($t:ty),+) => {
+        $(
+            impl SpecializationConstant for $t {
+                type Bytes = [u8; core::mem::size_of::<$t>()];
+
+                #[inline]
+                fn to_bytes (self) -> Self::Bytes {
+                    self.to_ne_bytes()
+                }
+            }
+        )+
+    };
+}
+
+impl_spec_constant! {
+    u8, u16, u32, u64,
+    i8, i16, i32, i64,
+    f32, f64
+}
+
+impl SpecializationConstant for bool {
+    type Bytes = [u8; core::mem::size_of::<vk::Bool32>()];
+
+    /// Booleans are represented as a `VkBool32`
+    #[inline]
+    fn to_bytes (self) -> Self::Bytes {
+        let value = if self { vk::TRUE as vk::Bool32 } else { vk::FALSE as vk::Bool32 };
+        value.to_ne_bytes()
+    }
+}
+
+/// Key used to find compatible pipeline layouts
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LayoutKey {
+    flags: vk::DescriptorSetLayoutCreateFlags,
+    pipe_flags: vk::PipelineLayoutCreateFlags,
+    bindings: Vec<(u32, vk::DescriptorType, u32, vk::ShaderStageFlags)>,
+    push_constant_size: u32
+}
+
+/// Descriptor set & pipeline layouts, which may be shared between compatible pipelines
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct PipelineLayout<D: DeviceRef> {
+    inner: NonZeroU64,
+    set_layout: NonZeroU64,
+    key: LayoutKey,
+    device: D
+}
+
+impl<D: DeviceRef> PipelineLayout<D> {
+    pub(crate) fn new<B> (device: D, builder: &ComputeBuilder<'_, B>) -> Result<Self> {
+        let entry = Entry::get();
+
+        // Create descriptor set layout
+        let set_info = vk::DescriptorSetLayoutCreateInfo {
+            sType: vk::STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_CREATE_INFO,
             pNext: core::ptr::null(),
-            flags: self.pipe_layout_flags.bits(),
-            setLayoutCount: 1,
-            pSetLayouts: &shader.layout(),
-            pushConstantRangeCount: 0,
-            pPushConstantRanges: core::ptr::null(),
+            flags: builder.layout_flags.bits(),
+            bindingCount: usize_to_u32(builder.bindings.len()),
+            pBindings: builder.bindings.as_ptr(),
         };
-        tri! { (entry.create_pipeline_layout)(self.device.id(), addr_of!(layout_info), core::ptr::null(), addr_of_mut!(my_layout)) }
-        let layout;
-        if let Some(my_layout) = NonZeroU64::new(my_layout) {
-            layout = my_layout;
-        } else {
-            return Err(vk::ERROR_UNKNOWN.into())
+
+        let mut set_layout = 0;
+        tri! {
+            (entry.create_descriptor_set_layout)(device.id(), addr_of!(set_info), core::ptr::null(), addr_of_mut!(set_layout))
         }
+        let set_layout = NonZeroU64::new(set_layout).ok_or(vk::ERROR_UNKNOWN)?;
+
+        // Create pipeline layout
+        let push_constants = vk::PushConstantRange {
+            stageFlags: ShaderStages::COMPUTE.bits(),
+            offset: 0,
+            size: builder.push_constant_size,
+        };
 
-        // Create pipeline (TODO FIX BUG)
-        let mut pipeline = 0;
-        let info = vk::ComputePipelineCreateInfo {
-            sType: vk::STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO,
+        let info = vk::PipelineLayoutCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO,
             pNext: core::ptr::null(),
-            //flags: vk::PIPELINE_CREATE_DISABLE_OPTIMIZATION_BIT, 
-            flags: self.pipe_flags.bits(),
-            stage: vk::PipelineShaderStageCreateInfo {
-                sType: vk::STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
-                pNext: core::ptr::null(),
-                flags: 0,
-                stage: ShaderStages::COMPUTE.bits(),
-                module: shader.module(),
-                pName: self.entry.as_ptr(),
-                //pName: b"compute_personal_event\0".as_ptr().cast(),
-                pSpecializationInfo: core::ptr::null(),
-            },
-            layout: layout.get(),
-            basePipelineHandle: vk::NULL_HANDLE,
-            basePipelineIndex: 0,
+            flags: builder.pipe_layout_flags.bits(),
+            setLayoutCount: 1,
+            pSetLayouts: addr_of!(set_layout).cast(),
+            pushConstantRangeCount: (builder.push_constant_size > 0) as u32,
+            pPushConstantRanges: addr_of!(push_constants),
         };
 
-        println!("{:#?}, {:#?}, {:#?}, {:#?}", self.pipe_flags, shader.module(), self.entry, layout);
-        println!("{:#?}, {:#?}, {:#?}", self.device.id(), cache.as_ref().map_or(vk::NULL_HANDLE, PipelineCache::id), pipeline);
-
-        match (entry.create_compute_pipelines)(
-            self.device.id(),
-            cache.as_ref().map_or(vk::NULL_HANDLE, PipelineCache::id),
-            1,
-            addr_of!(info),
-            core::ptr::null(),
-            addr_of_mut!(pipeline)
-        ) {
+        let mut inner = 0;
+        match (entry.create_pipeline_layout)(device.id(), addr_of!(info), core::ptr::null(), addr_of_mut!(inner)) {
             vk::SUCCESS => {},
             e => {
-                (Entry::get().destroy_pipeline_layout)(self.device.id(), layout.get(), core::ptr::null());
+                (entry.destroy_descriptor_set_layout)(device.id(), set_layout.get(), core::ptr::null());
                 return Err(e.into())
             }
         }
 
-        if let Some(inner) = NonZeroU64::new(pipeline) {
-            let pool = match self.build_descriptor_pool() {
-                Ok(x) => x,
-                Err(e) => {
-                    (Entry::get().destroy_pipeline)(self.device.id(), inner.get(), core::ptr::null());
-                    return Err(e);
-                }
-            };
-
-            let sets = DescriptorSets::new(pool, core::slice::from_ref(&shader))?;
-            return Ok(Pipeline { inner, layout, sets })
+        if let Some(inner) = NonZeroU64::new(inner) {
+            return Ok(Self { inner, set_layout, key: builder.layout_key(), device })
         }
 
-        (Entry::get().destroy_pipeline_layout)(self.device.id(), layout.get(), core::ptr::null());
+        (entry.destroy_descriptor_set_layout)(device.id(), set_layout.get(), core::ptr::null());
         return Err(vk::ERROR_UNKNOWN.into())
     }
 
-    fn build_shader (&mut self, words: &[u32]) -> Result<Shader<D>> {
-        let builder = crate::shader::Builder {
-            bindings: core::mem::take(&mut self.bindings),
-            flags: self.layout_flags,
-            stage: ShaderStages::COMPUTE,
-            device: self.device.clone(),
-            entry: self.entry,
-        };
+    #[inline]
+    pub fn id (&self) -> u64 {
+        return self.inner.get()
+    }
 
-        return builder.build(words);
+    #[inline]
+    pub fn set_layout (&self) -> u64 {
+        return self.set_layout.get()
     }
 
-    fn build_descriptor_pool (&mut self) -> Result<DescriptorPool<D>> {
-        let builder = crate::descriptor::Builder {
-            flags: DescriptorPoolFlags::empty(),
-            capacity: 1,
-            pool_sizes: core::mem::take(&mut self.pool_sizes),
-            device: self.device.clone(),
-        };
+    #[inline]
+    pub fn device (&self) -> &Device {
+        return &self.device
+    }
 
-        return builder.build()
+    #[inline]
+    pub(crate) fn key (&self) -> &LayoutKey {
+        return &self.key
+    }
+}
+
+impl<D: DeviceRef> Drop for PipelineLayout<D> {
+    #[inline]
+    fn drop(&mut self) {
+        let entry = Entry::get();
+        (entry.destroy_pipeline_layout)(self.device.id(), self.id(), core::ptr::null());
+        (entry.destroy_descriptor_set_layout)(self.device.id(), self.set_layout(), core::ptr::null());
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Pipeline<D: DeviceRef> {
-    inner: NonZeroU64,
-    layout: NonZeroU64,
-    sets: DescriptorSets<D>
+    pub(crate) inner: NonZeroU64,
+    pub(crate) layout: Arc<PipelineLayout<D>>,
+    pub(crate) sets: DescriptorSets<D>,
+    pub(crate) workgroup_size: [u32; 3]
 }
 
 impl<D: DeviceRef> Pipeline<D> {
@@ -220,7 +392,30 @@ impl<D: DeviceRef> Pipeline<D> {
 
     #[inline]
     pub fn layout (&self) -> u64 {
-        return self.layout.get()
+        return self.layout.id()
+    }
+
+    #[inline]
+    pub fn shared_layout (&self) -> &Arc<PipelineLayout<D>> {
+        return &self.layout
+    }
+
+    /// Workgroup size set with [`ComputeBuilder::workgroup_size`], or `[1, 1, 1]` if it wasn't specialized
+    #[inline]
+    pub fn workgroup_size (&self) -> [u32; 3] {
+        return self.workgroup_size
+    }
+
+    /// Workgroup counts of a dispatch of at least `invocations` invocations.
+    /// Shaders must ignore the extra invocations of the last workgroups.
+    #[inline]
+    pub fn workgroup_count (&self, invocations: [u32; 3]) -> [u32; 3] {
+        let mut result = [0; 3];
+        for i in 0..3 {
+            let size = self.workgroup_size[i].max(1);
+            result[i] = invocations[i] / size + (invocations[i] % size != 0) as u32;
+        }
+        return result
     }
 
     #[inline]
@@ -228,6 +423,12 @@ impl<D: DeviceRef> Pipeline<D> {
         return self.sets.device()
     }
 
+    /// Gives the pipeline a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_PIPELINE, self.id(), name)
+    }
+
     #[inline]
     pub fn sets (&self) -> &DescriptorSets<D> {
         return &self.sets
@@ -242,17 +443,12 @@ impl<D: DeviceRef> Pipeline<D> {
     pub fn pool (&self) -> &DescriptorPool<D> {
         return self.sets.pool()
     }
-
-    /*#[inline]
-    pub fn compute<'b: 'a> (shader: &'b Shader<'a>) -> ComputeBuilder<'a, 'b> {
-        return ComputeBuilder::new(shader)
-    }*/
 }
 
 impl<D: DeviceRef> Drop for Pipeline<D> {
     #[inline]
     fn drop(&mut self) {
-        (Entry::get().destroy_pipeline_layout)(self.device().id(), self.layout(), core::ptr::null());
+        crate::debug::unregister_pipeline(self.id());
         (Entry::get().destroy_pipeline)(self.device().id(), self.id(), core::ptr::null());
     }
 }
@@ -372,16 +568,109 @@ bitflags::bitflags! {
 }
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-struct PipelineCache<D: DeviceRef> {
+pub struct PipelineCache<D: DeviceRef> {
     inner: NonZeroU64,
     device: D
 }
 
 impl<D: DeviceRef> PipelineCache<D> {
+    #[inline]
+    pub fn new (device: D, flags: PipelineCacheFlags) -> Result<Self> {
+        return Self::with_data(device, flags, &[])
+    }
+
+    /// Creates a pipeline cache with the contents of a previous [`data`](PipelineCache::data) call.
+    /// The implementation will silently ignore `data` if it isn't compatible with the device.
+    pub fn with_data (device: D, flags: PipelineCacheFlags, data: &[u8]) -> Result<Self> {
+        let info = vk::PipelineCacheCreateInfo {
+            sType: vk::STRUCTURE_TYPE_PIPELINE_CACHE_CREATE_INFO,
+            pNext: core::ptr::null(),
+            flags: flags.bits(),
+            initialDataSize: data.len(),
+            pInitialData: data.as_ptr().cast(),
+        };
+
+        let mut inner = 0;
+        tri! {
+            (Entry::get().create_pipeline_cache)(device.id(), addr_of!(info), core::ptr::null(), addr_of_mut!(inner))
+        }
+
+        if let Some(inner) = NonZeroU64::new(inner) {
+            return Ok(Self { inner, device })
+        }
+        return Err(vk::ERROR_UNKNOWN.into())
+    }
+
+    /// Loads a pipeline cache previously stored with [`save`](PipelineCache::save).
+    /// If the file doesn't exist, or was created by another device or driver version, an empty cache is created instead
+    /// (logging a warning in the latter case).
+    pub fn load (device: D, flags: PipelineCacheFlags, path: impl AsRef<Path>) -> Result<Self> {
+        let file = match std::fs::read(path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::new(device, flags),
+            Err(e) => return Err(e.into())
+        };
+
+        let props = device.physical().properties();
+        return match validate_cache(&file, &props) {
+            Some(data) => Self::with_data(device, flags, data),
+            None => {
+                log::warn!(target: "vulkan::pipeline_cache", "Discarding incompatible pipeline cache");
+                Self::new(device, flags)
+            }
+        }
+    }
+
     #[inline]
     pub fn id (&self) -> u64 {
         return self.inner.get()
     }
+
+    #[inline]
+    pub fn device (&self) -> &Device {
+        return &self.device
+    }
+
+    /// Retrieves the contents of the cache, retrying if pipelines added to it meanwhile make it outgrow the queried size
+    pub fn data (&self) -> Result<Vec<u8>> {
+        let entry = Entry::get();
+
+        loop {
+            let mut len = 0;
+            tri! {
+                (entry.get_pipeline_cache_data)(self.device.id(), self.id(), addr_of_mut!(len), core::ptr::null_mut())
+            }
+
+            let mut result = Vec::<u8>::with_capacity(len);
+            match (entry.get_pipeline_cache_data)(self.device.id(), self.id(), addr_of_mut!(len), result.as_mut_ptr().cast()) {
+                vk::SUCCESS => {},
+                vk::INCOMPLETE => continue,
+                e => return Err(e.into())
+            }
+
+            unsafe { result.set_len(len) };
+            return Ok(result)
+        }
+    }
+
+    /// Stores the contents of the cache into `path`, tagged with the device's UUID and driver version.
+    pub fn save (&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let props = self.device.physical().properties();
+        let data = self.data()?;
+
+        let mut file = Vec::with_capacity(CACHE_HEADER_SIZE + data.len());
+        file.extend_from_slice(&CACHE_MAGIC);
+        file.extend_from_slice(&props.device_uuid());
+        file.extend_from_slice(&props.raw_driver_version().to_le_bytes());
+        file.extend_from_slice(&data);
+
+        // Write to a temporary file first, so a crash never leaves a half-written cache behind
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, &file)?;
+        std::fs::rename(&tmp, path)?;
+        return Ok(())
+    }
 }
 
 impl<D: DeviceRef> Drop for PipelineCache<D> {
@@ -389,4 +678,32 @@ impl<D: DeviceRef> Drop for PipelineCache<D> {
     fn drop(&mut self) {
         (Entry::get().destroy_pipeline_cache)(self.device.id(), self.id(), core::ptr::null())
     }
-}
\ No newline at end of file
+}
+
+fn validate_cache<'a> (file: &'a [u8], props: &Properties) -> Option<&'a [u8]> {
+    #[inline]
+    fn read_u32 (bytes: &[u8]) -> u32 {
+        u32::from_le_bytes(bytes[..4].try_into().unwrap())
+    }
+
+    let (header, data) = (file.get(..CACHE_HEADER_SIZE)?, file.get(CACHE_HEADER_SIZE..)?);
+    let (magic, header) = header.split_at(CACHE_MAGIC.len());
+    let (uuid, driver_version) = header.split_at(vk::UUID_SIZE as usize);
+
+    if magic != CACHE_MAGIC || uuid != props.device_uuid() || read_u32(driver_version) != props.raw_driver_version() {
+        return None
+    }
+
+    // Check the header written by the implementation (VkPipelineCacheHeaderVersionOne)
+    let vk_header = data.get(..VK_CACHE_HEADER_SIZE)?;
+    if (read_u32(&vk_header[0..]) as usize) < VK_CACHE_HEADER_SIZE
+        || read_u32(&vk_header[4..]) != vk::PIPELINE_CACHE_HEADER_VERSION_ONE as u32
+        || read_u32(&vk_header[8..]) != props.vendor_id()
+        || read_u32(&vk_header[12..]) != props.device_id()
+        || vk_header[16..] != props.pipeline_cache_uuid()
+    {
+        return None
+    }
+
+    return Some(data)
+}
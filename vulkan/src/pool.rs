@@ -1,11 +1,16 @@
-use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, sync::{TryLockError, RwLockWriteGuard, RwLock, RwLockReadGuard}, slice::SliceIndex, ffi::c_void, marker::PhantomData, ops::{RangeBounds, Bound, Index}};
-use crate::{Result, Entry, physical_dev::Family, device::{Device, DeviceRef}, utils::usize_to_u32, pipeline::{Pipeline, PipelineShaderStages, PipelineStages}, shader::ShaderStages, descriptor::DescriptorSet};
+use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, sync::{TryLockError, RwLockWriteGuard, RwLock, RwLockReadGuard}, slice::SliceIndex, ffi::{c_void, CStr}, marker::PhantomData, ops::{RangeBounds, Bound, Index}};
+use crate::{Result, Entry, error::Error, physical_dev::Family, device::{Device, DeviceRef}, utils::usize_to_u32, pipeline::{Pipeline, PipelineShaderStages, PipelineStages}, shader::ShaderStages, descriptor::DescriptorSet, buffer::Buffer, alloc::DeviceAllocator, barrier::{MemoryBarrier, BufferMemoryBarrier, AccessFlags, DependencyFlags}, sync::Event, query::{QueryPool, QueryFlags}};
+
+mod parallel;
+pub use parallel::*;
 
 #[derive(Debug)]
 pub struct CommandPool<D: DeviceRef> {
     inner: NonZeroU64,
     pub(crate) locks: Box<[std::sync::RwLock<()>]>,
     pub(crate) buffers: Box<[vk::CommandBuffer]>,
+    flags: CommandPoolFlags,
+    level: CommandBufferLevel,
     parent: D
 }
 
@@ -31,7 +36,7 @@ impl<D: DeviceRef> CommandPool<D> {
                     return Err(e)
                 }
             };
-            return Ok(Self { inner, locks, buffers, parent })
+            return Ok(Self { inner, locks, buffers, flags, level, parent })
         }
         return Err(vk::ERROR_UNKNOWN.into())
     }
@@ -41,6 +46,22 @@ impl<D: DeviceRef> CommandPool<D> {
         return self.inner.get()
     }
 
+    #[inline]
+    pub fn flags (&self) -> CommandPoolFlags {
+        return self.flags
+    }
+
+    #[inline]
+    pub fn level (&self) -> CommandBufferLevel {
+        return self.level
+    }
+
+    /// Number of command buffers allocated from the pool
+    #[inline]
+    pub fn len (&self) -> u32 {
+        return usize_to_u32(self.buffers.len())
+    }
+
     #[inline]
     pub fn owned_device (&self) -> D where D: Clone {
         return self.parent.clone()
@@ -51,6 +72,18 @@ impl<D: DeviceRef> CommandPool<D> {
         return &self.parent
     }
 
+    /// Gives the command pool a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_COMMAND_POOL, self.id(), name)
+    }
+
+    /// Gives the command buffer at `idx` a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_buffer_name (&self, idx: u32, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_COMMAND_BUFFER, self.buffers[idx as usize], name)
+    }
+
     #[inline]
     pub fn get_slice<I: Clone> (&self, bounds: I) -> Vec<CommandBuffer<'_>> where
         I: SliceIndex<[RwLock<()>], Output = [RwLock<()>]>
@@ -92,28 +125,58 @@ impl<D: DeviceRef> CommandPool<D> {
     #[inline]
     pub fn begin<P: DeviceRef> (&self, idx: u32, flags: CommandBufferUsage) -> Result<Command<'_, P>> {
         return match self.locks[idx as usize].write() {
-            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Guard(inner), flags),
-            Err(e) => Command::new(self.buffers[idx as usize], CommandLock::Guard(e.into_inner()), flags),
+            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Guard(inner), self.resettable(), self.level, flags),
+            Err(e) => Command::new(self.buffers[idx as usize], CommandLock::Guard(e.into_inner()), self.resettable(), self.level, flags),
         }
     }
 
     #[inline]
     pub fn begin_mut<P: DeviceRef> (&mut self, idx: u32, flags: CommandBufferUsage) -> Result<Command<'_, P>> {
+        let (resettable, level) = (self.resettable(), self.level);
         return match self.locks[idx as usize].get_mut() {
-            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Ref(inner), flags),
-            Err(e) => Command::new(self.buffers[idx as usize], CommandLock::Ref(e.into_inner()), flags),
+            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Ref(inner), resettable, level, flags),
+            Err(e) => Command::new(self.buffers[idx as usize], CommandLock::Ref(e.into_inner()), resettable, level, flags),
         }
     }
     
     #[inline]
     pub fn try_begin<P: DeviceRef> (&self, idx: u32, flags: CommandBufferUsage) -> Result<Option<Command<'_, P>>> {
         return match self.locks[idx as usize].try_write() {
-            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Guard(inner), flags).map(Some),
-            Err(TryLockError::Poisoned(e)) => Command::new(self.buffers[idx as usize], CommandLock::Guard(e.into_inner()), flags).map(Some),
+            Ok(inner) => Command::new(self.buffers[idx as usize], CommandLock::Guard(inner), self.resettable(), self.level, flags).map(Some),
+            Err(TryLockError::Poisoned(e)) => Command::new(self.buffers[idx as usize], CommandLock::Guard(e.into_inner()), self.resettable(), self.level, flags).map(Some),
             Err(_) => Ok(None)
         }
     }
 
+    /// Resets every command buffer of the pool back to the initial state.
+    /// None of them may be pending execution.
+    #[inline]
+    pub fn reset (&mut self, flags: CommandPoolResetFlags) -> Result<()> {
+        tri! {
+            (Entry::get().reset_command_pool)(self.device().id(), self.id(), flags.bits())
+        }
+        return Ok(())
+    }
+
+    /// Resets the command buffer at `idx` back to the initial state. It may not be pending execution.
+    /// The pool must have been created with [`CommandPoolFlags::RESET_COMMAND_BUFFER`].
+    #[inline]
+    pub fn reset_buffer (&mut self, idx: u32, flags: CommandBufferResetFlags) -> Result<()> {
+        if !self.flags.contains(CommandPoolFlags::RESET_COMMAND_BUFFER) {
+            return Err(Error::NotResettable)
+        }
+
+        tri! {
+            (Entry::get().reset_command_buffer)(self.buffers[idx as usize], flags.bits())
+        }
+        return Ok(())
+    }
+
+    #[inline]
+    fn resettable (&self) -> bool {
+        return self.flags.contains(CommandPoolFlags::RESET_COMMAND_BUFFER)
+    }
+
     fn create_buffers (parent: NonZeroU64, device: &Device, capacity: u32, level: CommandBufferLevel) -> Result<(Box<[std::sync::RwLock<()>]>, Box<[vk::CommandBuffer]>)> {
         let info = vk::CommandBufferAllocateInfo {
             sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
@@ -176,24 +239,62 @@ enum CommandLock<'a> {
 pub struct Command<'a, P: DeviceRef> {
     inner: vk::CommandBuffer,
     pipeline: Option<&'a Pipeline<P>>,
+    resettable: bool,
+    level: CommandBufferLevel,
     _lock: CommandLock<'a>
 }
 
 impl<'a, P: DeviceRef> Command<'a, P> {
-    fn new (inner: vk::CommandBuffer, lock: CommandLock<'a>, flags: CommandBufferUsage) -> Result<Self> {
-        let this = Self { inner, pipeline: None, _lock: lock };
+    fn new (inner: vk::CommandBuffer, lock: CommandLock<'a>, resettable: bool, level: CommandBufferLevel, flags: CommandBufferUsage) -> Result<Self> {
+        let this = Self { inner, pipeline: None, resettable, level, _lock: lock };
+        this.begin(flags)?;
+        return Ok(this)
+    }
+
+    #[inline]
+    fn begin (&self, flags: CommandBufferUsage) -> Result<()> {
+        // Secondary command buffers only run compute & transfer commands, so they inherit nothing from a render pass
+        let inheritance = vk::CommandBufferInheritanceInfo {
+            sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_INHERITANCE_INFO,
+            pNext: core::ptr::null(),
+            renderPass: 0,
+            subpass: 0,
+            framebuffer: 0,
+            occlusionQueryEnable: vk::FALSE,
+            queryFlags: 0,
+            pipelineStatistics: 0,
+        };
+
         let info = vk::CommandBufferBeginInfo {
             sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
             pNext: core::ptr::null(),
             flags: flags.bits(),
-            pInheritanceInfo: core::ptr::null(), // todo
+            pInheritanceInfo: match self.level {
+                CommandBufferLevel::Secondary => addr_of!(inheritance),
+                _ => core::ptr::null()
+            },
         };
 
         tri! {
-            (Entry::get().begin_command_buffer)(this.id(), addr_of!(info))
+            (Entry::get().begin_command_buffer)(self.id(), addr_of!(info))
+        }
+        return Ok(())
+    }
+
+    /// Discards every command recorded so far, and starts recording again with the new `flags`.
+    /// The pool must have been created with [`CommandPoolFlags::RESET_COMMAND_BUFFER`].
+    #[inline]
+    pub fn reset (&mut self, reset: CommandBufferResetFlags, flags: CommandBufferUsage) -> Result<()> {
+        if !self.resettable {
+            return Err(Error::NotResettable)
+        }
+
+        tri! {
+            (Entry::get().reset_command_buffer)(self.id(), reset.bits())
         }
 
-        return Ok(this)
+        self.pipeline = None;
+        return self.begin(flags)
     }
 
     #[inline]
@@ -201,14 +302,26 @@ impl<'a, P: DeviceRef> Command<'a, P> {
         return self.inner
     }
 
+    #[inline]
+    pub fn level (&self) -> CommandBufferLevel {
+        return self.level
+    }
+
     #[inline]
     pub fn push_contant<T: Copy> (&mut self, value: &T, stages: ShaderStages) -> Result<()> {
+        return self.push_constant_at(0, value, stages)
+    }
+
+    /// Updates the push constants of the bound pipeline starting at byte `offset`, which must be a multiple of 4
+    #[inline]
+    pub fn push_constant_at<T: Copy> (&mut self, offset: u32, value: &T, stages: ShaderStages) -> Result<()> {
         let pipeline = self.pipeline.ok_or(vk::ERROR_NOT_PERMITTED_KHR)?;
+        debug_assert_eq!(offset % 4, 0);
         (Entry::get().cmd_push_constants)(
             self.id(),
             pipeline.layout(),
             stages.bits(),
-            0,
+            offset,
             usize_to_u32(core::mem::size_of::<T>()),
             value as *const T as *const c_void
         );
@@ -250,6 +363,24 @@ impl<'a, P: DeviceRef> Command<'a, P> {
         self.pipeline = Some(pipeline);
     }
 
+    /// Executes the secondary command buffers, in order.
+    /// Their recording must have ended, and they must not be pending execution elsewhere (unless begun with [`CommandBufferUsage::SIMULTANEOUS_USE`]).
+    #[inline]
+    pub fn execute_commands (&mut self, buffers: &[CommandBuffer<'_>]) {
+        let buffers = buffers.iter().map(CommandBuffer::id).collect::<Vec<_>>();
+        self.execute_raw(&buffers);
+    }
+
+    #[inline]
+    fn execute_raw (&mut self, buffers: &[vk::CommandBuffer]) {
+        debug_assert_eq!(self.level, CommandBufferLevel::Primary);
+        (Entry::get().cmd_execute_commands)(
+            self.id(),
+            usize_to_u32(buffers.len()),
+            buffers.as_ptr()
+        );
+    }
+
     #[inline]
     pub fn dispatch (&mut self, x: u32, y: u32, z: u32) {
         (Entry::get().cmd_dispatch)(
@@ -257,6 +388,220 @@ impl<'a, P: DeviceRef> Command<'a, P> {
             x, y, z
         );
     }
+
+    /// Dispatches the workgroups with their ids starting at the base ones, instead of 0.
+    /// The bound pipeline must have been created with [`PipelineFlags::DISPATCH_BASE`](crate::pipeline::PipelineFlags::DISPATCH_BASE).
+    #[inline]
+    pub fn dispatch_base (&mut self, base_x: u32, base_y: u32, base_z: u32, x: u32, y: u32, z: u32) {
+        (Entry::get().cmd_dispatch_base)(
+            self.id(),
+            base_x, base_y, base_z,
+            x, y, z
+        );
+    }
+
+    /// Dispatches the workgroups described by element `offset` of `buffer`, which is read by the device once the command executes.
+    /// Writes to `buffer` must be made visible with [`AccessFlags::INDIRECT_COMMAND_READ`] on the [`PipelineStages::DRAW_INDIRECT`] stage.
+    #[inline]
+    pub fn dispatch_indirect<A: DeviceAllocator> (&mut self, buffer: &Buffer<DispatchIndirectCommand, A>, offset: u64) {
+        debug_assert!(offset < buffer.len());
+        (Entry::get().cmd_dispatch_indirect)(
+            self.id(),
+            buffer.id(),
+            offset * core::mem::size_of::<DispatchIndirectCommand>() as u64
+        );
+    }
+
+    /// Copies the specified byte regions from `src` into `dst`
+    #[inline]
+    pub fn copy_buffer<T, U, A: DeviceAllocator, B: DeviceAllocator> (&mut self, src: &Buffer<T, A>, dst: &Buffer<U, B>, regions: &[BufferCopy]) {
+        (Entry::get().cmd_copy_buffer)(
+            self.id(),
+            src.id(),
+            dst.id(),
+            usize_to_u32(regions.len()),
+            regions.as_ptr().cast()
+        );
+    }
+
+    /// Fills the byte range `bounds` of `dst` with repeated copies of `data`.
+    /// Both ends of the range must be multiples of 4 (so an included end must be one less than a multiple of 4), or the command isn't recorded.
+    #[inline]
+    pub fn fill_buffer<T, A: DeviceAllocator> (&mut self, dst: &Buffer<T, A>, bounds: impl RangeBounds<u64>, data: u32) -> Result<()> {
+        let offset = match bounds.start_bound() {
+            Bound::Excluded(x) => *x + 1,
+            Bound::Included(x) => *x,
+            Bound::Unbounded => 0
+        };
+
+        let size = match bounds.end_bound() {
+            Bound::Excluded(x) => *x - offset,
+            Bound::Included(x) => *x + 1 - offset,
+            Bound::Unbounded => vk::WHOLE_SIZE
+        };
+
+        if offset % 4 != 0 || (size != vk::WHOLE_SIZE && (size == 0 || size % 4 != 0)) {
+            return Err(Error::Validation(format!("fill range must be made of a positive multiple of 4 bytes at an offset multiple of 4, found {size} bytes at {offset}")))
+        }
+
+        (Entry::get().cmd_fill_buffer)(
+            self.id(),
+            dst.id(),
+            offset,
+            size,
+            data
+        );
+        return Ok(())
+    }
+
+    /// Updates the contents of `dst`, starting at element `offset`, with `data`, inlined into the command buffer.
+    /// The byte offset & the size of `data` must be multiples of 4, and `data` neither empty (or the command isn't recorded) nor bigger than 65536 bytes.
+    #[inline]
+    pub fn update_buffer<T: Copy, A: DeviceAllocator> (&mut self, dst: &Buffer<T, A>, offset: u64, data: &[T]) -> Result<()> {
+        const MAX_SIZE: usize = 65536;
+
+        let size = core::mem::size_of_val(data);
+        if size == 0 {
+            return Err(Error::Validation("can't update a buffer with empty data".into()))
+        }
+
+        let offset = offset * core::mem::size_of::<T>() as u64;
+        debug_assert!(size <= MAX_SIZE);
+        debug_assert_eq!(size % 4, 0);
+        debug_assert_eq!(offset % 4, 0);
+
+        (Entry::get().cmd_update_buffer)(
+            self.id(),
+            dst.id(),
+            offset,
+            size as u64,
+            data.as_ptr().cast()
+        );
+        return Ok(())
+    }
+
+    /// Makes the commands of `dst` stages recorded after this barrier wait for the commands of `src` stages recorded before it,
+    /// making the memory accesses described by the barriers available & visible to them.
+    #[inline]
+    pub fn pipeline_barrier (&mut self, src: PipelineStages, dst: PipelineStages, flags: DependencyFlags, memory: &[MemoryBarrier], buffers: &[BufferMemoryBarrier<'_>]) {
+        (Entry::get().cmd_pipeline_barrier)(
+            self.id(),
+            src.bits(),
+            dst.bits(),
+            flags.bits(),
+            usize_to_u32(memory.len()),
+            memory.as_ptr().cast(),
+            usize_to_u32(buffers.len()),
+            buffers.as_ptr().cast(),
+            0,
+            core::ptr::null()
+        );
+    }
+
+    /// Sets `event` once the commands of `stages` recorded before it complete
+    #[inline]
+    pub fn set_event<E: DeviceRef> (&mut self, event: &Event<E>, stages: PipelineStages) {
+        (Entry::get().cmd_set_event)(self.id(), event.id(), stages.bits());
+    }
+
+    /// Resets `event` once the commands of `stages` recorded before it complete
+    #[inline]
+    pub fn reset_event<E: DeviceRef> (&mut self, event: &Event<E>, stages: PipelineStages) {
+        (Entry::get().cmd_reset_event)(self.id(), event.id(), stages.bits());
+    }
+
+    /// Makes the commands of `dst` stages recorded after this command wait for all `events` to be set,
+    /// making the memory accesses described by the barriers available & visible to them.
+    #[inline]
+    pub fn wait_events<E: DeviceRef> (&mut self, events: &[&Event<E>], src: PipelineStages, dst: PipelineStages, memory: &[MemoryBarrier], buffers: &[BufferMemoryBarrier<'_>]) {
+        let events = events.iter().map(|x| x.id()).collect::<Vec<_>>();
+        (Entry::get().cmd_wait_events)(
+            self.id(),
+            usize_to_u32(events.len()),
+            events.as_ptr(),
+            src.bits(),
+            dst.bits(),
+            usize_to_u32(memory.len()),
+            memory.as_ptr().cast(),
+            usize_to_u32(buffers.len()),
+            buffers.as_ptr().cast(),
+            0,
+            core::ptr::null()
+        );
+    }
+
+    /// Resets the queries of `pool` inside `bounds`, which must be done before they're used
+    #[inline]
+    pub fn reset_queries<Q: DeviceRef> (&mut self, pool: &QueryPool<Q>, bounds: impl RangeBounds<u32>) {
+        let first = match bounds.start_bound() {
+            Bound::Excluded(x) => *x + 1,
+            Bound::Included(x) => *x,
+            Bound::Unbounded => 0
+        };
+
+        let count = match bounds.end_bound() {
+            Bound::Excluded(x) => *x - first,
+            Bound::Included(x) => *x + 1 - first,
+            Bound::Unbounded => pool.len() - first
+        };
+
+        (Entry::get().cmd_reset_query_pool)(self.id(), pool.id(), first, count);
+    }
+
+    /// Writes the device's timestamp into `query` once the commands of `stage` recorded before it complete
+    #[inline]
+    pub fn write_timestamp<Q: DeviceRef> (&mut self, pool: &QueryPool<Q>, query: u32, stage: PipelineStages) {
+        debug_assert!(query < pool.len());
+        (Entry::get().cmd_write_timestamp)(self.id(), stage.bits(), pool.id(), query);
+    }
+
+    #[inline]
+    pub fn begin_query<Q: DeviceRef> (&mut self, pool: &QueryPool<Q>, query: u32, flags: QueryFlags) {
+        debug_assert!(query < pool.len());
+        (Entry::get().cmd_begin_query)(self.id(), pool.id(), query, flags.bits());
+    }
+
+    #[inline]
+    pub fn end_query<Q: DeviceRef> (&mut self, pool: &QueryPool<Q>, query: u32) {
+        (Entry::get().cmd_end_query)(self.id(), pool.id(), query);
+    }
+
+    /// Records a global memory barrier between the `src` and `dst` stages
+    #[inline]
+    pub fn memory_barrier (&mut self, src: PipelineStages, src_access: AccessFlags, dst: PipelineStages, dst_access: AccessFlags) {
+        self.pipeline_barrier(src, dst, DependencyFlags::empty(), &[MemoryBarrier::new(src_access, dst_access)], &[]);
+    }
+
+    /// Records a memory barrier over `buffers` between the `src` and `dst` stages
+    #[inline]
+    pub fn buffer_barrier (&mut self, src: PipelineStages, dst: PipelineStages, buffers: &[BufferMemoryBarrier<'_>]) {
+        self.pipeline_barrier(src, dst, DependencyFlags::empty(), &[], buffers);
+    }
+}
+
+/// Workgroup counts of an indirect dispatch, written into a buffer (usually by the device itself)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(C)]
+pub struct DispatchIndirectCommand {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32
+}
+
+impl DispatchIndirectCommand {
+    #[inline]
+    pub const fn new (x: u32, y: u32, z: u32) -> Self {
+        return Self { x, y, z }
+    }
+}
+
+/// Byte region of a buffer copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct BufferCopy {
+    pub src_offset: u64,
+    pub dst_offset: u64,
+    pub size: u64
 }
 
 impl<P: DeviceRef> Drop for Command<'_, P> {
@@ -307,4 +652,22 @@ bitflags::bitflags! {
         /// Command buffer may be submitted/executed more than once simultaneously
         const SIMULTANEOUS_USE = vk::COMMAND_BUFFER_USAGE_SIMULTANEOUS_USE_BIT;
     }
-}
\ No newline at end of file
+}
+
+bitflags::bitflags! {
+    #[repr(transparent)]
+    #[non_exhaustive]
+    pub struct CommandPoolResetFlags: vk::CommandPoolResetFlagBits {
+        /// Returns the memory of the command buffers to the system
+        const RELEASE_RESOURCES = vk::COMMAND_POOL_RESET_RELEASE_RESOURCES_BIT;
+    }
+}
+
+bitflags::bitflags! {
+    #[repr(transparent)]
+    #[non_exhaustive]
+    pub struct CommandBufferResetFlags: vk::CommandBufferResetFlagBits {
+        /// Returns the memory of the command buffer to its pool
+        const RELEASE_RESOURCES = vk::COMMAND_BUFFER_RESET_RELEASE_RESOURCES_BIT;
+    }
+}
@@ -0,0 +1,93 @@
+use std::{num::NonZeroUsize, panic::resume_unwind, thread};
+use crate::{Result, error::Error, device::DeviceRef, physical_dev::Family, utils::usize_to_u32};
+use super::{Command, CommandBufferLevel, CommandBufferUsage, CommandPool, CommandPoolFlags};
+
+/// Secondary command pools, one per recording thread, since a pool can't be used by several threads at once.
+/// Each pool holds one secondary buffer per frame in flight, so that a frame can be recorded while the previous ones are still executing.
+#[derive(Debug)]
+pub struct ParallelPools<D: DeviceRef> {
+    pools: Box<[CommandPool<D>]>
+}
+
+impl<D: DeviceRef> ParallelPools<D> {
+    pub fn new (parent: D, family: Family, threads: NonZeroUsize, frames: u32) -> Result<Self> where D: Clone {
+        debug_assert!(frames > 0);
+        let pools = (0..threads.get())
+            .map(|_| CommandPool::new(
+                parent.clone(),
+                family,
+                CommandPoolFlags::TRANSIENT | CommandPoolFlags::RESET_COMMAND_BUFFER,
+                frames,
+                CommandBufferLevel::Secondary
+            ))
+            .collect::<Result<Box<[_]>>>()?;
+
+        return Ok(Self { pools })
+    }
+
+    /// Creates one pool per thread the system can run in parallel
+    #[inline]
+    pub fn with_available_parallelism (parent: D, family: Family, frames: u32) -> Result<Self> where D: Clone {
+        let threads = thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
+        return Self::new(parent, family, threads, frames)
+    }
+
+    /// Maximum number of threads recording at once
+    #[inline]
+    pub fn len (&self) -> u32 {
+        return usize_to_u32(self.pools.len())
+    }
+
+    /// Number of frames that can be in flight at once
+    #[inline]
+    pub fn frames (&self) -> u32 {
+        return self.pools[0].len()
+    }
+
+    /// Splits `jobs` into contiguous chunks, recording each chunk with `f` into the secondary command buffers of `frame`, each on its own thread.
+    /// Once every thread is done, the secondary buffers are executed from `primary` in the order of `jobs`.
+    ///
+    /// The secondary buffers of `frame` stay in use until the submission of `primary` completes,
+    /// so `frame` can't be recorded into again before then. Fails if `frame` isn't lower than [`frames`](Self::frames).
+    pub fn record<'a, T: Sync, P: DeviceRef, F> (&'a mut self, frame: u32, primary: &mut Command<'_, P>, jobs: &[T], f: F) -> Result<()> where
+        D: Send,
+        F: Sync + Fn(&mut Command<'a, P>, &T) -> Result<()>
+    {
+        if frame >= self.frames() {
+            return Err(Error::Validation(format!("frame {frame} is out of range, there are only {} frames in flight", self.frames())))
+        }
+
+        if jobs.is_empty() {
+            return Ok(())
+        }
+
+        let chunk_size = (jobs.len() + self.pools.len() - 1) / self.pools.len();
+        let buffers = self.pools.iter()
+            .take((jobs.len() + chunk_size - 1) / chunk_size)
+            .map(|x| x.buffers[frame as usize])
+            .collect::<Vec<_>>();
+
+        let pools: &'a mut [CommandPool<D>] = &mut self.pools;
+        let f = &f;
+
+        thread::scope(|s| {
+            let handles = pools.iter_mut()
+                .zip(jobs.chunks(chunk_size))
+                .map(|(pool, jobs)| s.spawn(move || {
+                    let mut cmd_buff = pool.begin_mut::<P>(frame, CommandBufferUsage::ONE_TIME_SUBMIT)?;
+                    for job in jobs {
+                        f(&mut cmd_buff, job)?;
+                    }
+                    return Ok(())
+                }))
+                .collect::<Vec<_>>();
+
+            return handles.into_iter()
+                .map(|x| x.join().unwrap_or_else(|e| resume_unwind(e)))
+                .collect::<Result<()>>()
+        })?;
+
+        primary.execute_raw(&buffers);
+        return Ok(())
+    }
+}
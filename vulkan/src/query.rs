@@ -0,0 +1,180 @@
+use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, time::Duration, ffi::CStr};
+use crate::{Result, Entry, device::{Device, DeviceRef}, physical_dev::Family};
+
+/// Pool of queries, written by command buffers & read from the host
+pub struct QueryPool<D: DeviceRef> {
+    inner: NonZeroU64,
+    len: u32,
+    values_per_query: u32,
+    timestamp_period: f32,
+    /// Valid bits of the timestamps written into the pool
+    timestamp_mask: u64,
+    device: D
+}
+
+impl<D: DeviceRef> QueryPool<D> {
+    /// Creates a pool of `len` timestamp queries, written by the queues of `family`.
+    /// Fails with `ERROR_FEATURE_NOT_PRESENT` if the family doesn't [support timestamps](Family::supports_timestamps).
+    pub fn timestamps (device: D, family: Family, len: u32) -> Result<Self> {
+        if !family.supports_timestamps() {
+            return Err(vk::ERROR_FEATURE_NOT_PRESENT.into())
+        }
+
+        // Supported families with no reported valid bits write full 64-bit timestamps
+        let valid_bits = match family.timestamp_valid_bits() {
+            0 => 64,
+            x => x.min(64)
+        };
+
+        let mut result = Self::new(device, vk::QUERY_TYPE_TIMESTAMP, PipelineStatistics::empty(), len)?;
+        result.timestamp_mask = u64::MAX >> (64 - valid_bits);
+        return Ok(result)
+    }
+
+    /// Creates a pool of `len` pipeline statistics queries, each one collecting the counters in `statistics`
+    #[inline]
+    pub fn pipeline_statistics (device: D, statistics: PipelineStatistics, len: u32) -> Result<Self> {
+        return Self::new(device, vk::QUERY_TYPE_PIPELINE_STATISTICS, statistics, len)
+    }
+
+    fn new (device: D, typ: vk::QueryType, statistics: PipelineStatistics, len: u32) -> Result<Self> {
+        let info = vk::QueryPoolCreateInfo {
+            sType: vk::STRUCTURE_TYPE_QUERY_POOL_CREATE_INFO,
+            pNext: core::ptr::null(),
+            flags: 0,
+            queryType: typ,
+            queryCount: len,
+            pipelineStatistics: statistics.bits(),
+        };
+
+        let mut result = 0;
+        tri! {
+            (Entry::get().create_query_pool)(device.id(), addr_of!(info), core::ptr::null(), addr_of_mut!(result))
+        }
+
+        if let Some(inner) = NonZeroU64::new(result) {
+            let values_per_query = match typ {
+                vk::QUERY_TYPE_PIPELINE_STATISTICS => statistics.bits().count_ones(),
+                _ => 1
+            };
+
+            let timestamp_period = device.physical().properties().limits().timestampPeriod;
+            return Ok(Self { inner, len, values_per_query, timestamp_period, timestamp_mask: u64::MAX, device })
+        }
+        return Err(vk::ERROR_INITIALIZATION_FAILED.into())
+    }
+
+    #[inline]
+    pub fn id (&self) -> u64 {
+        return self.inner.get()
+    }
+
+    #[inline]
+    pub fn len (&self) -> u32 {
+        return self.len
+    }
+
+    /// Number of values written by each query
+    #[inline]
+    pub fn values_per_query (&self) -> u32 {
+        return self.values_per_query
+    }
+
+    #[inline]
+    pub fn device (&self) -> &Device {
+        return &self.device
+    }
+
+    #[inline]
+    pub fn owned_device (&self) -> D where D: Clone {
+        return self.device.clone()
+    }
+
+    /// Gives the query pool a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_QUERY_POOL, self.id(), name)
+    }
+
+    /// Nanoseconds it takes for a timestamp to be incremented by one
+    #[inline]
+    pub fn timestamp_period (&self) -> f32 {
+        return self.timestamp_period
+    }
+
+    /// Converts the difference between two timestamps into a duration, accounting for the timestamps wrapping around their valid bits
+    #[inline]
+    pub fn elapsed (&self, start: u64, end: u64) -> Duration {
+        let ticks = end.wrapping_sub(start) & self.timestamp_mask;
+        let nanos = ticks as f64 * f64::from(self.timestamp_period);
+        return Duration::from_nanos(nanos.round() as u64)
+    }
+
+    /// Reads the results of `count` queries, starting at `first`.
+    ///
+    /// If `wait` is `false` and any of the queries isn't available yet, `None` is returned.
+    pub fn results (&self, first: u32, count: u32, wait: bool) -> Result<Option<Vec<u64>>> {
+        debug_assert!(first + count <= self.len);
+
+        let mut flags = vk::QUERY_RESULT_64_BIT;
+        if wait { flags |= vk::QUERY_RESULT_WAIT_BIT }
+
+        let stride = self.values_per_query as usize * core::mem::size_of::<u64>();
+        let mut result = vec![0u64; (count * self.values_per_query) as usize];
+
+        return match (Entry::get().get_query_pool_results)(
+            self.device.id(),
+            self.id(),
+            first,
+            count,
+            result.len() * core::mem::size_of::<u64>(),
+            result.as_mut_ptr().cast(),
+            stride as u64,
+            flags
+        ) {
+            vk::SUCCESS => {
+                // Bits outside of the valid ones are undefined
+                result.iter_mut().for_each(|x| *x &= self.timestamp_mask);
+                Ok(Some(result))
+            },
+            vk::NOT_READY => Ok(None),
+            e => Err(e.into())
+        }
+    }
+
+    /// Reads the results of every query in the pool
+    #[inline]
+    pub fn all_results (&self, wait: bool) -> Result<Option<Vec<u64>>> {
+        return self.results(0, self.len, wait)
+    }
+}
+
+impl<D: DeviceRef> Drop for QueryPool<D> {
+    #[inline]
+    fn drop(&mut self) {
+        (Entry::get().destroy_query_pool)(self.device.id(), self.id(), core::ptr::null())
+    }
+}
+
+bitflags::bitflags! {
+    #[repr(transparent)]
+    pub struct PipelineStatistics: vk::QueryPipelineStatisticFlagBits {
+        const INPUT_ASSEMBLY_VERTICES = vk::QUERY_PIPELINE_STATISTIC_INPUT_ASSEMBLY_VERTICES_BIT;
+        const INPUT_ASSEMBLY_PRIMITIVES = vk::QUERY_PIPELINE_STATISTIC_INPUT_ASSEMBLY_PRIMITIVES_BIT;
+        const VERTEX_SHADER_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_VERTEX_SHADER_INVOCATIONS_BIT;
+        const GEOMETRY_SHADER_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_GEOMETRY_SHADER_INVOCATIONS_BIT;
+        const GEOMETRY_SHADER_PRIMITIVES = vk::QUERY_PIPELINE_STATISTIC_GEOMETRY_SHADER_PRIMITIVES_BIT;
+        const CLIPPING_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_CLIPPING_INVOCATIONS_BIT;
+        const CLIPPING_PRIMITIVES = vk::QUERY_PIPELINE_STATISTIC_CLIPPING_PRIMITIVES_BIT;
+        const FRAGMENT_SHADER_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_FRAGMENT_SHADER_INVOCATIONS_BIT;
+        const TESSELLATION_CONTROL_SHADER_PATCHES = vk::QUERY_PIPELINE_STATISTIC_TESSELLATION_CONTROL_SHADER_PATCHES_BIT;
+        const TESSELLATION_EVALUATION_SHADER_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_TESSELLATION_EVALUATION_SHADER_INVOCATIONS_BIT;
+        const COMPUTE_SHADER_INVOCATIONS = vk::QUERY_PIPELINE_STATISTIC_COMPUTE_SHADER_INVOCATIONS_BIT;
+    }
+
+    #[repr(transparent)]
+    pub struct QueryFlags: vk::QueryControlFlagBits {
+        /// Require precise results to be collected by the query
+        const PRECISE = vk::QUERY_CONTROL_PRECISE_BIT;
+    }
+}
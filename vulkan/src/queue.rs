@@ -1,10 +1,11 @@
 use std::{num::NonZeroU64, ptr::{addr_of, addr_of_mut}, marker::PhantomData, sync::{RwLock, RwLockReadGuard, LockResult}, time::Duration, slice::SliceIndex};
-use crate::{device::{DeviceRef, Device}, Entry, Result, utils::usize_to_u32, pool::{CommandPool}, sync::{Fence, Semaphore}};
+use crate::{device::{DeviceRef, Device}, physical_dev::Family, Entry, Result, utils::usize_to_u32, pool::{CommandPool}, pipeline::PipelineStages, sync::{Fence, Semaphore, TimelineSemaphore}};
 
 #[derive(Debug, PartialEq, Hash)]
 pub struct Queue {
     pub(super) inner: NonZeroU64,
     pub(super) index: u32,
+    pub(super) family: Family,
     //pub(super) parent: &'a Device
 }
 
@@ -14,64 +15,153 @@ impl Queue {
         return self.inner.get()
     }
 
+    /// Index of the queue within its family
+    #[inline]
+    pub fn index (&self) -> u32 {
+        return self.index
+    }
+
+    #[inline]
+    pub fn family (&self) -> Family {
+        return self.family
+    }
+
     #[inline]
     pub fn submitter<'a, F: DeviceRef, P: DeviceRef, S: DeviceRef> (&'a mut self, fence: Option<&'a mut Fence<F>>) -> SubmitBuilder<'a, F, P, S> {
         return SubmitBuilder {
             queue: self,
             fence,
-            locks: Vec::with_capacity(1),
-            semaphores: Vec::with_capacity(1),
             submits: Vec::with_capacity(1),
             _phtm: PhantomData,
         }
     }
 }
 
+/// Builds a batch of submissions to a queue.
+///
+/// Semaphores added through [`wait`](SubmitBuilder::wait), [`signal`](SubmitBuilder::signal) and their timeline counterparts
+/// apply to the last submission added with [`add`](SubmitBuilder::add).
 pub struct SubmitBuilder<'a, F: DeviceRef, P: DeviceRef, S: DeviceRef = &'a Device> {
     queue: &'a mut Queue,
     fence: Option<&'a mut Fence<F>>,
-    submits: Vec<vk::SubmitInfo>,
-    locks: Vec<Vec<LockResult<RwLockReadGuard<'a, ()>>>>,
-    semaphores: Vec<Vec<vk::Semaphore>>,
-    _phtm: PhantomData<(&'a [Semaphore<S>], &'a CommandPool<P>)>
+    submits: Vec<Submit<'a>>,
+    _phtm: PhantomData<(&'a Semaphore<S>, &'a CommandPool<P>)>
+}
+
+#[derive(Default)]
+struct Submit<'a> {
+    buffers: &'a [vk::CommandBuffer],
+    locks: Vec<LockResult<RwLockReadGuard<'a, ()>>>,
+    wait: Vec<vk::Semaphore>,
+    wait_stages: Vec<vk::PipelineStageFlags>,
+    wait_values: Vec<u64>,
+    signal: Vec<vk::Semaphore>,
+    signal_values: Vec<u64>,
+    timeline: bool
 }
 
 impl<'a, F: DeviceRef, S: DeviceRef, P: DeviceRef> SubmitBuilder<'a, F, P, S> {
+    /// Adds a new submission, executing the specified command buffers of `pool`
     #[inline]
-    pub fn add<B: Clone + SliceIndex<[RwLock<()>], Output = [RwLock<()>]> + SliceIndex<[vk::CommandBuffer], Output = [vk::CommandBuffer]>> (mut self, pool: &'a CommandPool<P>, buffers: B, semaphores: Option<&'a [Semaphore<S>]>) -> Self {
-        let semaphores = semaphores.into_iter().flatten().map(Semaphore::id).collect::<Vec<_>>();
+    pub fn add<B: Clone + SliceIndex<[RwLock<()>], Output = [RwLock<()>]> + SliceIndex<[vk::CommandBuffer], Output = [vk::CommandBuffer]>> (mut self, pool: &'a CommandPool<P>, buffers: B) -> Self {
         let locks = pool.locks[buffers.clone()].iter().map(RwLock::read).collect::<Vec<_>>();
-        let buffers = &pool.buffers[buffers];
-
-        self.submits.push(vk::SubmitInfo {
-            sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
-            pNext: core::ptr::null(),
-            waitSemaphoreCount: usize_to_u32(semaphores.len()),
-            pWaitSemaphores: semaphores.as_ptr(),
-            pWaitDstStageMask: core::ptr::null(),
-            commandBufferCount: usize_to_u32(buffers.len()),
-            pCommandBuffers: buffers.as_ptr(),
-            signalSemaphoreCount: 0, // todo
-            pSignalSemaphores: core::ptr::null(), // todo
+        self.submits.push(Submit {
+            buffers: &pool.buffers[buffers],
+            locks,
+            ..Default::default()
         });
+        return self
+    }
 
-        self.locks.push(locks);
-        self.semaphores.push(semaphores);
+    /// Makes the `stages` of the last submission wait for `semaphore` to be signaled
+    #[inline]
+    pub fn wait (mut self, semaphore: &'a Semaphore<S>, stages: PipelineStages) -> Self {
+        let submit = self.current();
+        submit.wait.push(semaphore.id());
+        submit.wait_stages.push(stages.bits());
+        submit.wait_values.push(0);
+        return self
+    }
+
+    /// Signals `semaphore` once the last submission completes
+    #[inline]
+    pub fn signal (mut self, semaphore: &'a Semaphore<S>) -> Self {
+        let submit = self.current();
+        submit.signal.push(semaphore.id());
+        submit.signal_values.push(0);
+        return self
+    }
+
+    /// Makes the `stages` of the last submission wait for `semaphore` to reach `value`
+    #[inline]
+    pub fn wait_timeline (mut self, semaphore: &'a TimelineSemaphore<S>, value: u64, stages: PipelineStages) -> Self {
+        let submit = self.current();
+        submit.wait.push(semaphore.id());
+        submit.wait_stages.push(stages.bits());
+        submit.wait_values.push(value);
+        submit.timeline = true;
+        return self
+    }
+
+    /// Sets the value of `semaphore` to `value` once the last submission completes
+    #[inline]
+    pub fn signal_timeline (mut self, semaphore: &'a TimelineSemaphore<S>, value: u64) -> Self {
+        let submit = self.current();
+        submit.signal.push(semaphore.id());
+        submit.signal_values.push(value);
+        submit.timeline = true;
         return self
     }
 
     #[inline]
     pub fn submit (self) -> Result<()> {
+        let timelines = self.submits.iter()
+            .map(|x| vk::TimelineSemaphoreSubmitInfo {
+                sType: vk::STRUCTURE_TYPE_TIMELINE_SEMAPHORE_SUBMIT_INFO,
+                pNext: core::ptr::null(),
+                waitSemaphoreValueCount: usize_to_u32(x.wait_values.len()),
+                pWaitSemaphoreValues: x.wait_values.as_ptr(),
+                signalSemaphoreValueCount: usize_to_u32(x.signal_values.len()),
+                pSignalSemaphoreValues: x.signal_values.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let submits = self.submits.iter()
+            .zip(&timelines)
+            .map(|(x, timeline)| vk::SubmitInfo {
+                sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+                pNext: match x.timeline {
+                    true => addr_of!(*timeline).cast(),
+                    false => core::ptr::null()
+                },
+                waitSemaphoreCount: usize_to_u32(x.wait.len()),
+                pWaitSemaphores: x.wait.as_ptr(),
+                pWaitDstStageMask: x.wait_stages.as_ptr(),
+                commandBufferCount: usize_to_u32(x.buffers.len()),
+                pCommandBuffers: x.buffers.as_ptr(),
+                signalSemaphoreCount: usize_to_u32(x.signal.len()),
+                pSignalSemaphores: x.signal.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
         tri! {
             (Entry::get().queue_submit)(
                 self.queue.id(),
-                usize_to_u32(self.submits.len()),
-                self.submits.as_ptr(),
+                usize_to_u32(submits.len()),
+                submits.as_ptr(),
                 self.fence.map_or(vk::NULL_HANDLE, |x| x.id())
             )
         }
         return Ok(())
     }
+
+    #[inline]
+    fn current (&mut self) -> &mut Submit<'a> {
+        if self.submits.is_empty() {
+            self.submits.push(Submit::default());
+        }
+        return unsafe { self.submits.last_mut().unwrap_unchecked() }
+    }
 }
 
 // bitflags::bitflags! {
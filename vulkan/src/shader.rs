@@ -26,6 +26,12 @@ impl<D: DeviceRef> Shader<D> {
     pub fn device (&self) -> &Device {
         return self.device.deref()
     }
+
+    /// Gives the shader module a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name (&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_SHADER_MODULE, self.module(), name)
+    }
 }
 
 impl<D: DeviceRef> Drop for Shader<D> {
@@ -0,0 +1,156 @@
+use std::mem::MaybeUninit;
+use crate::{Result, buffer::{Buffer, UsageFlags, BufferFlags}, alloc::{DeviceAllocator, MemoryUsage}, pool::{Command, BufferCopy}, device::DeviceRef, utils::u64_to_usize};
+
+/// Uploads host data into (possibly device-local) buffers, through a host-visible staging ring.
+///
+/// Every upload records a copy into the provided command buffer, so staging memory has to stay alive until
+/// that command buffer finishes executing. To do so, take a [`StagingMark`] after recording a batch of uploads,
+/// and [`release`](StagingUploader::release) it once the submission's fence has signaled.
+pub struct StagingUploader<A: DeviceAllocator> {
+    buffer: Buffer<u8, A>,
+    head: u64,
+    tail: u64,
+    allocated: u64,
+    released: u64
+}
+
+/// Position of a [`StagingUploader`]'s ring at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StagingMark {
+    head: u64,
+    allocated: u64
+}
+
+impl<A: DeviceAllocator> StagingUploader<A> {
+    /// Creates a new uploader with a staging ring of `capacity` bytes
+    pub fn new (capacity: u64, alloc: A) -> Result<Self> {
+        let buffer = Buffer::<u8, A>::new_uninit(
+            capacity,
+            UsageFlags::TRANSFER_SRC,
+            BufferFlags::empty(),
+            MemoryUsage::upload(),
+            alloc
+        )?;
+
+        return Ok(Self {
+            buffer: unsafe { buffer.assume_init() },
+            head: 0,
+            tail: 0,
+            allocated: 0,
+            released: 0
+        })
+    }
+
+    #[inline]
+    pub fn capacity (&self) -> u64 {
+        return self.buffer.size()
+    }
+
+    /// Bytes of the ring currently in use by uploads that haven't been released
+    #[inline]
+    pub fn used (&self) -> u64 {
+        return self.allocated - self.released
+    }
+
+    /// Copies `src` into the staging ring, and records its transfer into `dst`, starting at element `offset`.
+    ///
+    /// Returns `ERROR_OUT_OF_POOL_MEMORY` if there isn't enough free space in the ring.
+    #[inline]
+    pub fn upload<T: Copy, B: DeviceAllocator, P: DeviceRef> (&mut self, cmd: &mut Command<'_, P>, src: &[T], dst: &Buffer<T, B>, offset: u64) -> Result<()> {
+        return self.upload_raw(cmd, src, dst, offset)
+    }
+
+    /// Copies `src` into the staging ring, and records its transfer into the uninitialized `dst`, starting at element `offset`.
+    ///
+    /// Returns `ERROR_OUT_OF_POOL_MEMORY` if there isn't enough free space in the ring.
+    #[inline]
+    pub fn upload_uninit<T: Copy, B: DeviceAllocator, P: DeviceRef> (&mut self, cmd: &mut Command<'_, P>, src: &[T], dst: &Buffer<MaybeUninit<T>, B>, offset: u64) -> Result<()> {
+        return self.upload_raw(cmd, src, dst, offset)
+    }
+
+    /// Returns the current position of the ring, to be released once every upload recorded before it has completed
+    #[inline]
+    pub fn mark (&self) -> StagingMark {
+        return StagingMark { head: self.head, allocated: self.allocated }
+    }
+
+    /// Frees the staging memory of every upload recorded before `mark`.
+    ///
+    /// # Safety
+    /// The GPU must have finished executing every copy recorded before `mark`
+    #[inline]
+    pub unsafe fn release (&mut self, mark: StagingMark) {
+        if mark.allocated <= self.released { return }
+        debug_assert!(mark.allocated <= self.allocated);
+
+        self.tail = mark.head;
+        self.released = mark.allocated;
+    }
+
+    /// Frees the whole ring.
+    ///
+    /// # Safety
+    /// The GPU must have finished executing every recorded copy
+    #[inline]
+    pub unsafe fn release_all (&mut self) {
+        self.release(self.mark())
+    }
+
+    fn upload_raw<T: Copy, U, B: DeviceAllocator, P: DeviceRef> (&mut self, cmd: &mut Command<'_, P>, src: &[T], dst: &Buffer<U, B>, offset: u64) -> Result<()> {
+        let size = core::mem::size_of_val(src) as u64;
+        if size == 0 { return Ok(()) }
+
+        let align = core::mem::align_of::<T>().max(4) as u64;
+        let start = match self.allocate(size, align) {
+            Some(x) => x,
+            None => return Err(vk::ERROR_OUT_OF_POOL_MEMORY.into())
+        };
+
+        let mut map = self.buffer.map_mut(u64_to_usize(start)..u64_to_usize(start + size))?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(src.as_ptr().cast::<u8>(), map.as_mut_ptr(), map.len());
+        }
+        drop(map);
+
+        cmd.copy_buffer(&self.buffer, dst, &[BufferCopy {
+            src_offset: start,
+            dst_offset: offset * core::mem::size_of::<U>() as u64,
+            size
+        }]);
+
+        return Ok(())
+    }
+
+    fn allocate (&mut self, size: u64, align: u64) -> Option<u64> {
+        let capacity = self.capacity();
+        let used = self.used();
+        if used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        } else if used >= capacity {
+            return None
+        }
+
+        let start = (self.head + align - 1) & !(align - 1);
+        let (start, waste) = if self.head >= self.tail {
+            // Free space is [head, capacity) and [0, tail)
+            if start + size <= capacity {
+                (start, start - self.head)
+            } else if used == 0 || size <= self.tail {
+                if size > capacity { return None }
+                (0, capacity - self.head)
+            } else {
+                return None
+            }
+        } else if start + size <= self.tail {
+            // Free space is [head, tail)
+            (start, start - self.head)
+        } else {
+            return None
+        };
+
+        self.head = start + size;
+        self.allocated += waste + size;
+        return Some(start)
+    }
+}
@@ -5,11 +5,17 @@ use crate::{
     Entry, Result,
 };
 use std::{
+    ffi::CStr,
+    future::Future,
     num::NonZeroU64,
+    pin::Pin,
     ptr::{addr_of, addr_of_mut},
+    task::{Context, Poll},
     time::Duration,
 };
 
+mod waiter;
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Fence<D: DeviceRef> {
     inner: NonZeroU64,
@@ -58,6 +64,12 @@ impl<D: DeviceRef> Fence<D> {
         return self.parent.clone();
     }
 
+    /// Gives the fence a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name(&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_FENCE, self.id(), name);
+    }
+
     #[inline]
     pub fn status(&self) -> Result<bool> {
         return match (Entry::get().get_fence_status)(self.device().id(), self.id()) {
@@ -89,35 +101,23 @@ impl<D: DeviceRef> Fence<D> {
         return Ok(());
     }
 
+    /// Submits the first command buffer of `pool` to `queue`, signaling this fence once it completes
     #[inline]
-    pub fn bind_to<'b, P: DeviceRef, S: DeviceRef>(
-        &mut self,
-        pool: &'b mut CommandPool<P>,
-        queue: &mut Queue,
-        semaphores: Option<&'b [Semaphore<S>]>,
-    ) -> Result<()> {
+    pub fn bind_to<P: DeviceRef>(&mut self, pool: &mut CommandPool<P>, queue: &mut Queue) -> Result<()> {
         queue
-            .submitter(Some(self))
-            .add(pool, 0..1, semaphores)
+            .submitter::<D, P, &Device>(Some(self))
+            .add(pool, 0..1)
             .submit()
     }
 
     #[inline]
     pub fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
-        let timeout = match timeout {
-            #[cfg(debug_assertions)]
-            Some(x) => u64::try_from(x.as_nanos()).unwrap(),
-            #[cfg(not(debug_assertions))]
-            Some(x) => x.as_nanos() as u64,
-            None => u64::MAX,
-        };
-
         return match (Entry::get().wait_for_fences)(
             self.parent.id(),
             1,
             addr_of!(self.inner).cast(),
             vk::TRUE,
-            timeout,
+            timeout_nanos(timeout),
         ) {
             vk::SUCCESS => Ok(true),
             vk::TIMEOUT => Ok(false),
@@ -125,26 +125,78 @@ impl<D: DeviceRef> Fence<D> {
         };
     }
 
+    /// Returns a future that resolves once the fence is signaled.
+    ///
+    /// Pending fences are waited on by a dedicated thread, which wakes the future once the fence is signaled,
+    /// so awaiting GPU work doesn't block the async runtime's worker threads.
+    #[inline]
+    pub fn wait_async(&self) -> FenceWait<'_, D> {
+        return FenceWait { fence: self, key: None };
+    }
+
     #[inline]
-    pub fn bind_and_wait<'a, P: DeviceRef, S: DeviceRef>(
+    pub fn bind_and_wait<P: DeviceRef>(
         &mut self,
-        pool: &'a mut CommandPool<P>,
+        pool: &mut CommandPool<P>,
         queue: &mut Queue,
-        semaphores: Option<&'a [Semaphore<S>]>,
         timeout: Option<Duration>,
     ) -> Result<bool> {
-        self.bind_to(pool, queue, semaphores)?;
+        self.bind_to(pool, queue)?;
         return self.wait(timeout);
     }
 }
 
 impl<D: DeviceRef> Drop for Fence<D> {
+    /// Stops the fence waiter thread from waiting on the fence. If it's in the middle of a wait including this fence,
+    /// blocks until that wait times out, which takes at most a millisecond per device with pending fences.
     #[inline]
     fn drop(&mut self) {
+        waiter::forget(self.parent.id(), self.id());
         (Entry::get().destroy_fence)(self.parent.id(), self.id(), core::ptr::null())
     }
 }
 
+/// Future returned by [`Fence::wait_async`]
+#[derive(Debug)]
+pub struct FenceWait<'a, D: DeviceRef> {
+    fence: &'a Fence<D>,
+    key: Option<u64>,
+}
+
+impl<D: DeviceRef> Future for FenceWait<'_, D> {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.fence.status() {
+            Ok(true) => return Poll::Ready(Ok(())),
+            Ok(false) => {}
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+
+        let key = match waiter::register(self.key, self.fence.device().id(), self.fence.id(), cx.waker()) {
+            Ok(x) => x,
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        self.key = Some(key);
+
+        // The fence may have been signaled before the registration
+        return match self.fence.status() {
+            Ok(true) => Poll::Ready(Ok(())),
+            Ok(false) => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
+        };
+    }
+}
+
+impl<D: DeviceRef> Drop for FenceWait<'_, D> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            waiter::deregister(self.fence.device().id(), self.fence.id(), key);
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Semaphore<D: DeviceRef> {
     inner: NonZeroU64,
@@ -180,6 +232,25 @@ impl<D: DeviceRef> Semaphore<D> {
     pub fn id(&self) -> u64 {
         return self.inner.get();
     }
+
+    #[inline]
+    pub fn device(&self) -> &Device {
+        return &self.parent;
+    }
+
+    #[inline]
+    pub fn owned_device(&self) -> D
+    where
+        D: Clone,
+    {
+        return self.parent.clone();
+    }
+
+    /// Gives the semaphore a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name(&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_SEMAPHORE, self.id(), name);
+    }
 }
 
 impl<D: DeviceRef> Drop for Semaphore<D> {
@@ -189,9 +260,247 @@ impl<D: DeviceRef> Drop for Semaphore<D> {
     }
 }
 
+/// Synchronization primitive that can be set & reset from both the host and command buffers,
+/// allowing fine-grained dependencies between commands of the same queue.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Event<D: DeviceRef> {
+    inner: NonZeroU64,
+    parent: D,
+}
+
+impl<D: DeviceRef> Event<D> {
+    #[inline]
+    pub fn new(parent: D, flags: EventFlags) -> Result<Self> {
+        let info = vk::EventCreateInfo {
+            sType: vk::STRUCTURE_TYPE_EVENT_CREATE_INFO,
+            pNext: core::ptr::null(),
+            flags: flags.bits(),
+        };
+
+        let mut result = 0;
+        tri! {
+            (Entry::get().create_event)(
+                parent.id(),
+                addr_of!(info),
+                core::ptr::null(),
+                addr_of_mut!(result)
+            )
+        }
+
+        if let Some(inner) = NonZeroU64::new(result) {
+            return Ok(Self { inner, parent });
+        }
+        return Err(vk::ERROR_UNKNOWN.into());
+    }
+
+    #[inline]
+    pub fn id(&self) -> u64 {
+        return self.inner.get();
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Device {
+        return &self.parent;
+    }
+
+    #[inline]
+    pub fn owned_device(&self) -> D
+    where
+        D: Clone,
+    {
+        return self.parent.clone();
+    }
+
+    /// Gives the event a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name(&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_EVENT, self.id(), name);
+    }
+
+    /// Returns `true` if the event is set
+    #[inline]
+    pub fn status(&self) -> Result<bool> {
+        return match (Entry::get().get_event_status)(self.parent.id(), self.id()) {
+            vk::EVENT_SET => Ok(true),
+            vk::EVENT_RESET => Ok(false),
+            e => Err(e.into()),
+        };
+    }
+
+    /// Same as [`status`](Event::status), failing instead of reporting the event as unset if its status can't be retrieved (e.g. on device loss)
+    #[inline]
+    pub fn is_set(&self) -> Result<bool> {
+        return self.status();
+    }
+
+    /// Sets the event from the host
+    #[inline]
+    pub fn set(&self) -> Result<()> {
+        tri! {
+            (Entry::get().set_event)(self.parent.id(), self.id())
+        }
+        return Ok(());
+    }
+
+    /// Resets the event from the host
+    #[inline]
+    pub fn reset(&self) -> Result<()> {
+        tri! {
+            (Entry::get().reset_event)(self.parent.id(), self.id())
+        }
+        return Ok(());
+    }
+}
+
+impl<D: DeviceRef> Drop for Event<D> {
+    #[inline]
+    fn drop(&mut self) {
+        (Entry::get().destroy_event)(self.parent.id(), self.id(), core::ptr::null())
+    }
+}
+
+/// Semaphore holding a monotonically increasing value, which can be waited on and signaled from both the host and the device.
+/// Requires Vulkan 1.2, with the `timelineSemaphore` feature enabled.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct TimelineSemaphore<D: DeviceRef> {
+    inner: NonZeroU64,
+    parent: D,
+}
+
+impl<D: DeviceRef> TimelineSemaphore<D> {
+    #[inline]
+    pub fn new(parent: D, initial_value: u64) -> Result<Self> {
+        let timeline = vk::SemaphoreTypeCreateInfo {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_TYPE_CREATE_INFO,
+            pNext: core::ptr::null(),
+            semaphoreType: vk::SEMAPHORE_TYPE_TIMELINE,
+            initialValue: initial_value,
+        };
+
+        let info = vk::SemaphoreCreateInfo {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+            pNext: addr_of!(timeline).cast(),
+            flags: 0,
+        };
+
+        let mut result = 0;
+        tri! {
+            (Entry::get().create_semaphore)(
+                parent.id(),
+                addr_of!(info),
+                core::ptr::null(),
+                addr_of_mut!(result)
+            )
+        }
+
+        if let Some(inner) = NonZeroU64::new(result) {
+            return Ok(Self { inner, parent });
+        }
+        return Err(vk::ERROR_UNKNOWN.into());
+    }
+
+    #[inline]
+    pub fn id(&self) -> u64 {
+        return self.inner.get();
+    }
+
+    #[inline]
+    pub fn device(&self) -> &Device {
+        return &self.parent;
+    }
+
+    #[inline]
+    pub fn owned_device(&self) -> D
+    where
+        D: Clone,
+    {
+        return self.parent.clone();
+    }
+
+    /// Gives the semaphore a debug name. Does nothing if `VK_EXT_debug_utils` isn't enabled.
+    #[inline]
+    pub fn set_name(&self, name: &CStr) -> Result<()> {
+        return crate::debug::set_name(self.device(), vk::OBJECT_TYPE_SEMAPHORE, self.id(), name);
+    }
+
+    /// Returns the current value of the semaphore
+    #[inline]
+    pub fn value(&self) -> Result<u64> {
+        let mut value = 0;
+        tri! {
+            (Entry::get().get_semaphore_counter_value)(
+                self.parent.id(),
+                self.id(),
+                addr_of_mut!(value)
+            )
+        }
+        return Ok(value);
+    }
+
+    /// Sets the value of the semaphore from the host. `value` must be greater than the current value.
+    #[inline]
+    pub fn signal(&self, value: u64) -> Result<()> {
+        let info = vk::SemaphoreSignalInfo {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_SIGNAL_INFO,
+            pNext: core::ptr::null(),
+            semaphore: self.id(),
+            value,
+        };
+
+        tri! {
+            (Entry::get().signal_semaphore)(self.parent.id(), addr_of!(info))
+        }
+        return Ok(());
+    }
+
+    /// Blocks the current thread until the semaphore reaches `value`, or the timeout expires.
+    /// Returns `false` if the timeout expired.
+    #[inline]
+    pub fn wait(&self, value: u64, timeout: Option<Duration>) -> Result<bool> {
+        let info = vk::SemaphoreWaitInfo {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_WAIT_INFO,
+            pNext: core::ptr::null(),
+            flags: 0,
+            semaphoreCount: 1,
+            pSemaphores: addr_of!(self.inner).cast(),
+            pValues: addr_of!(value),
+        };
+
+        return match (Entry::get().wait_semaphores)(self.parent.id(), addr_of!(info), timeout_nanos(timeout)) {
+            vk::SUCCESS => Ok(true),
+            vk::TIMEOUT => Ok(false),
+            e => Err(e.into()),
+        };
+    }
+}
+
+impl<D: DeviceRef> Drop for TimelineSemaphore<D> {
+    #[inline]
+    fn drop(&mut self) {
+        (Entry::get().destroy_semaphore)(self.parent.id(), self.id(), core::ptr::null())
+    }
+}
+
+#[inline]
+fn timeout_nanos(timeout: Option<Duration>) -> u64 {
+    return match timeout {
+        #[cfg(debug_assertions)]
+        Some(x) => u64::try_from(x.as_nanos()).unwrap(),
+        #[cfg(not(debug_assertions))]
+        Some(x) => x.as_nanos() as u64,
+        None => u64::MAX,
+    };
+}
+
 bitflags::bitflags! {
     #[repr(transparent)]
     pub struct FenceFlags: vk::FenceCreateFlagBits {
         const SIGNALED = vk::FENCE_CREATE_SIGNALED_BIT;
     }
+
+    #[repr(transparent)]
+    pub struct EventFlags: vk::EventCreateFlagBits {
+        /// Event can only be set & waited on by the device
+        const DEVICE_ONLY = vk::EVENT_CREATE_DEVICE_ONLY_BIT;
+    }
 }
@@ -0,0 +1,157 @@
+use crate::{utils::usize_to_u32, Entry};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::{Condvar, Mutex, MutexGuard},
+    task::Waker,
+};
+
+/// Longest a wait on the pending fences lasts, in nanoseconds, before the waiter picks up the fences registered meanwhile.
+/// Also bounds how long dropping a fence blocks while the waiter is using it.
+const WAIT_TIMEOUT: u64 = 1_000_000;
+
+static WAITER: Lazy<Waiter> = Lazy::new(|| Waiter {
+    state: Mutex::new(State::default()),
+    changed: Condvar::new(),
+});
+
+/// Waits on every fence awaited through [`Fence::wait_async`](super::Fence::wait_async),
+/// from a single thread that waits on all the pending fences of each device at once, with a timeout,
+/// and wakes the futures of the fences that got signaled.
+struct Waiter {
+    state: Mutex<State>,
+    /// Notified when fences are registered, and when the thread stops using the fences of its last wait
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    /// Registered wakers, by device & fence. A fence is removed once signaled, or once it has no wakers left.
+    fences: HashMap<(vk::Device, vk::Fence), Vec<WaitEntry>>,
+    /// Fences the thread is waiting on, which mustn't be destroyed until it's done
+    waiting: Vec<(vk::Device, vk::Fence)>,
+    running: bool,
+    next_key: u64,
+}
+
+struct WaitEntry {
+    key: u64,
+    waker: Waker,
+}
+
+impl Waiter {
+    fn run(&self) {
+        let entry = Entry::get();
+        let mut state = self.lock();
+
+        loop {
+            if state.fences.is_empty() {
+                state = match self.changed.wait(state) {
+                    Ok(x) => x,
+                    Err(e) => e.into_inner(),
+                };
+                continue;
+            }
+
+            let mut pending = state.fences.keys().copied().collect::<Vec<_>>();
+            pending.sort_unstable();
+            state.waiting.clone_from(&pending);
+            drop(state);
+
+            // Fences are sorted by device, so each device's fences are waited on by a single call
+            let mut rest = &pending[..];
+            while let Some(&(device, _)) = rest.first() {
+                let len = rest.iter().take_while(|x| x.0 == device).count();
+                let fences = rest[..len].iter().map(|x| x.1).collect::<Vec<_>>();
+                // Errors (such as a device loss) are reported by the fences' status, checked below
+                let _ = (entry.wait_for_fences)(device, usize_to_u32(len), fences.as_ptr(), vk::FALSE, WAIT_TIMEOUT);
+                rest = &rest[len..];
+            }
+
+            // Signaled or device lost, either way the futures have to be polled again
+            pending.retain(|(device, fence)| (entry.get_fence_status)(*device, *fence) != vk::NOT_READY);
+
+            state = self.lock();
+            state.waiting.clear();
+            self.changed.notify_all();
+
+            let wakers = pending.iter()
+                .filter_map(|x| state.fences.remove(x))
+                .flatten()
+                .collect::<Vec<_>>();
+
+            if !wakers.is_empty() {
+                drop(state);
+                wakers.into_iter().for_each(|x| x.waker.wake());
+                state = self.lock();
+            }
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, State> {
+        return match self.state.lock() {
+            Ok(x) => x,
+            Err(e) => e.into_inner(),
+        };
+    }
+}
+
+/// Registers `fence` to wake `waker` once signaled, updating the previous registration if `key` is provided.
+/// Returns the key of the registration.
+pub(super) fn register(key: Option<u64>, device: vk::Device, fence: vk::Fence, waker: &Waker) -> crate::Result<u64> {
+    let waiter = &*WAITER;
+    let mut state = waiter.lock();
+    let state = &mut *state;
+
+    if !state.running {
+        std::thread::Builder::new()
+            .name("vulkan-fence-waiter".to_string())
+            .spawn(|| WAITER.run())?;
+        state.running = true;
+    }
+
+    let key = key.unwrap_or_else(|| {
+        state.next_key += 1;
+        state.next_key
+    });
+
+    let entries = state.fences.entry((device, fence)).or_default();
+    match entries.iter_mut().find(|x| x.key == key) {
+        Some(entry) if !entry.waker.will_wake(waker) => entry.waker = waker.clone(),
+        Some(_) => {}
+        None => entries.push(WaitEntry {
+            key,
+            waker: waker.clone(),
+        }),
+    }
+
+    waiter.changed.notify_all();
+    return Ok(key);
+}
+
+/// Removes the registration of `key`, and stops waiting on `fence` if it was the last one
+pub(super) fn deregister(device: vk::Device, fence: vk::Fence, key: u64) {
+    let mut state = WAITER.lock();
+    if let Some(entries) = state.fences.get_mut(&(device, fence)) {
+        entries.retain(|x| x.key != key);
+        if entries.is_empty() {
+            state.fences.remove(&(device, fence));
+        }
+    }
+}
+
+/// Stops waiting on `fence`, so that it can be destroyed.
+/// If the thread is currently waiting on it, blocks until that wait times out, which takes at most [`WAIT_TIMEOUT`] per device.
+pub(super) fn forget(device: vk::Device, fence: vk::Fence) {
+    let waiter = &*WAITER;
+    let mut state = waiter.lock();
+    state.fences.remove(&(device, fence));
+
+    while state.waiting.contains(&(device, fence)) {
+        state = match waiter.changed.wait(state) {
+            Ok(x) => x,
+            Err(e) => e.into_inner(),
+        };
+    }
+}
@@ -111,12 +111,19 @@ pub async fn read_spv_tokio<R: ?Sized + Unpin + tokio::io::AsyncRead> (r: &mut R
     read_spv_async!(|b| tokio::io::AsyncReadExt::read(r, &mut b), u32::from_ne_bytes)
 }
 
+/// Fixed-capacity list that can be pushed to concurrently
 pub struct UpQueue<T> {
     ptr: NonNull<T>,
+    /// Number of slots claimed by pushers, which may still be writing to them
+    reserved: AtomicUsize,
+    /// Number of initialized elements, published in order
     len: AtomicUsize,
     capacity: usize
 }
 
+unsafe impl<T: Send> Send for UpQueue<T> {}
+unsafe impl<T: Send + Sync> Sync for UpQueue<T> {}
+
 impl<T> UpQueue<T> {
     #[inline]
     pub fn new (capacity: usize) -> Self {
@@ -126,6 +133,7 @@ impl<T> UpQueue<T> {
         
         return Self {
             ptr: unsafe { NonNull::new_unchecked(ptr.cast()) },
+            reserved: AtomicUsize::new(0),
             len: AtomicUsize::new(0),
             capacity
         }
@@ -133,7 +141,7 @@ impl<T> UpQueue<T> {
     
     #[inline]
     pub fn len (&self) -> usize {
-        return self.len.load(Ordering::Relaxed)
+        return self.len.load(Ordering::Acquire)
     }
 
     #[inline]
@@ -169,16 +177,21 @@ impl<T> UpQueue<T> {
 
     #[inline]
     pub fn try_push<'a> (&'a self, v: T) -> Result<(usize, &'a T), T> {
-        let idx = self.len.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
+        let idx = self.reserved.fetch_add(1, Ordering::AcqRel);
         if idx >= self.capacity {
-            self.len.store(self.capacity, std::sync::atomic::Ordering::Relaxed);
+            self.reserved.store(self.capacity, Ordering::Relaxed);
             return Err(v)
         }
 
         unsafe {
             let ptr = self.ptr.as_ptr().add(idx);
             ptr.write(v);
-            return Ok((idx, &mut *ptr))
+
+            // Publish the element once every previous one has been, so readers never see a slot that's still being written
+            while self.len.compare_exchange_weak(idx, idx + 1, Ordering::Release, Ordering::Relaxed).is_err() {
+                core::hint::spin_loop()
+            }
+            return Ok((idx, &*ptr))
         }
     }
 
@@ -188,6 +201,7 @@ impl<T> UpQueue<T> {
         let idx = *len;
         if idx >= self.capacity { return Err(v) }
         *len += 1;
+        *self.reserved.get_mut() = *len;
 
         unsafe {
             let ptr = self.ptr.as_ptr().add(idx);
@@ -213,6 +227,17 @@ impl<T> UpQueue<T> {
     }
 }
 
+impl<T> Drop for UpQueue<T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let len = *self.len.get_mut();
+            core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.ptr.as_ptr(), len));
+            std::alloc::dealloc(self.ptr.as_ptr().cast(), Layout::array::<T>(self.capacity).unwrap());
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Iter<'a, T> {
     parent: &'a UpQueue<T>,
@@ -0,0 +1,418 @@
+use std::{sync::Barrier, thread, time::Duration};
+use futures::executor::block_on;
+use common::{op, Op};
+use proptest::prelude::*;
+use vulkan::{
+    alloc::{align_to_atom, select_memory_types, Book, Dedicated, HostMemory, MemoryFlags, MemoryRequirements, MemoryUsage, Page},
+    error::Error,
+};
+use vulkan_bindings::ERROR_OUT_OF_DEVICE_MEMORY;
+
+mod common;
+
+const PAGE_SIZE: u64 = 1 << 20;
+/// Index of the plain host-visible memory type in [`HostMemory::DISCRETE_TYPES`]
+const HOST_VISIBLE_TYPE: u32 = 1;
+
+/// Usage that always sub-allocates from pages
+fn paged(flags: MemoryFlags) -> MemoryUsage {
+    MemoryUsage::new(flags).dedicated(Dedicated::Never)
+}
+
+/// Fills an allocation with its tag, so overlapping allocations overwrite each other's tags
+unsafe fn fill(bytes: *mut [u8], tag: u8) {
+    (*bytes).fill(tag);
+}
+
+unsafe fn check(bytes: *mut [u8], tag: u8) -> bool {
+    (*bytes).iter().all(|x| *x == tag)
+}
+
+proptest! {
+    #[test]
+    fn page_allocations_are_aligned_and_disjoint(ops in prop::collection::vec(op(16 * 1024, 12), 1..256)) {
+        let backend = HostMemory::new();
+        let page = Page::with_backend(&backend, PAGE_SIZE, HOST_VISIBLE_TYPE).unwrap();
+        let base = page.id();
+        let mut live = Vec::new();
+
+        for (i, op) in ops.into_iter().enumerate() {
+            match op {
+                Op::Allocate { size, align } => {
+                    if let Ok(ptr) = page.allocate_block(size, align) {
+                        let range = ptr.range();
+                        prop_assert_eq!(range.end - range.start, size);
+                        prop_assert_eq!((base + range.start) % align, 0);
+                        prop_assert!(range.end <= PAGE_SIZE);
+
+                        let bytes = unsafe { page.map_block(&ptr, ..) }.unwrap();
+                        unsafe { fill(bytes.as_ptr(), i as u8) };
+                        live.push((ptr, bytes, i as u8));
+                    }
+                }
+                Op::Free(idx) if !live.is_empty() => {
+                    let (ptr, bytes, tag) = live.swap_remove(idx % live.len());
+                    prop_assert!(unsafe { check(bytes.as_ptr(), tag) }, "allocation {:?} was overwritten", ptr.range());
+                    unsafe { page.free_block(ptr) };
+                }
+                Op::Free(_) => {}
+            }
+        }
+
+        for (ptr, bytes, tag) in live {
+            prop_assert!(unsafe { check(bytes.as_ptr(), tag) }, "allocation {:?} was overwritten", ptr.range());
+            unsafe { page.free_block(ptr) };
+        }
+
+        prop_assert_eq!(page.metrics().used, 0);
+        prop_assert_eq!(page.metrics().largest_free_block, PAGE_SIZE);
+    }
+
+    #[test]
+    fn book_grows_and_coalesces(sizes in prop::collection::vec((1u64..=PAGE_SIZE, any::<bool>()), 1..64)) {
+        let backend = HostMemory::new();
+        let book = Book::with_backend(&backend, PAGE_SIZE..4 * PAGE_SIZE, 64);
+
+        let ptrs = sizes.iter()
+            .map(|(size, visible)| {
+                let flags = match visible {
+                    true => MemoryFlags::HOST_VISIBLE,
+                    false => MemoryFlags::DEVICE_LOCAL,
+                };
+                book.allocate_block(&MemoryRequirements::new(*size, 1), &paged(flags)).map(|x| (x, flags))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let pages = book.pages().map(|x| x.size()).sum::<u64>();
+        prop_assert_eq!(backend.allocated(), pages);
+        prop_assert_eq!(book.pages().map(|x| x.metrics().requested).sum::<u64>(), sizes.iter().map(|(x, _)| *x).sum::<u64>());
+
+        for (ptr, _) in ptrs {
+            unsafe { book.free_block(ptr) };
+        }
+
+        for page in book.pages() {
+            prop_assert_eq!(page.metrics().used, 0);
+            prop_assert_eq!(page.metrics().fragmentation(), 0.0);
+        }
+    }
+
+    #[test]
+    fn selected_types_are_allowed_and_complete(
+        types in prop::collection::vec(any::<u32>().prop_map(MemoryFlags::from_bits_truncate), 0..32),
+        type_bits in any::<u32>(),
+        required in any::<u32>().prop_map(MemoryFlags::from_bits_truncate),
+        preferred in any::<u32>().prop_map(MemoryFlags::from_bits_truncate),
+    ) {
+        let usage = MemoryUsage::new(required).prefer(preferred).or(MemoryFlags::empty());
+        let selected = select_memory_types(&types, type_bits, &usage);
+
+        // Every allowed type satisfies the fallback, so each is tried exactly once
+        let allowed = (0..types.len() as u32).filter(|i| type_bits & (1 << i) != 0).count();
+        prop_assert_eq!(selected.len(), allowed);
+
+        let mut seen = 0u32;
+        for i in &selected {
+            prop_assert!(type_bits & (1 << i) != 0);
+            prop_assert_eq!(seen & (1 << i), 0);
+            seen |= 1 << i;
+        }
+
+        // Types with the required flags come first, ranked by how many of the preferred flags they have
+        let first = selected.iter().take_while(|i| types[**i as usize].contains(required)).count();
+        prop_assert!(selected[first..].iter().all(|i| !types[*i as usize].contains(required)));
+
+        let matched = selected[..first].iter().map(|i| (types[*i as usize] & preferred).bits().count_ones()).collect::<Vec<_>>();
+        prop_assert!(matched.windows(2).all(|x| x[0] >= x[1]));
+    }
+
+    #[test]
+    fn atom_aligned_ranges_cover_the_original(
+        start in 0u64..1 << 20,
+        len in 0u64..1 << 16,
+        slack in 0u64..1 << 16,
+        atom in prop::sample::select(vec![1u64, 4, 64, 256, 1024]),
+    ) {
+        let size = start + len + slack;
+        let aligned = align_to_atom(start..start + len, atom, size);
+
+        prop_assert!(aligned.start <= start && aligned.end >= start + len);
+        prop_assert!(aligned.end <= size);
+        prop_assert_eq!(aligned.start % atom, 0);
+        prop_assert!(aligned.end % atom == 0 || aligned.end == size);
+        // Widened by less than an atom on each side
+        prop_assert!(start - aligned.start < atom && aligned.end - (start + len) < atom);
+    }
+}
+
+#[test]
+fn book_reports_exhausted_backend() {
+    let backend = HostMemory::with_limit(2 * PAGE_SIZE);
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 8);
+
+    let requirements = MemoryRequirements::new(PAGE_SIZE, 1);
+    let usage = paged(MemoryFlags::DEVICE_LOCAL);
+
+    let first = book.allocate_block(&requirements, &usage).unwrap();
+    let second = book.allocate_block(&requirements, &usage).unwrap();
+    assert!(book.allocate_block(&requirements, &usage).is_err());
+
+    unsafe { book.free_block(first) };
+    let third = book.allocate_block(&requirements, &usage).unwrap();
+
+    unsafe {
+        book.free_block(second);
+        book.free_block(third);
+    }
+}
+
+#[test]
+fn book_concurrent_access() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 256;
+
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 64);
+    let barrier = Barrier::new(THREADS);
+
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let (book, barrier) = (&book, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                let mut live = Vec::new();
+
+                for i in 0..ROUNDS {
+                    let size = 1 + ((t * 7919 + i * 104729) % (32 * 1024)) as u64;
+                    let ptr = book.allocate_block(&MemoryRequirements::new(size, 256), &MemoryUsage::upload()).unwrap();
+                    let bytes = unsafe { book.map_block(&ptr, ..) }.unwrap();
+                    let tag = (t * ROUNDS + i) as u8;
+                    unsafe { fill(bytes.as_ptr(), tag) };
+                    live.push((ptr, bytes, tag));
+
+                    // Keep a few allocations alive, so threads interleave allocations & frees
+                    if live.len() > 4 {
+                        let (ptr, bytes, tag) = live.remove(i % live.len());
+                        assert!(unsafe { check(bytes.as_ptr(), tag) }, "allocation {:?} was overwritten", ptr.range());
+                        unsafe { book.free_block(ptr) };
+                    }
+                }
+
+                for (ptr, bytes, tag) in live {
+                    assert!(unsafe { check(bytes.as_ptr(), tag) }, "allocation {:?} was overwritten", ptr.range());
+                    unsafe { book.free_block(ptr) };
+                }
+            });
+        }
+    });
+
+    for page in book.pages() {
+        assert_eq!(page.metrics().used, 0);
+    }
+}
+
+#[test]
+fn memory_types_follow_usage() {
+    let types = HostMemory::DISCRETE_TYPES;
+    assert_eq!(select_memory_types(types, u32::MAX, &MemoryUsage::device_local()), [0, 3, 1, 2]);
+    assert_eq!(select_memory_types(types, u32::MAX, &MemoryUsage::upload()), [3, 1, 2]);
+    assert_eq!(select_memory_types(types, u32::MAX, &MemoryUsage::readback()), [2, 1, 3]);
+
+    // Types the resource can't be bound to are skipped, even if they're preferred
+    assert_eq!(select_memory_types(types, 0b0110, &MemoryUsage::device_local()), [1, 2]);
+    assert_eq!(select_memory_types(types, 0b0111, &MemoryUsage::upload()), [1, 2]);
+}
+
+#[test]
+fn book_honors_type_bits() {
+    const TYPES: &[MemoryFlags] = &[MemoryFlags::DEVICE_LOCAL, MemoryFlags::HOST_VISIBLE];
+    let backend = HostMemory::new().with_types(TYPES);
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 8);
+
+    let requirements = MemoryRequirements { type_bits: 0b10, ..MemoryRequirements::new(1024, 1) };
+    let ptr = book.allocate_block(&requirements, &MemoryUsage::device_local()).unwrap();
+    assert!(book.pages().all(|x| x.memory_type() == 1));
+
+    // Without a fallback, no memory type fits
+    let result = book.allocate_block(&requirements, &paged(MemoryFlags::DEVICE_LOCAL));
+    assert!(matches!(result, Err(Error::NoMemoryType(_))));
+
+    unsafe { book.free_block(ptr) };
+}
+
+#[test]
+fn large_allocations_are_dedicated() {
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..4 * PAGE_SIZE, 8);
+
+    let small = book.allocate_block(&MemoryRequirements::new(1024, 1), &MemoryUsage::upload()).unwrap();
+    let large = book.allocate_block(&MemoryRequirements::new(3 * PAGE_SIZE, 1), &MemoryUsage::upload()).unwrap();
+    let forced = book
+        .allocate_block(&MemoryRequirements::new(1024, 1), &MemoryUsage::upload().dedicated(Dedicated::Always))
+        .unwrap();
+
+    assert!(!small.metadata().is_dedicated());
+    assert!(large.metadata().is_dedicated());
+    assert!(forced.metadata().is_dedicated());
+    assert_eq!(book.pages().count(), 1);
+    assert_eq!(backend.allocated(), PAGE_SIZE + 3 * PAGE_SIZE + 1024);
+
+    // Dedicated blocks are mapped for as long as they live
+    let bytes = unsafe { book.map_block(&large, ..) }.unwrap();
+    assert_eq!(bytes.len() as u64, 3 * PAGE_SIZE);
+    unsafe {
+        fill(bytes.as_ptr(), 7);
+        assert!(check(bytes.as_ptr(), 7));
+    }
+
+    unsafe {
+        book.free_block(large);
+        book.free_block(forced);
+        book.free_block(small);
+    }
+    assert_eq!(backend.allocated(), PAGE_SIZE);
+}
+
+#[test]
+fn only_non_coherent_memory_is_flushed() {
+    const TYPES: &[MemoryFlags] = &[
+        MemoryFlags::HOST_VISIBLE.union(MemoryFlags::HOST_CACHED),
+        MemoryFlags::HOST_VISIBLE.union(MemoryFlags::HOST_COHERENT),
+    ];
+
+    let backend = HostMemory::new().with_types(TYPES);
+    let book = Book::with_backend(&backend, PAGE_SIZE..4 * PAGE_SIZE, 8);
+    let cached = MemoryUsage::readback();
+    let coherent = MemoryUsage::new(MemoryFlags::HOST_VISIBLE | MemoryFlags::HOST_COHERENT);
+
+    // Odd sizes & ranges, so that nothing is aligned to the atom size unless the book aligns it
+    let small = book.allocate_block(&MemoryRequirements::new(1000, 1), &cached).unwrap();
+    let other = book.allocate_block(&MemoryRequirements::new(333, 1), &cached).unwrap();
+    let large = book.allocate_block(&MemoryRequirements::new(PAGE_SIZE + 17, 1), &cached).unwrap();
+    let plain = book.allocate_block(&MemoryRequirements::new(1000, 1), &coherent).unwrap();
+    assert!(large.metadata().is_dedicated());
+
+    unsafe {
+        book.flush_block(&small, 3..997).unwrap();
+        book.invalidate_block(&other, 1..333).unwrap();
+        book.flush_block(&large, 5..(PAGE_SIZE + 17) as usize).unwrap();
+        book.invalidate_block(&large, 0..1).unwrap();
+        // Empty ranges have nothing to flush
+        book.flush_block(&small, 10..10).unwrap();
+
+        book.flush_block(&plain, 3..997).unwrap();
+        book.invalidate_block(&plain, 0..1000).unwrap();
+    }
+
+    assert_eq!(backend.flushes(), 2);
+    assert_eq!(backend.invalidations(), 2);
+
+    unsafe {
+        book.free_block(small);
+        book.free_block(other);
+        book.free_block(large);
+        book.free_block(plain);
+    }
+}
+
+#[test]
+fn book_stats_match_backend() {
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..4 * PAGE_SIZE, 8);
+
+    let small = book.allocate_block(&MemoryRequirements::new(1000, 1), &MemoryUsage::upload()).unwrap();
+    let other = book.allocate_block(&MemoryRequirements::new(3000, 1), &MemoryUsage::upload()).unwrap();
+    let large = book.allocate_block(&MemoryRequirements::new(3 * PAGE_SIZE, 1), &MemoryUsage::upload()).unwrap();
+
+    let stats = book.stats();
+    assert_eq!(stats.allocations, 3);
+    assert_eq!(stats.pages, 1);
+    assert_eq!(stats.page_bytes, PAGE_SIZE);
+    assert_eq!(stats.requested, 4000);
+    assert!(stats.used >= stats.requested);
+    assert_eq!((stats.dedicated, stats.dedicated_bytes), (1, 3 * PAGE_SIZE));
+    assert_eq!(stats.allocated(), backend.allocated());
+
+    unsafe {
+        book.free_block(small);
+        book.free_block(large);
+    }
+
+    let stats = book.stats();
+    assert_eq!(stats.allocations, 1);
+    assert_eq!(stats.dedicated_bytes, 0);
+    assert_eq!(stats.requested, 3000);
+    assert_eq!(stats.allocated(), backend.allocated());
+
+    unsafe { book.free_block(other) };
+    assert_eq!(book.stats().allocations, 0);
+    assert_eq!(book.stats().fragmentation, 0.0);
+}
+
+#[test]
+fn book_out_of_pages_fails() {
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 2);
+
+    let requirements = MemoryRequirements::new(PAGE_SIZE, 1);
+    let usage = paged(MemoryFlags::HOST_VISIBLE);
+
+    let first = book.allocate_block(&requirements, &usage).unwrap();
+    let second = book.allocate_block(&requirements, &usage).unwrap();
+    let result = book.allocate_block(&requirements, &usage);
+    assert!(matches!(result, Err(Error::Vulkan(ERROR_OUT_OF_DEVICE_MEMORY))));
+    assert_eq!(backend.allocated(), 2 * PAGE_SIZE);
+
+    unsafe {
+        book.free_block(first);
+        book.free_block(second);
+    }
+}
+
+#[test]
+fn book_async_allocation_waits_for_frees() {
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 1);
+
+    let requirements = MemoryRequirements::new(PAGE_SIZE, 1);
+    let usage = paged(MemoryFlags::HOST_VISIBLE);
+
+    let first = block_on(book.allocate_block_async(&requirements, &usage)).unwrap();
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            unsafe { book.free_block(first) };
+        });
+
+        let second = block_on(book.allocate_block_async(&requirements, &usage)).unwrap();
+        assert_eq!(book.stats().allocations, 1);
+        unsafe { book.free_block(second) };
+    });
+
+    // Nothing would ever be freed, so there's nothing to wait for
+    let empty = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 1);
+    let result = block_on(empty.allocate_block_async(&MemoryRequirements::new(2 * PAGE_SIZE, 1), &usage));
+    assert!(result.is_err());
+}
+
+#[test]
+fn book_async_allocation_fails_if_no_free_helps() {
+    let backend = HostMemory::new();
+    let book = Book::with_backend(&backend, PAGE_SIZE..PAGE_SIZE, 1);
+    let requirements = MemoryRequirements::new(PAGE_SIZE, 1);
+
+    // Takes the only page slot with a page of another memory type, which stays once empty
+    let other = book.allocate_block(&requirements, &paged(MemoryFlags::DEVICE_LOCAL)).unwrap();
+    unsafe { book.free_block(other) };
+
+    // Freeing the dedicated allocation wouldn't free the page slot, so there's nothing to wait for
+    let dedicated = MemoryUsage::new(MemoryFlags::DEVICE_LOCAL).dedicated(Dedicated::Always);
+    let live = book.allocate_block(&requirements, &dedicated).unwrap();
+
+    let result = block_on(book.allocate_block_async(&requirements, &paged(MemoryFlags::HOST_VISIBLE)));
+    assert!(matches!(result, Err(Error::Vulkan(ERROR_OUT_OF_DEVICE_MEMORY))));
+
+    unsafe { book.free_block(live) };
+}
@@ -0,0 +1,170 @@
+use std::alloc::Layout;
+use common::{op, Op};
+use proptest::prelude::*;
+use vulkan::alloc::{Buddy, BuddyBlock};
+
+mod common;
+
+const MIN_BLOCK: u64 = 256;
+/// Alignment of [`MockMemory`], as large as the largest alignment [`op`] asks for
+const MOCK_ALIGN: usize = 1 << 14;
+
+/// Host memory standing in for the device memory a page sub-allocates from
+struct MockMemory {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+impl MockMemory {
+    fn new(size: u64) -> Self {
+        let layout = Layout::from_size_align(size as usize, MOCK_ALIGN).unwrap();
+        let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+        assert!(!ptr.is_null());
+        Self { ptr, layout }
+    }
+
+    fn block(&mut self, block: &BuddyBlock, size: u64) -> &mut [u8] {
+        assert!(block.offset + size <= self.layout.size() as u64);
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.add(block.offset as usize), size as usize) }
+    }
+}
+
+impl Drop for MockMemory {
+    fn drop(&mut self) {
+        unsafe { std::alloc::dealloc(self.ptr, self.layout) }
+    }
+}
+
+fn block_size(block: &BuddyBlock) -> u64 {
+    MIN_BLOCK << block.order
+}
+
+proptest! {
+    #[test]
+    fn blocks_are_aligned_disjoint_and_in_bounds(size in MIN_BLOCK..=4 << 20, ops in prop::collection::vec(op(64 * 1024, 14), 1..256)) {
+        let mut buddy = Buddy::new(size, MIN_BLOCK);
+        let mut live = Vec::<(BuddyBlock, u64)>::new();
+
+        for op in ops {
+            match op {
+                Op::Allocate { size, align } => {
+                    let expected = Buddy::block_size(size, align, MIN_BLOCK).unwrap();
+                    let fits = buddy.largest_free_block() >= expected;
+
+                    match buddy.allocate(size, align) {
+                        Some(block) => {
+                            prop_assert!(fits);
+                            prop_assert_eq!(block_size(&block), expected);
+                            prop_assert_eq!(block.offset % align, 0);
+                            prop_assert!(block.offset + size <= buddy.size());
+
+                            for (other, _) in live.iter() {
+                                let disjoint = block.offset + block_size(&block) <= other.offset
+                                    || other.offset + block_size(other) <= block.offset;
+                                prop_assert!(disjoint, "{:?} overlaps {:?}", block, other);
+                            }
+                            live.push((block, size));
+                        }
+                        None => prop_assert!(!fits),
+                    }
+                }
+                Op::Free(idx) if !live.is_empty() => {
+                    let (block, size) = live.swap_remove(idx % live.len());
+                    buddy.free(block, size);
+                }
+                Op::Free(_) => {}
+            }
+
+            let metrics = buddy.metrics();
+            prop_assert_eq!(metrics.used, live.iter().map(|(x, _)| block_size(x)).sum::<u64>());
+            prop_assert_eq!(metrics.requested, live.iter().map(|(_, x)| *x).sum::<u64>());
+            prop_assert!(metrics.largest_free_block <= metrics.free());
+            prop_assert!((0.0..=1.0).contains(&metrics.fragmentation()));
+        }
+    }
+
+    #[test]
+    fn freeing_everything_coalesces(size in MIN_BLOCK..=4 << 20, sizes in prop::collection::vec(1u64..=64 * 1024, 1..128), seed in any::<u64>()) {
+        let mut buddy = Buddy::new(size, MIN_BLOCK);
+        let initial = buddy.metrics();
+
+        let mut live = sizes.into_iter()
+            .filter_map(|size| buddy.allocate(size, 1).map(|x| (x, size)))
+            .collect::<Vec<_>>();
+
+        // Free in a scrambled order, so blocks are merged with buddies freed before & after them
+        let mut state = seed;
+        while !live.is_empty() {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let (block, size) = live.swap_remove((state >> 33) as usize % live.len());
+            buddy.free(block, size);
+        }
+
+        prop_assert_eq!(buddy.metrics(), initial);
+        prop_assert_eq!(buddy.metrics().fragmentation(), 0.0);
+    }
+}
+
+proptest! {
+    #[test]
+    fn blocks_keep_their_contents_in_mock_memory(ops in prop::collection::vec(op(64 * 1024, 14), 1..256)) {
+        const SIZE: u64 = 1 << 20;
+        let mut memory = MockMemory::new(SIZE);
+        let mut buddy = Buddy::new(SIZE, MIN_BLOCK);
+        let mut live = Vec::<(BuddyBlock, u64, u8)>::new();
+
+        for (i, op) in ops.into_iter().enumerate() {
+            match op {
+                Op::Allocate { size, align } => if let Some(block) = buddy.allocate(size, align) {
+                    // Aligned in the backing memory, not just as an offset
+                    let bytes = memory.block(&block, size);
+                    prop_assert_eq!(bytes.as_ptr() as u64 % align, 0);
+
+                    // Overlapping blocks would overwrite each other's tags
+                    let tag = i as u8;
+                    bytes.fill(tag);
+                    live.push((block, size, tag));
+                },
+                Op::Free(idx) if !live.is_empty() => {
+                    let (block, size, tag) = live.swap_remove(idx % live.len());
+                    prop_assert!(memory.block(&block, size).iter().all(|x| *x == tag));
+                    buddy.free(block, size);
+                }
+                Op::Free(_) => {}
+            }
+        }
+
+        for (block, size, tag) in live {
+            prop_assert!(memory.block(&block, size).iter().all(|x| *x == tag));
+        }
+    }
+}
+
+#[test]
+fn splits_and_merges_buddies() {
+    let mut buddy = Buddy::new(4 * MIN_BLOCK, MIN_BLOCK);
+    let a = buddy.allocate(MIN_BLOCK, 1).unwrap();
+    let b = buddy.allocate(MIN_BLOCK, 1).unwrap();
+    assert_eq!((a.offset, b.offset), (0, MIN_BLOCK));
+    assert_eq!(buddy.largest_free_block(), 2 * MIN_BLOCK);
+
+    buddy.free(a, MIN_BLOCK);
+    assert_eq!(buddy.largest_free_block(), 2 * MIN_BLOCK);
+    assert_eq!(buddy.metrics().fragmentation(), 1.0 - 2.0 / 3.0);
+
+    buddy.free(b, MIN_BLOCK);
+    assert_eq!(buddy.largest_free_block(), 4 * MIN_BLOCK);
+    assert_eq!(buddy.metrics().free_blocks, 1);
+}
+
+#[test]
+fn non_power_of_two_sizes() {
+    let mut buddy = Buddy::new(3 * MIN_BLOCK + 17, MIN_BLOCK);
+    assert_eq!(buddy.size(), 3 * MIN_BLOCK);
+    assert_eq!(buddy.largest_free_block(), 2 * MIN_BLOCK);
+    assert!(buddy.allocate(3 * MIN_BLOCK, 1).is_none());
+
+    let block = buddy.allocate(2 * MIN_BLOCK, 2 * MIN_BLOCK).unwrap();
+    assert_eq!(block.offset, 0);
+    assert_eq!(buddy.allocate(MIN_BLOCK, 1).unwrap().offset, 2 * MIN_BLOCK);
+}
@@ -0,0 +1,16 @@
+use proptest::prelude::*;
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    Allocate { size: u64, align: u64 },
+    /// Frees the live allocation at this index (modulo their count)
+    Free(usize),
+}
+
+/// Allocations of up to `max_size` bytes, aligned to up to `1 << max_shift`, mixed with frees
+pub fn op(max_size: u64, max_shift: u32) -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (1u64..=max_size, 0u32..=max_shift).prop_map(|(size, shift)| Op::Allocate { size, align: 1 << shift }),
+        2 => any::<usize>().prop_map(Op::Free),
+    ]
+}